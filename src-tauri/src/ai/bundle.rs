@@ -0,0 +1,379 @@
+// NixDeck 2133 - Loadout Bundles
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use super::loadout::{self, LOADOUT_EXTENSION};
+use super::prompts::{self, TEMPLATE_EXTENSION};
+
+const BUNDLE_FORMAT: &str = "nd2133-bundle";
+const BUNDLE_VERSION: u32 = 1;
+const MANIFEST_FILE: &str = "manifest.json";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Manifest {
+    format: String,
+    version: u32,
+    created: String,
+    files: Vec<ManifestEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ManifestEntry {
+    path: String,
+    sha256: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ItemKind {
+    Loadout,
+    Prompt,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BundleItem {
+    pub kind: ItemKind,
+    pub name: String,
+    // A loadout or template with this name is already installed
+    pub exists: bool,
+    // Loadout settings that reach the network, the filesystem or the shell, or
+    // send local data to the provider. A bundle can't grant them: they are reset on import and must be set again.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub reset: Vec<String>,
+}
+
+// How to import one item; items are keyed "loadout:<name>" or "prompt:<name>"
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum Resolution {
+    Overwrite,
+    Skip,
+    Rename { name: String },
+}
+
+// A bundle unpacked into a scratch directory, removed again on drop
+struct Unpacked {
+    dir: PathBuf,
+    items: Vec<(ItemKind, String, PathBuf)>,
+}
+
+impl Drop for Unpacked {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+pub async fn export_bundle(loadouts: Vec<String>, path: String) -> Result<(), String> {
+    if loadouts.is_empty() {
+        return Err("Select at least one loadout to export".to_string());
+    }
+
+    let staging = scratch_dir("export")?;
+    let result = write_bundle(&staging, &loadouts, &path);
+    let _ = fs::remove_dir_all(&staging);
+    result
+}
+
+// List a bundle's contents, verifying every digest, so the UI can ask how to
+// resolve name collisions before anything is installed
+pub async fn inspect_bundle(path: String) -> Result<Vec<BundleItem>, String> {
+    let unpacked = unpack(&path)?;
+
+    Ok(unpacked
+        .items
+        .iter()
+        .map(|(kind, name, file)| BundleItem {
+            kind: *kind,
+            exists: installed_path(*kind, name).map(|p| p.exists()).unwrap_or(false),
+            reset: match kind {
+                ItemKind::Loadout => fs::read_to_string(file)
+                    .ok()
+                    .and_then(|content| loadout::parse_loadout(name, &content).ok())
+                    .map(|(parsed, _)| untrusted_settings(&parsed))
+                    .unwrap_or_default(),
+                ItemKind::Prompt => Vec::new(),
+            },
+            name: name.clone(),
+        })
+        .collect())
+}
+
+// Install a bundle. Colliding items need a resolution; without one the
+// import is refused before anything is written. Returns what was installed,
+// with the loadout settings that were reset to their defaults.
+pub async fn import_bundle(
+    path: String,
+    resolutions: BTreeMap<String, Resolution>,
+) -> Result<Vec<BundleItem>, String> {
+    let unpacked = unpack(&path)?;
+
+    // Work out every final name first so a collision aborts the whole import
+    let mut plan = Vec::new();
+    let mut collisions = Vec::new();
+    for (kind, name, file) in &unpacked.items {
+        let key = item_key(*kind, name);
+        let target = match resolutions.get(&key) {
+            Some(Resolution::Skip) => continue,
+            Some(Resolution::Overwrite) => name.clone(),
+            Some(Resolution::Rename { name: new_name }) => {
+                if installed_path(*kind, new_name)?.exists() {
+                    collisions.push(item_key(*kind, new_name));
+                }
+                new_name.clone()
+            }
+            None => {
+                if installed_path(*kind, name)?.exists() {
+                    collisions.push(key);
+                }
+                name.clone()
+            }
+        };
+        plan.push((*kind, name.clone(), target, file.clone()));
+    }
+
+    if !collisions.is_empty() {
+        return Err(format!(
+            "Already installed: {}. Choose overwrite, skip or rename for each.",
+            collisions.join(", ")
+        ));
+    }
+
+    // Renamed templates must be renamed wherever the bundle includes them
+    let prompt_renames: Vec<(String, String)> = plan
+        .iter()
+        .filter(|(kind, name, target, _)| *kind == ItemKind::Prompt && name != target)
+        .map(|(_, name, target, _)| (name.clone(), target.clone()))
+        .collect();
+    let relink = |text: String| {
+        prompt_renames
+            .iter()
+            .fold(text, |text, (old, new)| prompts::rename_includes(&text, old, new))
+    };
+
+    // Parse every loadout up front; a bad one shouldn't leave a half import
+    let mut staged = Vec::new();
+    for (kind, name, target, file) in plan {
+        let content = fs::read_to_string(&file)
+            .map_err(|e| format!("Failed to read {} from bundle: {}", name, e))?;
+        match kind {
+            ItemKind::Loadout => {
+                let (mut parsed, _) = loadout::parse_loadout(&target, &content)?;
+                parsed.system_prompt = relink(parsed.system_prompt);
+                let reset = untrusted_settings(&parsed);
+                let defaults = loadout::AILoadout::default();
+                parsed.endpoint = defaults.endpoint;
+                parsed.tools_enabled = defaults.tools_enabled;
+                parsed.risky_steps = defaults.risky_steps;
+                parsed.context = defaults.context;
+                parsed.retrieval = defaults.retrieval;
+                staged.push((kind, target, None, Some(parsed), reset));
+            }
+            ItemKind::Prompt => staged.push((kind, target, Some(relink(content)), None, Vec::new())),
+        }
+    }
+
+    let mut installed = Vec::new();
+    for (kind, name, prompt, parsed, reset) in staged {
+        match (prompt, parsed) {
+            (Some(content), _) => prompts::save_template(name.clone(), content).await?,
+            (_, Some(parsed)) => loadout::write_loadout(&parsed)?,
+            _ => continue,
+        }
+        installed.push(BundleItem { kind, name, exists: true, reset });
+    }
+
+    Ok(installed)
+}
+
+fn write_bundle(staging: &Path, loadouts: &[String], path: &str) -> Result<(), String> {
+    let mut files = Vec::new();
+    let mut templates = BTreeSet::new();
+
+    for name in loadouts {
+        let source = loadout::get_loadout_path(name)?;
+        if !source.exists() {
+            return Err(format!("Loadout '{}' not found", name));
+        }
+        let content = fs::read(&source)
+            .map_err(|e| format!("Failed to read loadout '{}': {}", name, e))?;
+
+        let parsed = loadout::read_loadout(name)?;
+        collect_templates(&parsed.system_prompt, &mut templates)?;
+
+        files.push((format!("loadouts/{}.{}", name, LOADOUT_EXTENSION), content));
+    }
+
+    for name in &templates {
+        let content = prompts::read_template(name)?;
+        files.push((format!("prompts/{}.{}", name, TEMPLATE_EXTENSION), content.into_bytes()));
+    }
+
+    let mut manifest = Manifest {
+        format: BUNDLE_FORMAT.to_string(),
+        version: BUNDLE_VERSION,
+        created: chrono::Local::now().to_rfc3339(),
+        files: Vec::new(),
+    };
+
+    for (relative, content) in &files {
+        let target = staging.join(relative);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create bundle directory: {}", e))?;
+        }
+        fs::write(&target, content)
+            .map_err(|e| format!("Failed to stage {}: {}", relative, e))?;
+
+        manifest.files.push(ManifestEntry {
+            path: relative.clone(),
+            sha256: hex::encode(Sha256::digest(content)),
+        });
+    }
+
+    let manifest_json = serde_json::to_string_pretty(&manifest)
+        .map_err(|e| format!("Failed to serialize bundle manifest: {}", e))?;
+    fs::write(staging.join(MANIFEST_FILE), manifest_json)
+        .map_err(|e| format!("Failed to write bundle manifest: {}", e))?;
+
+    let output = Command::new("tar")
+        .args(["-czf", path, "-C", &staging.to_string_lossy(), "."])
+        .output()
+        .map_err(|e| format!("Failed to create bundle: {}", e))?;
+
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).to_string());
+    }
+
+    Ok(())
+}
+
+// The settings a loadout changes from the defaults that a shared bundle
+// mustn't carry: where requests (and the API key) go, which tools run,
+// which operation steps skip confirmation, and which local data (system
+// context, retrieved files) is sent along with every message
+fn untrusted_settings(parsed: &loadout::AILoadout) -> Vec<String> {
+    let defaults = loadout::AILoadout::default();
+    let mut settings = Vec::new();
+    if parsed.endpoint != defaults.endpoint {
+        settings.push("endpoint".to_string());
+    }
+    if parsed.tools_enabled != defaults.tools_enabled {
+        settings.push("tools_enabled".to_string());
+    }
+    if parsed.risky_steps != defaults.risky_steps {
+        settings.push("risky_steps".to_string());
+    }
+    if parsed.context.is_some() {
+        settings.push("context".to_string());
+    }
+    if parsed.retrieval.is_some() {
+        settings.push("retrieval".to_string());
+    }
+    settings
+}
+
+// Templates `text` includes, followed transitively
+fn collect_templates(text: &str, found: &mut BTreeSet<String>) -> Result<(), String> {
+    for name in prompts::includes(text) {
+        if found.insert(name.clone()) {
+            collect_templates(&prompts::read_template(&name)?, found)?;
+        }
+    }
+    Ok(())
+}
+
+fn unpack(path: &str) -> Result<Unpacked, String> {
+    let dir = scratch_dir("import")?;
+    let mut unpacked = Unpacked { dir, items: Vec::new() };
+
+    let output = Command::new("tar")
+        .args(["-xzf", path, "-C", &unpacked.dir.to_string_lossy()])
+        .output()
+        .map_err(|e| format!("Failed to open bundle: {}", e))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "Failed to open bundle: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    let manifest_content = fs::read_to_string(unpacked.dir.join(MANIFEST_FILE))
+        .map_err(|_| "Bundle has no manifest".to_string())?;
+    let manifest: Manifest = serde_json::from_str(&manifest_content)
+        .map_err(|e| format!("Failed to parse bundle manifest: {}", e))?;
+
+    if manifest.format != BUNDLE_FORMAT {
+        return Err("Not a NixDeck loadout bundle".to_string());
+    }
+    if manifest.version > BUNDLE_VERSION {
+        return Err(format!(
+            "Bundle uses format v{}, newer than this build supports (v{})",
+            manifest.version, BUNDLE_VERSION
+        ));
+    }
+
+    // Only manifest entries are read, so stray files in the archive are ignored
+    for entry in &manifest.files {
+        let (kind, name) = parse_entry_path(&entry.path)?;
+        let file = unpacked.dir.join(&entry.path);
+
+        let content = fs::read(&file)
+            .map_err(|_| format!("Bundle is missing {}", entry.path))?;
+        let digest = hex::encode(Sha256::digest(&content));
+        if !digest.eq_ignore_ascii_case(&entry.sha256) {
+            return Err(format!("Checksum mismatch for {}; the bundle is corrupt or was modified", entry.path));
+        }
+
+        unpacked.items.push((kind, name, file));
+    }
+
+    if unpacked.items.is_empty() {
+        return Err("Bundle is empty".to_string());
+    }
+
+    Ok(unpacked)
+}
+
+// "loadouts/<name>.nd2133-loadout" or "prompts/<name>.md"
+fn parse_entry_path(path: &str) -> Result<(ItemKind, String), String> {
+    let invalid = || format!("Unexpected file in bundle manifest: {}", path);
+
+    let (dir, file) = path.split_once('/').ok_or_else(invalid)?;
+    let (kind, extension) = match dir {
+        "loadouts" => (ItemKind::Loadout, LOADOUT_EXTENSION),
+        "prompts" => (ItemKind::Prompt, TEMPLATE_EXTENSION),
+        _ => return Err(invalid()),
+    };
+
+    let name = file
+        .strip_suffix(extension)
+        .and_then(|stem| stem.strip_suffix('.'))
+        .ok_or_else(invalid)?;
+
+    // The installed path check rejects separators and dot-files
+    installed_path(kind, name).map_err(|_| invalid())?;
+    Ok((kind, name.to_string()))
+}
+
+fn installed_path(kind: ItemKind, name: &str) -> Result<PathBuf, String> {
+    match kind {
+        ItemKind::Loadout => loadout::get_loadout_path(name),
+        ItemKind::Prompt => prompts::get_template_path(name),
+    }
+}
+
+fn item_key(kind: ItemKind, name: &str) -> String {
+    match kind {
+        ItemKind::Loadout => format!("loadout:{}", name),
+        ItemKind::Prompt => format!("prompt:{}", name),
+    }
+}
+
+fn scratch_dir(purpose: &str) -> Result<PathBuf, String> {
+    crate::atomic::private_dir(&format!("bundle-{}", purpose))
+}
@@ -0,0 +1,222 @@
+// NixDeck 2133 - AI Model Catalog
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use super::client::{self, Endpoint};
+use super::provider::ProviderKind;
+use super::AILoadout;
+
+// How long a fetched catalog is trusted before it is refreshed
+const CACHE_TTL_SECS: i64 = 24 * 60 * 60;
+
+// Offered when OpenRouter is unreachable and nothing has been cached yet
+const BUILTIN_OPENROUTER_MODELS: &[&str] = &[
+    "anthropic/claude-3.5-sonnet",
+    "anthropic/claude-3-opus",
+    "openai/gpt-4",
+    "openai/gpt-3.5-turbo",
+    "google/gemini-pro",
+];
+
+// USD per token
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ModelPrice {
+    pub prompt: f64,
+    pub completion: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelInfo {
+    pub id: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    pub context_length: Option<u64>,
+    pub pricing: Option<ModelPrice>,
+    #[serde(default)]
+    pub supports_tools: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelCatalog {
+    pub provider: ProviderKind,
+    // When the listing was fetched; None for the built-in fallback
+    pub fetched: Option<String>,
+    // True when the provider couldn't be reached and an old or built-in list is returned
+    pub stale: bool,
+    pub models: Vec<ModelInfo>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CatalogCache {
+    fetched: String,
+    models: Vec<ModelInfo>,
+}
+
+pub async fn list_models(
+    provider: ProviderKind,
+    endpoint: Option<String>,
+    refresh: bool,
+) -> Result<ModelCatalog, String> {
+    catalog_for(&Endpoint::new(provider, endpoint.as_deref()).await, refresh).await
+}
+
+// Price of `model` under `loadout`'s provider, or None when it isn't known.
+// Only the cached listing is read, so recording usage never waits on the
+// network; a missing or expired listing is refreshed in the background.
+pub fn price_for(loadout: &AILoadout, model: &str) -> Option<ModelPrice> {
+    if loadout.provider == ProviderKind::Ollama {
+        // Local inference costs nothing per token
+        return Some(ModelPrice { prompt: 0.0, completion: 0.0 });
+    }
+
+    let cached = read_cache(&get_cache_path(loadout.provider, &loadout_base_url(loadout)));
+    let fresh = cached.as_ref().is_some_and(|cache| cache_age(cache) < CACHE_TTL_SECS);
+    if !fresh {
+        refresh_in_background(loadout.provider, loadout.endpoint.clone());
+    }
+
+    cached?.models.into_iter().find(|m| m.id == model)?.pricing
+}
+
+// At most one refresh per listing runs at a time. The key is only looked up
+// once a refresh actually starts.
+fn refresh_in_background(kind: ProviderKind, endpoint: Option<String>) {
+    static REFRESHING: OnceLock<Mutex<HashSet<PathBuf>>> = OnceLock::new();
+    let refreshing = REFRESHING.get_or_init(|| Mutex::new(HashSet::new()));
+
+    let cache_path = get_cache_path(kind, &Endpoint::base_url(kind, endpoint.as_deref()));
+    let started = refreshing
+        .lock()
+        .map(|mut paths| paths.insert(cache_path.clone()))
+        .unwrap_or(false);
+    if !started {
+        return;
+    }
+
+    tauri::async_runtime::spawn(async move {
+        let endpoint = Endpoint::new(kind, endpoint.as_deref()).await;
+        let _ = catalog_for(&endpoint, true).await;
+        if let Ok(mut paths) = refreshing.lock() {
+            paths.remove(&cache_path);
+        }
+    });
+}
+
+// Warnings for a loadout whose models are missing from the provider's listing.
+// Only consults the cache so saving a loadout never waits on the network.
+pub fn model_warnings(loadout: &AILoadout) -> Vec<String> {
+    let cache = match read_cache(&get_cache_path(loadout.provider, &loadout_base_url(loadout))) {
+        Some(cache) => cache,
+        None => return Vec::new(),
+    };
+
+    loadout
+        .models()
+        .into_iter()
+        .filter(|model| !cache.models.iter().any(|m| &m.id == model))
+        .map(|model| {
+            format!(
+                "model '{}' is not in the {} model list (fetched {})",
+                model,
+                loadout.provider.as_str(),
+                cache.fetched
+            )
+        })
+        .collect()
+}
+
+async fn catalog_for(endpoint: &Endpoint, refresh: bool) -> Result<ModelCatalog, String> {
+    let cache_path = get_cache_path(endpoint.kind, &endpoint.base_url);
+    let cached = read_cache(&cache_path);
+
+    if let Some(cache) = &cached {
+        if !refresh && cache_age(cache) < CACHE_TTL_SECS {
+            return Ok(catalog(endpoint, Some(cache.fetched.clone()), false, cache.models.clone()));
+        }
+    }
+
+    let error = match client::list_models(endpoint).await {
+        Ok(models) => {
+            let cache = write_cache(&cache_path, models)?;
+            return Ok(catalog(endpoint, Some(cache.fetched), false, cache.models));
+        }
+        Err(e) => e,
+    };
+
+    // Offline: an expired listing beats no listing at all
+    if let Some(cache) = cached {
+        return Ok(catalog(endpoint, Some(cache.fetched), true, cache.models));
+    }
+
+    if endpoint.kind == ProviderKind::OpenRouter {
+        let models = BUILTIN_OPENROUTER_MODELS
+            .iter()
+            .map(|id| ModelInfo {
+                id: id.to_string(),
+                name: None,
+                context_length: None,
+                pricing: None,
+                supports_tools: false,
+            })
+            .collect();
+        return Ok(catalog(endpoint, None, true, models));
+    }
+
+    Err(format!("Failed to fetch model catalog: {}", error))
+}
+
+fn catalog(endpoint: &Endpoint, fetched: Option<String>, stale: bool, models: Vec<ModelInfo>) -> ModelCatalog {
+    ModelCatalog {
+        provider: endpoint.kind,
+        fetched,
+        stale,
+        models,
+    }
+}
+
+fn cache_age(cache: &CatalogCache) -> i64 {
+    chrono::DateTime::parse_from_rfc3339(&cache.fetched)
+        .map(|fetched| chrono::Local::now().signed_duration_since(fetched).num_seconds())
+        .unwrap_or(i64::MAX)
+}
+
+fn read_cache(path: &Path) -> Option<CatalogCache> {
+    let content = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+fn write_cache(path: &Path, models: Vec<ModelInfo>) -> Result<CatalogCache, String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create cache directory: {}", e))?;
+    }
+
+    let cache = CatalogCache {
+        fetched: chrono::Local::now().to_rfc3339(),
+        models,
+    };
+    let json = serde_json::to_string(&cache)
+        .map_err(|e| format!("Failed to serialize model catalog: {}", e))?;
+
+    fs::write(path, json)
+        .map_err(|e| format!("Failed to write model catalog cache: {}", e))?;
+
+    Ok(cache)
+}
+
+fn loadout_base_url(loadout: &AILoadout) -> String {
+    Endpoint::base_url(loadout.provider, loadout.endpoint.as_deref())
+}
+
+// One cache file per provider and base URL, so two Ollama hosts don't collide
+fn get_cache_path(kind: ProviderKind, base_url: &str) -> PathBuf {
+    let home = dirs::home_dir().expect("Could not find home directory");
+    let url_hash = hex::encode(Sha256::digest(base_url.as_bytes()));
+    home.join(".nixdeck")
+        .join("cache")
+        .join(format!("models-{}-{}.json", kind.as_str(), &url_hash[..12]))
+}
@@ -0,0 +1,864 @@
+// NixDeck 2133 - AI Chat Client
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use super::catalog::ModelInfo;
+use super::provider::{Provider, ProviderKind};
+use super::retry;
+use super::tools::ToolSchema;
+use super::AILoadout;
+
+// Overrides every loadout's endpoint (e.g. to point at a local mock server)
+const BASE_URL_ENV: &str = "NIXDECK_AI_BASE_URL";
+
+#[derive(Debug)]
+pub enum AIError {
+    MissingApiKey(String),
+    Unauthorized(String),
+    RateLimited {
+        retry_after: Option<u64>,
+        message: String,
+    },
+    InvalidModel(String),
+    Http {
+        status: u16,
+        message: String,
+    },
+    Network(String),
+    InvalidResponse(String),
+    ToolRoundsExceeded(usize),
+    Redaction(String),
+    BudgetExceeded {
+        loadout: String,
+        spent: f64,
+        budget: f64,
+    },
+    // A budgeted loadout would use a model whose price isn't known
+    BudgetUnpriced {
+        loadout: String,
+        model: String,
+    },
+    // The usage ledger couldn't be read, so spending can't be checked
+    BudgetUnavailable {
+        loadout: String,
+        reason: String,
+    },
+    Cancelled,
+}
+
+impl fmt::Display for AIError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AIError::MissingApiKey(provider) => write!(f, "No API key configured for {}", provider),
+            AIError::Unauthorized(msg) => write!(f, "Authentication failed: {}", msg),
+            AIError::RateLimited { retry_after: Some(secs), message } => {
+                write!(f, "Rate limited (retry after {}s): {}", secs, message)
+            }
+            AIError::RateLimited { retry_after: None, message } => {
+                write!(f, "Rate limited: {}", message)
+            }
+            AIError::InvalidModel(msg) => write!(f, "Invalid model: {}", msg),
+            AIError::Http { status, message } => write!(f, "HTTP {}: {}", status, message),
+            AIError::Network(msg) => write!(f, "Network error: {}", msg),
+            AIError::InvalidResponse(msg) => write!(f, "Invalid response: {}", msg),
+            AIError::ToolRoundsExceeded(rounds) => {
+                write!(f, "Model kept calling tools after {} rounds", rounds)
+            }
+            AIError::Redaction(msg) => write!(f, "Secret redaction failed: {}", msg),
+            AIError::BudgetExceeded { loadout, spent, budget } => write!(
+                f,
+                "Monthly budget for loadout '{}' exhausted (${:.2} of ${:.2} spent)",
+                loadout, spent, budget
+            ),
+            AIError::BudgetUnpriced { loadout, model } => write!(
+                f,
+                "Loadout '{}' has a monthly budget, but no price is known for '{}'; refresh the model list or remove the budget",
+                loadout, model
+            ),
+            AIError::BudgetUnavailable { loadout, reason } => write!(
+                f,
+                "Could not check the monthly budget for loadout '{}': {}",
+                loadout, reason
+            ),
+            AIError::Cancelled => write!(f, "Request cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for AIError {}
+
+impl From<AIError> for String {
+    fn from(err: AIError) -> String {
+        err.to_string()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub role: String,
+    // Assistant turns that only call tools come back with `"content": null`
+    #[serde(default, deserialize_with = "null_as_empty")]
+    pub content: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_calls: Option<Vec<ToolCall>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolCall {
+    pub id: String,
+    #[serde(rename = "type", default = "function_kind")]
+    pub kind: String,
+    pub function: FunctionCall,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FunctionCall {
+    pub name: String,
+    #[serde(default)]
+    pub arguments: String,
+}
+
+impl ChatMessage {
+    fn new(role: &str, content: String) -> Self {
+        ChatMessage {
+            role: role.to_string(),
+            content,
+            tool_calls: None,
+            tool_call_id: None,
+        }
+    }
+
+    pub fn system(content: impl Into<String>) -> Self {
+        ChatMessage::new("system", content.into())
+    }
+
+    pub fn user(content: impl Into<String>) -> Self {
+        ChatMessage::new("user", content.into())
+    }
+
+    pub fn assistant(content: impl Into<String>) -> Self {
+        ChatMessage::new("assistant", content.into())
+    }
+
+    pub fn tool(call_id: impl Into<String>, content: impl Into<String>) -> Self {
+        ChatMessage {
+            tool_call_id: Some(call_id.into()),
+            ..ChatMessage::new("tool", content.into())
+        }
+    }
+
+    pub fn requested_tools(&self) -> &[ToolCall] {
+        self.tool_calls.as_deref().unwrap_or(&[])
+    }
+}
+
+pub fn function_kind() -> String {
+    "function".to_string()
+}
+
+fn null_as_empty<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(Option::<String>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+// Token counts reported by the provider for one request
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Usage {
+    #[serde(default)]
+    pub prompt_tokens: u64,
+    #[serde(default)]
+    pub completion_tokens: u64,
+}
+
+// An assistant turn plus what it cost to produce and which model answered
+#[derive(Debug)]
+pub struct Completion {
+    pub message: ChatMessage,
+    pub usage: Option<Usage>,
+    pub model: String,
+}
+
+// Where and how a loadout's requests are sent
+pub struct Endpoint {
+    pub kind: ProviderKind,
+    pub provider: &'static dyn Provider,
+    pub base_url: String,
+    // Credential store entry the key came from, or would have
+    pub credential: String,
+    pub api_key: Option<String>,
+}
+
+impl Endpoint {
+    pub async fn new(kind: ProviderKind, endpoint: Option<&str>) -> Self {
+        let provider = kind.backend();
+        let base_url = Endpoint::base_url(kind, endpoint);
+        let credential = credential_for(provider, &base_url);
+        let api_key = match &credential {
+            Some(credential) => crate::credentials::get_key(credential).await,
+            None => None,
+        };
+        let credential = credential.unwrap_or_else(|| provider.credential().to_string());
+
+        Endpoint { kind, provider, base_url, credential, api_key }
+    }
+
+    pub async fn for_loadout(loadout: &AILoadout) -> Self {
+        Endpoint::new(loadout.provider, loadout.endpoint.as_deref()).await
+    }
+
+    // Where requests go, without looking up a key
+    pub fn base_url(kind: ProviderKind, endpoint: Option<&str>) -> String {
+        std::env::var(BASE_URL_ENV)
+            .ok()
+            .or_else(|| endpoint.map(str::to_string))
+            .filter(|url| !url.trim().is_empty())
+            .unwrap_or_else(|| kind.backend().default_endpoint().to_string())
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("{}/{}", self.base_url.trim_end_matches('/'), path)
+    }
+}
+
+// The provider's key is only ever sent to the provider's own endpoint. Any
+// other endpoint, say a loadout pointing at a local server or one from an
+// imported bundle, uses a key stored for that host, e.g. `openai@localhost:8080`.
+fn credential_for(provider: &dyn Provider, base_url: &str) -> Option<String> {
+    let url = reqwest::Url::parse(base_url).ok()?;
+    let default = reqwest::Url::parse(provider.default_endpoint()).ok()?;
+    if url.origin() == default.origin() {
+        return Some(provider.credential().to_string());
+    }
+
+    let host = url.host_str()?;
+    Some(match url.port() {
+        Some(port) => format!("{}@{}:{}", provider.credential(), host, port),
+        None => format!("{}@{}", provider.credential(), host),
+    })
+}
+
+// Provider-neutral request parameters
+pub struct ChatParams<'a> {
+    pub model: &'a str,
+    pub messages: &'a [ChatMessage],
+    pub temperature: f32,
+    pub max_tokens: u32,
+    pub tools: &'a [ToolSchema],
+    pub stream: bool,
+}
+
+// One parsed line of a streaming response
+#[derive(Debug, Default)]
+pub struct StreamDelta {
+    pub content: Option<String>,
+    pub tool_calls: Vec<ToolCallDelta>,
+    pub usage: Option<Usage>,
+    pub done: bool,
+}
+
+// A fragment of a tool call. OpenAI-style streams spread a call over several
+// fragments sharing an index; `index: None` marks a call delivered whole.
+#[derive(Debug, Default)]
+pub struct ToolCallDelta {
+    pub index: Option<usize>,
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub arguments: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum ErrorBody {
+    Detailed { error: ErrorDetail },
+    Plain { error: String },
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ErrorDetail {
+    pub message: String,
+}
+
+pub async fn chat_completion(
+    endpoint: &Endpoint,
+    loadout: &AILoadout,
+    messages: &[ChatMessage],
+    tools: &[ToolSchema],
+) -> Result<Completion, AIError> {
+    let (response, model) = send_request(endpoint, loadout, messages, tools, false).await?;
+
+    let body: serde_json::Value = response
+        .json()
+        .await
+        .map_err(|e| AIError::InvalidResponse(e.to_string()))?;
+
+    // Providers parse the body; only the retry loop knows which model answered
+    let mut completion = endpoint.provider.parse_response(body)?;
+    completion.model = model;
+    Ok(completion)
+}
+
+// Stream a completion, handing each content delta to `on_delta` as it
+// arrives. Returns the fully assembled assistant turn. Only failures before
+// the first byte are retried, since deltas can't be taken back.
+pub async fn chat_completion_stream<F>(
+    endpoint: &Endpoint,
+    loadout: &AILoadout,
+    messages: &[ChatMessage],
+    tools: &[ToolSchema],
+    mut on_delta: F,
+) -> Result<Completion, AIError>
+where
+    F: FnMut(&str),
+{
+    let (mut response, model) = send_request(endpoint, loadout, messages, tools, true).await?;
+
+    // Buffer raw bytes so multi-byte characters split across chunks survive
+    let mut buffer: Vec<u8> = Vec::new();
+    let mut reply = ChatMessage::assistant(String::new());
+    let mut calls: Vec<ToolCall> = Vec::new();
+    let mut usage = None;
+
+    loop {
+        let chunk = response
+            .chunk()
+            .await
+            .map_err(|e| AIError::Network(e.to_string()))?;
+        let ended = chunk.is_none();
+        match chunk {
+            Some(bytes) => buffer.extend_from_slice(&bytes),
+            // Some servers end the stream without a final newline; what's
+            // left is still a whole last line
+            None if !buffer.is_empty() => buffer.push(b'\n'),
+            None => {}
+        }
+
+        while let Some(newline) = buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = buffer.drain(..=newline).collect();
+            let line = String::from_utf8_lossy(&line);
+
+            let delta = match endpoint.provider.parse_stream_line(line.trim())? {
+                Some(delta) => delta,
+                None => continue,
+            };
+
+            if let Some(content) = delta.content.filter(|c| !c.is_empty()) {
+                on_delta(&content);
+                reply.content.push_str(&content);
+            }
+            for fragment in delta.tool_calls {
+                merge_tool_call(&mut calls, fragment);
+            }
+            if delta.usage.is_some() {
+                usage = delta.usage;
+            }
+            if delta.done {
+                return Ok(finish_stream(reply, calls, usage, model));
+            }
+        }
+
+        if ended {
+            return Ok(finish_stream(reply, calls, usage, model));
+        }
+    }
+}
+
+// Fetch the provider's model listing
+pub async fn list_models(endpoint: &Endpoint) -> Result<Vec<ModelInfo>, AIError> {
+    let provider = endpoint.provider;
+
+    let mut request = reqwest::Client::new().get(endpoint.url(provider.models_path()));
+    if let Some(api_key) = &endpoint.api_key {
+        request = request.bearer_auth(api_key);
+    }
+    for (name, value) in provider.extra_headers() {
+        request = request.header(*name, *value);
+    }
+
+    let response = request
+        .send()
+        .await
+        .map_err(|e| AIError::Network(e.to_string()))?;
+
+    if !response.status().is_success() {
+        return Err(error_from_response(response, "model catalog").await);
+    }
+
+    let body: serde_json::Value = response
+        .json()
+        .await
+        .map_err(|e| AIError::InvalidResponse(e.to_string()))?;
+
+    provider.parse_models(body)
+}
+
+fn merge_tool_call(calls: &mut Vec<ToolCall>, fragment: ToolCallDelta) {
+    let index = fragment.index.unwrap_or(calls.len());
+    if calls.len() <= index {
+        calls.resize_with(index + 1, || ToolCall {
+            id: String::new(),
+            kind: function_kind(),
+            function: FunctionCall::default(),
+        });
+    }
+
+    let call = &mut calls[index];
+    if let Some(id) = fragment.id {
+        call.id = id;
+    }
+    if let Some(name) = fragment.name {
+        call.function.name.push_str(&name);
+    }
+    if let Some(arguments) = fragment.arguments {
+        call.function.arguments.push_str(&arguments);
+    }
+}
+
+fn finish_stream(mut reply: ChatMessage, mut calls: Vec<ToolCall>, usage: Option<Usage>, model: String) -> Completion {
+    // Calls streamed without an ID (Ollama's) are numbered by their place in
+    // the stream, so no two share one
+    let stamp = chrono::Local::now().timestamp_millis();
+    for (index, call) in calls.iter_mut().enumerate() {
+        if call.id.is_empty() {
+            call.id = format!("call_{}_{}", stamp, index);
+        }
+    }
+    if !calls.is_empty() {
+        reply.tool_calls = Some(calls);
+    }
+    Completion { message: reply, usage, model }
+}
+
+// Send to the loadout's model, retrying and falling back per its policy.
+// Returns the response and the model that produced it.
+async fn send_request(
+    endpoint: &Endpoint,
+    loadout: &AILoadout,
+    messages: &[ChatMessage],
+    tools: &[ToolSchema],
+    stream: bool,
+) -> Result<(reqwest::Response, String), AIError> {
+    let provider = endpoint.provider;
+    if provider.requires_key() && endpoint.api_key.is_none() {
+        return Err(AIError::MissingApiKey(endpoint.credential.clone()));
+    }
+
+    let policy = loadout.retry.clone().unwrap_or_default();
+    retry::run(&policy, &loadout.models(), |model| async move {
+        send_once(endpoint, loadout, &model, messages, tools, stream).await
+    })
+    .await
+}
+
+async fn send_once(
+    endpoint: &Endpoint,
+    loadout: &AILoadout,
+    model: &str,
+    messages: &[ChatMessage],
+    tools: &[ToolSchema],
+    stream: bool,
+) -> Result<reqwest::Response, AIError> {
+    let provider = endpoint.provider;
+    let body = provider.request_body(&ChatParams {
+        model,
+        messages,
+        temperature: loadout.temperature,
+        max_tokens: loadout.max_tokens,
+        tools,
+        stream,
+    });
+
+    let mut request = reqwest::Client::new()
+        .post(endpoint.url(provider.chat_path()))
+        .json(&body);
+    if let Some(api_key) = &endpoint.api_key {
+        request = request.bearer_auth(api_key);
+    }
+    for (name, value) in provider.extra_headers() {
+        request = request.header(*name, *value);
+    }
+
+    let response = request
+        .send()
+        .await
+        .map_err(|e| AIError::Network(e.to_string()))?;
+
+    if !response.status().is_success() {
+        return Err(error_from_response(response, model).await);
+    }
+
+    Ok(response)
+}
+
+// Retry-After is either a number of seconds or an HTTP date
+fn parse_retry_after(value: &str) -> Option<u64> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(secs);
+    }
+
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let secs = (date.with_timezone(&chrono::Utc) - chrono::Utc::now()).num_seconds();
+    Some(secs.max(0) as u64)
+}
+
+// Map a non-2xx response onto a typed error
+async fn error_from_response(response: reqwest::Response, model: &str) -> AIError {
+    let status = response.status().as_u16();
+    let retry_after = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(parse_retry_after);
+
+    let text = response.text().await.unwrap_or_default();
+    let message = match serde_json::from_str::<ErrorBody>(&text) {
+        Ok(ErrorBody::Detailed { error }) => error.message,
+        Ok(ErrorBody::Plain { error }) => error,
+        Err(_) => text,
+    };
+
+    match status {
+        401 | 403 => AIError::Unauthorized(message),
+        429 => AIError::RateLimited { retry_after, message },
+        404 => AIError::InvalidModel(format!("{} ({})", model, message)),
+        400 if message.to_lowercase().contains("model") => {
+            AIError::InvalidModel(format!("{} ({})", model, message))
+        }
+        _ => AIError::Http { status, message },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::retry::RetryPolicy;
+    use std::collections::VecDeque;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    // One canned HTTP response
+    struct Reply {
+        status: u16,
+        headers: Vec<(&'static str, &'static str)>,
+        body: String,
+    }
+
+    fn reply(status: u16, body: &str) -> Reply {
+        Reply { status, headers: Vec::new(), body: body.to_string() }
+    }
+
+    fn rate_limited() -> Reply {
+        Reply {
+            status: 429,
+            headers: vec![("Retry-After", "0")],
+            body: r#"{"error":{"message":"slow down"}}"#.to_string(),
+        }
+    }
+
+    fn answer(content: &str) -> Reply {
+        reply(
+            200,
+            &serde_json::json!({
+                "choices": [{"message": {"role": "assistant", "content": content}}],
+                "usage": {"prompt_tokens": 3, "completion_tokens": 2}
+            })
+            .to_string(),
+        )
+    }
+
+    // What the server saw of each request
+    #[derive(Debug, Clone)]
+    struct Seen {
+        path: String,
+        authorization: Option<String>,
+        model: String,
+    }
+
+    // A local HTTP server that answers requests with `replies` in order, one
+    // per connection, and 500 once they run out
+    struct MockServer {
+        url: String,
+        seen: Arc<Mutex<Vec<Seen>>>,
+    }
+
+    impl MockServer {
+        async fn start(replies: Vec<Reply>) -> MockServer {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let url = format!("http://{}/v1", listener.local_addr().unwrap());
+            let seen = Arc::new(Mutex::new(Vec::new()));
+            let replies = Arc::new(Mutex::new(VecDeque::from(replies)));
+
+            let log = seen.clone();
+            tokio::spawn(async move {
+                while let Ok((mut socket, _)) = listener.accept().await {
+                    let request = read_request(&mut socket).await;
+                    log.lock().unwrap().push(request);
+
+                    let next = replies.lock().unwrap().pop_front();
+                    let reply = next.unwrap_or_else(|| reply(500, "no reply left"));
+                    let mut head = format!(
+                        "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n",
+                        reply.status,
+                        reply.body.len()
+                    );
+                    for (name, value) in &reply.headers {
+                        head.push_str(&format!("{}: {}\r\n", name, value));
+                    }
+                    head.push_str("\r\n");
+                    let _ = socket.write_all(head.as_bytes()).await;
+                    let _ = socket.write_all(reply.body.as_bytes()).await;
+                    let _ = socket.shutdown().await;
+                }
+            });
+
+            MockServer { url, seen }
+        }
+
+        fn seen(&self) -> Vec<Seen> {
+            self.seen.lock().unwrap().clone()
+        }
+
+        fn models(&self) -> Vec<String> {
+            self.seen().into_iter().map(|request| request.model).collect()
+        }
+
+        fn endpoint(&self, kind: ProviderKind, api_key: Option<&str>) -> Endpoint {
+            Endpoint {
+                kind,
+                provider: kind.backend(),
+                base_url: self.url.clone(),
+                credential: kind.backend().credential().to_string(),
+                api_key: api_key.map(str::to_string),
+            }
+        }
+    }
+
+    async fn read_request(socket: &mut tokio::net::TcpStream) -> Seen {
+        let mut data = Vec::new();
+        let mut buffer = [0u8; 4096];
+        let header_end = loop {
+            let read = socket.read(&mut buffer).await.unwrap_or(0);
+            data.extend_from_slice(&buffer[..read]);
+            if let Some(end) = data.windows(4).position(|window| window == b"\r\n\r\n") {
+                break end + 4;
+            }
+            if read == 0 {
+                break data.len();
+            }
+        };
+
+        let head = String::from_utf8_lossy(&data[..header_end]).to_string();
+        let header = |name: &str| {
+            head.lines().find_map(|line| {
+                let (key, value) = line.split_once(':')?;
+                key.eq_ignore_ascii_case(name).then(|| value.trim().to_string())
+            })
+        };
+        let length: usize = header("content-length").and_then(|value| value.parse().ok()).unwrap_or(0);
+        while data.len() < header_end + length {
+            let read = socket.read(&mut buffer).await.unwrap_or(0);
+            if read == 0 {
+                break;
+            }
+            data.extend_from_slice(&buffer[..read]);
+        }
+
+        let body: serde_json::Value = serde_json::from_slice(&data[header_end..]).unwrap_or_default();
+        Seen {
+            path: head.split_whitespace().nth(1).unwrap_or_default().to_string(),
+            authorization: header("authorization"),
+            model: body["model"].as_str().unwrap_or_default().to_string(),
+        }
+    }
+
+    // Retries without real waiting
+    fn loadout(fallback_models: &[&str]) -> AILoadout {
+        AILoadout {
+            model: "primary".to_string(),
+            fallback_models: fallback_models.iter().map(|model| model.to_string()).collect(),
+            retry: Some(RetryPolicy {
+                max_attempts: 2,
+                initial_delay_ms: 1,
+                max_delay_ms: 1,
+                multiplier: 1.0,
+                max_elapsed_secs: 10,
+            }),
+            ..AILoadout::default()
+        }
+    }
+
+    async fn ask(server: &MockServer, loadout: &AILoadout) -> Result<Completion, AIError> {
+        let endpoint = server.endpoint(ProviderKind::OpenAI, Some("sk-test"));
+        chat_completion(&endpoint, loadout, &[ChatMessage::user("hi")], &[]).await
+    }
+
+    #[tokio::test]
+    async fn answers_with_the_model_that_replied() {
+        let server = MockServer::start(vec![answer("hello")]).await;
+
+        let completion = ask(&server, &loadout(&[])).await.unwrap();
+        assert_eq!(completion.message.content, "hello");
+        assert_eq!(completion.model, "primary");
+        assert_eq!(completion.usage.map(|usage| usage.completion_tokens), Some(2));
+
+        let seen = server.seen();
+        assert_eq!(seen[0].path, "/v1/chat/completions");
+        assert_eq!(seen[0].authorization.as_deref(), Some("Bearer sk-test"));
+    }
+
+    #[tokio::test]
+    async fn unauthorized_is_typed_and_not_retried() {
+        let server = MockServer::start(vec![reply(401, r#"{"error":{"message":"bad key"}}"#)]).await;
+
+        match ask(&server, &loadout(&["backup"])).await {
+            Err(AIError::Unauthorized(message)) => assert_eq!(message, "bad key"),
+            other => panic!("expected Unauthorized, got {:?}", other),
+        }
+        assert_eq!(server.models(), ["primary"]);
+    }
+
+    #[tokio::test]
+    async fn rate_limit_is_retried_then_falls_back() {
+        let server = MockServer::start(vec![rate_limited(), rate_limited(), answer("from backup")]).await;
+
+        let completion = ask(&server, &loadout(&["backup"])).await.unwrap();
+        assert_eq!(completion.model, "backup");
+        assert_eq!(completion.message.content, "from backup");
+        assert_eq!(server.models(), ["primary", "primary", "backup"]);
+    }
+
+    #[tokio::test]
+    async fn rate_limit_without_fallback_reports_retry_after() {
+        let server = MockServer::start(vec![rate_limited(), rate_limited()]).await;
+
+        match ask(&server, &loadout(&[])).await {
+            Err(AIError::RateLimited { retry_after, message }) => {
+                assert_eq!(retry_after, Some(0));
+                assert_eq!(message, "slow down");
+            }
+            other => panic!("expected RateLimited, got {:?}", other),
+        }
+        assert_eq!(server.models(), ["primary", "primary"]);
+    }
+
+    #[tokio::test]
+    async fn server_error_is_retried() {
+        let server = MockServer::start(vec![reply(503, r#"{"error":"overloaded"}"#), answer("ok")]).await;
+
+        let completion = ask(&server, &loadout(&["backup"])).await.unwrap();
+        assert_eq!(completion.model, "primary");
+        assert_eq!(server.models(), ["primary", "primary"]);
+    }
+
+    #[tokio::test]
+    async fn server_errors_on_every_model_give_the_last_one() {
+        let server = MockServer::start(Vec::new()).await;
+
+        match ask(&server, &loadout(&["backup"])).await {
+            Err(AIError::Http { status, message }) => {
+                assert_eq!(status, 500);
+                assert_eq!(message, "no reply left");
+            }
+            other => panic!("expected Http, got {:?}", other),
+        }
+        assert_eq!(server.models(), ["primary", "primary", "backup", "backup"]);
+    }
+
+    #[tokio::test]
+    async fn unknown_model_is_not_retried() {
+        let server = MockServer::start(vec![reply(404, r#"{"error":{"message":"no such model"}}"#)]).await;
+
+        match ask(&server, &loadout(&["backup"])).await {
+            Err(AIError::InvalidModel(message)) => assert!(message.starts_with("primary"), "{}", message),
+            other => panic!("expected InvalidModel, got {:?}", other),
+        }
+        assert_eq!(server.models(), ["primary"]);
+    }
+
+    #[tokio::test]
+    async fn bad_json_is_an_invalid_response() {
+        let server = MockServer::start(vec![reply(200, "<html>not json</html>"), reply(200, r#"{"choices":[]}"#)]).await;
+
+        assert!(matches!(ask(&server, &loadout(&[])).await, Err(AIError::InvalidResponse(_))));
+        assert!(matches!(ask(&server, &loadout(&[])).await, Err(AIError::InvalidResponse(_))));
+        assert_eq!(server.models(), ["primary", "primary"]);
+    }
+
+    #[tokio::test]
+    async fn missing_key_fails_before_sending() {
+        let server = MockServer::start(vec![answer("unused")]).await;
+        let endpoint = server.endpoint(ProviderKind::OpenRouter, None);
+
+        let result = chat_completion(&endpoint, &loadout(&[]), &[ChatMessage::user("hi")], &[]).await;
+        assert!(matches!(result, Err(AIError::MissingApiKey(credential)) if credential == "openrouter"));
+        assert!(server.seen().is_empty());
+    }
+
+    #[tokio::test]
+    async fn unreachable_server_is_a_network_error() {
+        // Bind then drop, so nothing listens on the port
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        drop(listener);
+
+        let endpoint = Endpoint {
+            kind: ProviderKind::OpenAI,
+            provider: ProviderKind::OpenAI.backend(),
+            base_url: format!("http://{}/v1", address),
+            credential: "openai".to_string(),
+            api_key: None,
+        };
+        let result = chat_completion(&endpoint, &loadout(&["backup"]), &[ChatMessage::user("hi")], &[]).await;
+        assert!(matches!(result, Err(AIError::Network(_))));
+    }
+
+    #[tokio::test]
+    async fn streamed_reply_is_assembled() {
+        let events = [
+            r#"data: {"choices":[{"delta":{"content":"hel"}}]}"#,
+            r#"data: {"choices":[{"delta":{"content":"lo"}}]}"#,
+            r#"data: {"choices":[],"usage":{"prompt_tokens":1,"completion_tokens":2}}"#,
+            "data: [DONE]",
+        ];
+        let server = MockServer::start(vec![rate_limited(), reply(200, &(events.join("\n\n") + "\n\n"))]).await;
+        let endpoint = server.endpoint(ProviderKind::OpenAI, None);
+
+        let mut deltas = Vec::new();
+        let completion = chat_completion_stream(&endpoint, &loadout(&[]), &[ChatMessage::user("hi")], &[], |delta| {
+            deltas.push(delta.to_string())
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(deltas, ["hel", "lo"]);
+        assert_eq!(completion.message.content, "hello");
+        assert_eq!(completion.usage.map(|usage| usage.completion_tokens), Some(2));
+        assert_eq!(server.models(), ["primary", "primary"]);
+    }
+
+    #[tokio::test]
+    async fn stream_without_a_final_newline_keeps_its_last_line() {
+        let lines = [
+            r#"{"message":{"content":"hel"},"done":false}"#,
+            r#"{"message":{"content":"lo"},"done":true,"prompt_eval_count":3,"eval_count":4}"#,
+        ];
+        let server = MockServer::start(vec![reply(200, &lines.join("\n"))]).await;
+        let endpoint = server.endpoint(ProviderKind::Ollama, None);
+
+        let mut deltas = Vec::new();
+        let completion = chat_completion_stream(&endpoint, &loadout(&[]), &[ChatMessage::user("hi")], &[], |delta| {
+            deltas.push(delta.to_string())
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(deltas, ["hel", "lo"]);
+        assert_eq!(completion.message.content, "hello");
+        assert_eq!(completion.usage.map(|usage| usage.completion_tokens), Some(4));
+    }
+}
@@ -0,0 +1,169 @@
+// NixDeck 2133 - AI System Context
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+// Sections a loadout can opt into, in the order they appear in the prompt
+pub const SECTIONS: &[&str] = &["system", "compositor", "rice", "services", "cron"];
+
+pub const MAX_CHARS_RANGE: (usize, usize) = (100, 20_000);
+
+// Window managers and compositors recognised in the process list
+const KNOWN_WMS: &[&str] = &[
+    "Hyprland", "sway", "river", "wayfire", "niri", "labwc", "i3", "bspwm", "awesome",
+    "openbox", "qtile", "xmonad", "herbstluftwm", "dwm", "kwin_wayland", "kwin_x11",
+    "gnome-shell", "picom",
+];
+
+// Per-loadout opt-in for describing this machine in the system prompt
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContextSettings {
+    pub sections: Vec<String>,
+    // Cap on each section's text, in characters
+    #[serde(default = "default_max_chars")]
+    pub max_chars: usize,
+}
+
+fn default_max_chars() -> usize {
+    2000
+}
+
+impl ContextSettings {
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        for section in &self.sections {
+            if !SECTIONS.contains(&section.as_str()) {
+                problems.push(format!("unknown context section '{}'", section));
+            }
+        }
+        let (min_chars, max_chars) = MAX_CHARS_RANGE;
+        if !(min_chars..=max_chars).contains(&self.max_chars) {
+            problems.push(format!(
+                "context max_chars {} is outside {}-{}",
+                self.max_chars, min_chars, max_chars
+            ));
+        }
+
+        problems
+    }
+}
+
+// Render the enabled sections as a block to append to the system prompt.
+// A section that fails to gather is noted rather than failing the request.
+pub async fn build(settings: &ContextSettings) -> String {
+    let mut blocks = Vec::new();
+
+    for &section in SECTIONS {
+        if !settings.sections.iter().any(|s| s == section) {
+            continue;
+        }
+
+        let (title, body) = match section {
+            "system" => ("System", system_section().await),
+            "compositor" => ("Compositor / WM", Ok(compositor_section())),
+            "rice" => ("Rice components with configs", Ok(rice_section())),
+            "services" => ("Running user services", services_section().await),
+            "cron" => ("Crontab", cron_section().await),
+            _ => continue,
+        };
+
+        let body = body.unwrap_or_else(|e| format!("(unavailable: {})", e));
+        blocks.push(format!("## {}\n{}", title, truncate(&body, settings.max_chars)));
+    }
+
+    if blocks.is_empty() {
+        return String::new();
+    }
+
+    format!("# Current system\n\n{}", blocks.join("\n\n"))
+}
+
+async fn system_section() -> Result<String, String> {
+    let info = crate::commands::get_system_info().await?;
+    Ok(format!(
+        "hostname: {}\nkernel: {}\ndistro: {}\nuptime: {}",
+        info.hostname, info.kernel, info.distro, info.uptime
+    ))
+}
+
+fn compositor_section() -> String {
+    let mut lines = Vec::new();
+
+    for var in ["XDG_CURRENT_DESKTOP", "XDG_SESSION_TYPE", "DESKTOP_SESSION"] {
+        if let Ok(value) = std::env::var(var) {
+            lines.push(format!("{}: {}", var, value));
+        }
+    }
+
+    let running = running_wms();
+    if running.is_empty() {
+        lines.push("running: (no known compositor or WM found)".to_string());
+    } else {
+        lines.push(format!("running: {}", running.join(", ")));
+    }
+
+    lines.join("\n")
+}
+
+// Names from /proc/<pid>/comm matching KNOWN_WMS. comm is cut at 15
+// characters, so compare against the truncated name.
+fn running_wms() -> Vec<String> {
+    let entries = match fs::read_dir("/proc") {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut found: Vec<String> = Vec::new();
+    for entry in entries.flatten() {
+        let comm = match fs::read_to_string(entry.path().join("comm")) {
+            Ok(comm) => comm.trim().to_string(),
+            Err(_) => continue,
+        };
+        let matched = KNOWN_WMS
+            .iter()
+            .find(|wm| wm.chars().take(15).collect::<String>() == comm);
+        if let Some(wm) = matched {
+            if !found.iter().any(|f| f == wm) {
+                found.push(wm.to_string());
+            }
+        }
+    }
+
+    found.sort();
+    found
+}
+
+fn rice_section() -> String {
+    let components = crate::rice::installed_components();
+    if components.is_empty() {
+        "(none)".to_string()
+    } else {
+        components.join(", ")
+    }
+}
+
+async fn services_section() -> Result<String, String> {
+    let services: Vec<String> = crate::daemon::list_services()
+        .await?
+        .into_iter()
+        .filter(|line| line.contains(" running "))
+        .filter_map(|line| line.split_whitespace().find(|word| word.ends_with(".service")).map(str::to_string))
+        .collect();
+
+    Ok(if services.is_empty() { "(none)".to_string() } else { services.join("\n") })
+}
+
+async fn cron_section() -> Result<String, String> {
+    let jobs = crate::cron_mod::list_jobs().await?;
+    Ok(if jobs.is_empty() { "(no cron jobs)".to_string() } else { jobs.join("\n") })
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+
+    let mut truncated: String = text.chars().take(max_chars).collect();
+    truncated.push_str("\n[... truncated]");
+    truncated
+}
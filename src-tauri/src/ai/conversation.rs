@@ -0,0 +1,228 @@
+// NixDeck 2133 - AI Conversation Sessions
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+use super::client::ChatMessage;
+
+// Tokens held back for the note that stands in for trimmed turns
+const SUMMARY_RESERVE: usize = 64;
+const SUMMARY_MAX_CHARS: usize = 240;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Conversation {
+    pub id: String,
+    pub title: String,
+    pub loadout: String,
+    pub created: String,
+    pub updated: String,
+    pub messages: Vec<ChatMessage>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConversationSummary {
+    pub id: String,
+    pub title: String,
+    pub loadout: String,
+    pub updated: String,
+    pub message_count: usize,
+}
+
+impl Conversation {
+    pub fn record_exchange(&mut self, user: String, assistant: String) {
+        self.messages.push(ChatMessage::user(user));
+        self.messages.push(ChatMessage::assistant(assistant));
+        self.updated = chrono::Local::now().to_rfc3339();
+    }
+}
+
+pub async fn create_conversation(title: Option<String>, loadout: String) -> Result<Conversation, String> {
+    let now = chrono::Local::now();
+    let id = format!("conv-{}", now.format("%Y%m%d-%H%M%S-%3f"));
+
+    let conversation = Conversation {
+        title: title
+            .filter(|t| !t.trim().is_empty())
+            .unwrap_or_else(|| format!("Session {}", now.format("%Y-%m-%d %H:%M"))),
+        id,
+        loadout,
+        created: now.to_rfc3339(),
+        updated: now.to_rfc3339(),
+        messages: Vec::new(),
+    };
+
+    write_conversation(&conversation)?;
+    Ok(conversation)
+}
+
+pub async fn list_conversations() -> Result<Vec<ConversationSummary>, String> {
+    let conversations_dir = get_conversations_dir();
+
+    if !conversations_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let entries = fs::read_dir(conversations_dir)
+        .map_err(|e| format!("Failed to read conversations directory: {}", e))?;
+
+    let mut conversations = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+            continue;
+        }
+
+        // Skip unreadable files rather than failing the whole listing
+        let conversation: Conversation = match fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+        {
+            Some(conversation) => conversation,
+            None => continue,
+        };
+
+        conversations.push(ConversationSummary {
+            message_count: conversation.messages.len(),
+            id: conversation.id,
+            title: conversation.title,
+            loadout: conversation.loadout,
+            updated: conversation.updated,
+        });
+    }
+
+    // Most recently active first
+    conversations.sort_by(|a, b| b.updated.cmp(&a.updated));
+    Ok(conversations)
+}
+
+pub async fn load_conversation(id: String) -> Result<Conversation, String> {
+    read_conversation(&id)
+}
+
+pub async fn delete_conversation(id: String) -> Result<(), String> {
+    let path = get_conversation_path(&id)?;
+
+    if !path.exists() {
+        return Err(format!("Conversation '{}' not found", id));
+    }
+
+    fs::remove_file(path)
+        .map_err(|e| format!("Failed to delete conversation: {}", e))
+}
+
+pub async fn rename_conversation(id: String, title: String) -> Result<(), String> {
+    if title.trim().is_empty() {
+        return Err("Conversation title cannot be empty".to_string());
+    }
+
+    let mut conversation = read_conversation(&id)?;
+    conversation.title = title.trim().to_string();
+    write_conversation(&conversation)
+}
+
+pub(super) fn read_conversation(id: &str) -> Result<Conversation, String> {
+    let path = get_conversation_path(id)?;
+
+    if !path.exists() {
+        return Err(format!("Conversation '{}' not found", id));
+    }
+
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read conversation: {}", e))?;
+
+    serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse conversation '{}': {}", id, e))
+}
+
+pub(super) fn write_conversation(conversation: &Conversation) -> Result<(), String> {
+    let path = get_conversation_path(&conversation.id)?;
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create conversations directory: {}", e))?;
+    }
+
+    let json = serde_json::to_string_pretty(conversation)
+        .map_err(|e| format!("Failed to serialize conversation: {}", e))?;
+
+    fs::write(path, json)
+        .map_err(|e| format!("Failed to write conversation: {}", e))
+}
+
+// Rough token estimate (~4 characters per token plus per-message overhead).
+// Good enough for budgeting without shipping a tokenizer.
+pub(super) fn estimate_tokens(message: &ChatMessage) -> usize {
+    message.content.chars().count().div_ceil(4) + 4
+}
+
+// Keep the newest turns of `history` that fit into `budget` tokens alongside
+// the `fixed` messages (system prompt and the new user turn). Older turns are
+// dropped and replaced by a short system note describing what was cut.
+pub(super) fn trim_history(history: &[ChatMessage], fixed: &[ChatMessage], budget: usize) -> Vec<ChatMessage> {
+    let fixed_tokens: usize = fixed.iter().map(estimate_tokens).sum();
+    let total: usize = history.iter().map(estimate_tokens).sum();
+
+    if fixed_tokens + total <= budget {
+        return history.to_vec();
+    }
+
+    let available = budget.saturating_sub(fixed_tokens + SUMMARY_RESERVE);
+
+    let mut used = 0;
+    let mut keep_from = history.len();
+    for (index, message) in history.iter().enumerate().rev() {
+        let cost = estimate_tokens(message);
+        if used + cost > available {
+            break;
+        }
+        used += cost;
+        keep_from = index;
+    }
+
+    // Never open the kept window on an assistant turn
+    while keep_from < history.len() && history[keep_from].role == "assistant" {
+        keep_from += 1;
+    }
+
+    let mut trimmed = Vec::with_capacity(history.len() - keep_from + 1);
+    if keep_from > 0 {
+        trimmed.push(summarize_dropped(&history[..keep_from]));
+    }
+    trimmed.extend_from_slice(&history[keep_from..]);
+    trimmed
+}
+
+fn summarize_dropped(dropped: &[ChatMessage]) -> ChatMessage {
+    let topics: Vec<String> = dropped
+        .iter()
+        .filter(|message| message.role == "user")
+        .map(|message| {
+            let line = message.content.lines().next().unwrap_or("").trim();
+            line.chars().take(60).collect()
+        })
+        .collect();
+
+    let mut note = format!(
+        "[{} earlier messages omitted to fit the context budget. Earlier topics: {}]",
+        dropped.len(),
+        topics.join(" | ")
+    );
+    if note.chars().count() > SUMMARY_MAX_CHARS {
+        note = note.chars().take(SUMMARY_MAX_CHARS - 4).collect::<String>() + "...]";
+    }
+
+    ChatMessage::system(note)
+}
+
+fn get_conversations_dir() -> PathBuf {
+    let home = dirs::home_dir().expect("Could not find home directory");
+    home.join(".nixdeck").join("conversations")
+}
+
+fn get_conversation_path(id: &str) -> Result<PathBuf, String> {
+    if id.is_empty() || id.contains('/') || id.contains("..") {
+        return Err(format!("Invalid conversation ID: {}", id));
+    }
+
+    Ok(get_conversations_dir().join(format!("{}.json", id)))
+}
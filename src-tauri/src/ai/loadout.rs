@@ -0,0 +1,393 @@
+// NixDeck 2133 - AI Loadouts
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::catalog;
+use super::context::ContextSettings;
+use super::provider::ProviderKind;
+use super::retrieval::RetrievalSettings;
+use super::retry::RetryPolicy;
+use super::tools;
+
+// Name the AI console uses before any loadout has been activated
+pub const DEFAULT_LOADOUT: &str = "DEFAULT";
+
+pub const LOADOUT_EXTENSION: &str = "nd2133-loadout";
+
+// Current on-disk schema version. Files written before versioning are v1.
+pub const LOADOUT_VERSION: u32 = 2;
+
+pub const TEMPERATURE_RANGE: (f32, f32) = (0.0, 2.0);
+pub const MAX_TOKENS_RANGE: (u32, u32) = (1, 200_000);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AILoadout {
+    #[serde(default = "current_version")]
+    pub version: u32,
+    pub name: String,
+    #[serde(default)]
+    pub provider: ProviderKind,
+    // Overrides the provider's default base URL, e.g. http://localhost:11434
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<String>,
+    pub model: String,
+    // Tried in order when `model` is rate limited or its provider errors
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fallback_models: Vec<String>,
+    // May use {{variable}} placeholders and {{> template}} includes
+    pub system_prompt: String,
+    // Values for the system prompt's own placeholders; override the built-ins
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub prompt_variables: BTreeMap<String, String>,
+    pub temperature: f32,
+    pub max_tokens: u32,
+    pub tools_enabled: Vec<String>,
+    // USD per calendar month; requests are refused once it has been spent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub monthly_budget: Option<f64>,
+    // Operation step kinds (tool names, or "shell") that wait for confirmation.
+    // None means every mutating tool plus shell steps.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub risky_steps: Option<Vec<String>>,
+    // Opt-in description of this machine appended to the system prompt
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<ContextSettings>,
+    // Opt-in attachment of local files relevant to each message
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retrieval: Option<RetrievalSettings>,
+    // None uses the default retry policy
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry: Option<RetryPolicy>,
+}
+
+impl Default for AILoadout {
+    fn default() -> Self {
+        AILoadout {
+            version: LOADOUT_VERSION,
+            name: DEFAULT_LOADOUT.to_string(),
+            provider: ProviderKind::default(),
+            endpoint: None,
+            model: "anthropic/claude-3.5-sonnet".to_string(),
+            fallback_models: Vec::new(),
+            system_prompt: "You are a helpful AI assistant for Linux system configuration.".to_string(),
+            prompt_variables: BTreeMap::new(),
+            temperature: 0.7,
+            max_tokens: 4096,
+            tools_enabled: Vec::new(),
+            monthly_budget: None,
+            risky_steps: None,
+            context: None,
+            retrieval: None,
+            retry: None,
+        }
+    }
+}
+
+impl AILoadout {
+    // Collect every problem at once so the loadout manager can show them together
+    pub fn validate(&self) -> Result<(), String> {
+        let mut problems = Vec::new();
+
+        if let Err(e) = validate_name(&self.name) {
+            problems.push(e);
+        }
+        if let Some(endpoint) = &self.endpoint {
+            if !endpoint.starts_with("http://") && !endpoint.starts_with("https://") {
+                problems.push(format!("endpoint '{}' must be an http(s) URL", endpoint));
+            }
+        }
+        if self.model.trim().is_empty() {
+            problems.push("model must not be empty".to_string());
+        }
+        if self.fallback_models.iter().any(|model| model.trim().is_empty()) {
+            problems.push("fallback models must not be empty".to_string());
+        }
+        let (min_temp, max_temp) = TEMPERATURE_RANGE;
+        if !(min_temp..=max_temp).contains(&self.temperature) {
+            problems.push(format!(
+                "temperature {} is outside {:.1}-{:.1}",
+                self.temperature, min_temp, max_temp
+            ));
+        }
+        let (min_tokens, max_tokens) = MAX_TOKENS_RANGE;
+        if !(min_tokens..=max_tokens).contains(&self.max_tokens) {
+            problems.push(format!(
+                "max_tokens {} is outside {}-{}",
+                self.max_tokens, min_tokens, max_tokens
+            ));
+        }
+        if let Some(budget) = self.monthly_budget {
+            if !budget.is_finite() || budget < 0.0 {
+                problems.push(format!("monthly_budget {} must be a non-negative amount", budget));
+            }
+        }
+        for kind in self.risky_steps.iter().flatten() {
+            if kind != crate::operations::SHELL_STEP && !tools::is_known_command(kind) {
+                problems.push(format!("unknown risky step '{}'", kind));
+            }
+        }
+        if let Some(context) = &self.context {
+            problems.extend(context.problems());
+        }
+        if let Some(retrieval) = &self.retrieval {
+            problems.extend(retrieval.problems());
+        }
+        if let Some(retry) = &self.retry {
+            problems.extend(retry.problems());
+        }
+        for tool in &self.tools_enabled {
+            if !tools::is_known_group(tool) {
+                problems.push(format!("unknown tool '{}'", tool));
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("Invalid loadout '{}': {}", self.name, problems.join("; ")))
+        }
+    }
+
+    // The primary model followed by its fallbacks, without repeats
+    pub fn models(&self) -> Vec<String> {
+        let mut models = vec![self.model.clone()];
+        for model in &self.fallback_models {
+            if !models.contains(model) {
+                models.push(model.clone());
+            }
+        }
+        models
+    }
+}
+
+pub fn default_risky_steps() -> Vec<String> {
+    let mut risky = tools::mutating_commands();
+    risky.push(crate::operations::SHELL_STEP.to_string());
+    risky
+}
+
+fn current_version() -> u32 {
+    LOADOUT_VERSION
+}
+
+pub async fn load_loadout(name: String) -> Result<AILoadout, String> {
+    let loadout_path = get_loadout_path(&name)?;
+
+    if !loadout_path.exists() {
+        return Err(format!("Loadout '{}' not found", name));
+    }
+
+    read_loadout_file(&name, &loadout_path)
+}
+
+// Returns non-fatal warnings, e.g. a model the provider no longer lists
+pub async fn save_loadout(name: String, mut config: AILoadout) -> Result<Vec<String>, String> {
+    // The file name is authoritative; keep the embedded name in sync with it
+    config.name = name;
+    config.version = LOADOUT_VERSION;
+    write_loadout(&config)?;
+    Ok(catalog::model_warnings(&config))
+}
+
+pub async fn check_loadout(name: String) -> Result<Vec<String>, String> {
+    let loadout = read_loadout(&name)?;
+    Ok(catalog::model_warnings(&loadout))
+}
+
+pub async fn list_loadouts() -> Result<Vec<String>, String> {
+    let loadouts_dir = get_loadouts_dir();
+
+    if !loadouts_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let entries = fs::read_dir(loadouts_dir)
+        .map_err(|e| format!("Failed to read loadouts directory: {}", e))?;
+
+    let mut loadouts = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some(LOADOUT_EXTENSION) {
+            continue;
+        }
+        if let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) {
+            loadouts.push(name.to_string());
+        }
+    }
+
+    loadouts.sort();
+    Ok(loadouts)
+}
+
+pub async fn delete_loadout(name: String) -> Result<(), String> {
+    let loadout_path = get_loadout_path(&name)?;
+
+    if !loadout_path.exists() {
+        return Err(format!("Loadout '{}' not found", name));
+    }
+
+    fs::remove_file(loadout_path)
+        .map_err(|e| format!("Failed to delete loadout: {}", e))
+}
+
+pub async fn rename_loadout(name: String, new_name: String) -> Result<(), String> {
+    let old_path = get_loadout_path(&name)?;
+    let new_path = get_loadout_path(&new_name)?;
+
+    if !old_path.exists() {
+        return Err(format!("Loadout '{}' not found", name));
+    }
+    if new_path.exists() {
+        return Err(format!("Loadout '{}' already exists", new_name));
+    }
+
+    let mut loadout = read_loadout_file(&name, &old_path)?;
+    loadout.name = new_name;
+    write_loadout(&loadout)?;
+
+    fs::remove_file(old_path)
+        .map_err(|e| format!("Failed to remove old loadout file: {}", e))
+}
+
+pub async fn duplicate_loadout(name: String, new_name: String) -> Result<(), String> {
+    let new_path = get_loadout_path(&new_name)?;
+
+    if new_path.exists() {
+        return Err(format!("Loadout '{}' already exists", new_name));
+    }
+
+    let mut loadout = read_loadout(&name)?;
+    loadout.name = new_name;
+    write_loadout(&loadout)
+}
+
+// Resolve a loadout by name, falling back to the built-in default
+pub(super) fn read_loadout(name: &str) -> Result<AILoadout, String> {
+    let loadout_path = get_loadout_path(name)?;
+
+    if !loadout_path.exists() {
+        if name == DEFAULT_LOADOUT {
+            return Ok(AILoadout::default());
+        }
+        return Err(format!("Loadout '{}' not found", name));
+    }
+
+    read_loadout_file(name, &loadout_path)
+}
+
+fn read_loadout_file(name: &str, path: &Path) -> Result<AILoadout, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to load loadout: {}", e))?;
+
+    let (loadout, changed) = parse_loadout(name, &content)?;
+
+    // Persist upgrades so the file only has to be migrated once
+    if changed {
+        write_loadout(&loadout)?;
+    }
+
+    Ok(loadout)
+}
+
+// Parse, migrate and validate loadout JSON. Also reports whether migration
+// changed anything.
+pub(super) fn parse_loadout(name: &str, content: &str) -> Result<(AILoadout, bool), String> {
+    let raw: Value = serde_json::from_str(content)
+        .map_err(|e| format!("Failed to parse loadout '{}': {}", name, e))?;
+
+    let (migrated, changed) = migrate(raw, name)?;
+
+    let mut loadout: AILoadout = serde_json::from_value(migrated)
+        .map_err(|e| format!("Failed to parse loadout '{}': {}", name, e))?;
+    loadout.name = name.to_string();
+    loadout.validate()?;
+
+    Ok((loadout, changed))
+}
+
+// Bring an older loadout document up to LOADOUT_VERSION. Returns the upgraded
+// document and whether anything changed.
+fn migrate(mut raw: Value, name: &str) -> Result<(Value, bool), String> {
+    let object = raw
+        .as_object_mut()
+        .ok_or_else(|| format!("Loadout '{}' is not a JSON object", name))?;
+
+    let version = object
+        .get("version")
+        .and_then(Value::as_u64)
+        .unwrap_or(1) as u32;
+
+    if version > LOADOUT_VERSION {
+        return Err(format!(
+            "Loadout '{}' uses schema v{}, newer than this build supports (v{})",
+            name, version, LOADOUT_VERSION
+        ));
+    }
+
+    if version < 2 {
+        // v1 files came straight from the frontend: fields could be missing and
+        // tool names were free-form checkbox values
+        let defaults = AILoadout::default();
+        object.entry("name").or_insert_with(|| Value::from(name));
+        object.entry("system_prompt").or_insert_with(|| Value::from(defaults.system_prompt));
+        object.entry("temperature").or_insert_with(|| Value::from(defaults.temperature));
+        object.entry("max_tokens").or_insert_with(|| Value::from(defaults.max_tokens));
+
+        let tools: Vec<Value> = object
+            .get("tools_enabled")
+            .and_then(Value::as_array)
+            .map(|tools| {
+                tools
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .map(|tool| Value::from(tool.trim().to_lowercase()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        object.insert("tools_enabled".to_string(), Value::from(tools));
+    }
+
+    let changed = version != LOADOUT_VERSION;
+    object.insert("version".to_string(), Value::from(LOADOUT_VERSION));
+
+    Ok((raw, changed))
+}
+
+pub(super) fn write_loadout(loadout: &AILoadout) -> Result<(), String> {
+    loadout.validate()?;
+
+    let loadout_path = get_loadout_path(&loadout.name)?;
+    if let Some(parent) = loadout_path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create loadouts directory: {}", e))?;
+    }
+
+    let json = serde_json::to_string_pretty(loadout)
+        .map_err(|e| format!("Failed to serialize loadout: {}", e))?;
+
+    fs::write(loadout_path, json)
+        .map_err(|e| format!("Failed to save loadout: {}", e))
+}
+
+fn validate_name(name: &str) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("name must not be empty".to_string());
+    }
+    if name.contains('/') || name.contains('\\') || name.starts_with('.') {
+        return Err(format!("name '{}' contains invalid characters", name));
+    }
+    Ok(())
+}
+
+fn get_loadouts_dir() -> PathBuf {
+    let home = dirs::home_dir().expect("Could not find home directory");
+    home.join(".nixdeck").join("loadouts")
+}
+
+pub(super) fn get_loadout_path(name: &str) -> Result<PathBuf, String> {
+    validate_name(name).map_err(|e| format!("Invalid loadout {}", e))?;
+    Ok(get_loadouts_dir().join(format!("{}.{}", name, LOADOUT_EXTENSION)))
+}
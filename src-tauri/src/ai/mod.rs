@@ -1,70 +1,247 @@
 // NixDeck 2133 - AI Orchestration Module
-use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+mod bundle;
+mod catalog;
+mod client;
+mod context;
+mod conversation;
+mod loadout;
+mod prompts;
+mod proposal;
+mod provider;
+mod redact;
+mod retrieval;
+mod retry;
+mod stream;
+mod tools;
+mod usage;
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct AILoadout {
-    pub name: String,
+use serde::Serialize;
+use std::collections::HashMap;
+
+pub use bundle::{export_bundle, import_bundle, inspect_bundle, BundleItem, Resolution};
+pub use catalog::{list_models, ModelCatalog};
+use client::{AIError, ChatMessage, Endpoint};
+pub use conversation::{
+    create_conversation, delete_conversation, list_conversations, load_conversation,
+    rename_conversation, Conversation, ConversationSummary,
+};
+pub use loadout::{
+    check_loadout, delete_loadout, duplicate_loadout, list_loadouts, load_loadout, rename_loadout,
+    save_loadout, AILoadout,
+};
+pub use prompts::{list_templates, load_template, render_template, save_template, RenderedPrompt};
+pub use proposal::{propose_rice, RiceProposal};
+pub use provider::ProviderKind;
+pub use redact::{get_rules as get_redaction_rules, save_rules as save_redaction_rules, RedactionRules};
+pub use retrieval::{IndexStats, Snippet};
+pub use stream::{cancel_stream, stream_message};
+pub use tools::{is_known_command, resolve_approval};
+pub use usage::{summarize as summarize_usage, UsageBucket};
+
+// Upper bound on model/tool round-trips within a single exchange
+const MAX_TOOL_ROUNDS: usize = 8;
+
+// A final answer and the model that gave it, which may be a fallback
+#[derive(Debug, Clone, Serialize)]
+pub struct Reply {
+    pub content: String,
     pub model: String,
-    pub system_prompt: String,
-    pub temperature: f32,
-    pub max_tokens: u32,
-    pub tools_enabled: Vec<String>,
 }
 
-pub async fn send_message(message: String, loadout: String) -> Result<String, String> {
-    // TODO: Implement OpenRouter API integration
-    // This is a placeholder that will be expanded
-    Ok(format!("[AI Response Placeholder]\nReceived: {}\nLoadout: {}", message, loadout))
+// A loadout, its optional conversation session and the messages to send
+struct PreparedRequest {
+    loadout: AILoadout,
+    session: Option<Conversation>,
+    message: String,
+    messages: Vec<ChatMessage>,
 }
 
-pub async fn load_loadout(name: String) -> Result<String, String> {
-    let loadout_path = get_loadout_path(&name);
-    
-    if !loadout_path.exists() {
-        return Err(format!("Loadout '{}' not found", name));
+impl PreparedRequest {
+    async fn new(message: String, loadout: &str, conversation: Option<String>) -> Result<Self, String> {
+        let loadout = loadout::read_loadout(loadout)?;
+        let session = conversation
+            .map(|id| conversation::read_conversation(&id))
+            .transpose()?;
+
+        let mut system_prompt = build_system_prompt(&loadout).await?.text;
+        if let Some(settings) = &loadout.retrieval {
+            let snippets = retrieval::build(settings, &message).await;
+            if !snippets.is_empty() {
+                system_prompt = format!("{}\n\n{}", system_prompt, snippets).trim().to_string();
+            }
+        }
+
+        let mut messages = Vec::new();
+        if !system_prompt.is_empty() {
+            messages.push(ChatMessage::system(system_prompt));
+        }
+        let user = ChatMessage::user(message.clone());
+
+        if let Some(session) = &session {
+            // The system prompt and new turn always go out; history fills what's left
+            let mut pinned = messages.clone();
+            pinned.push(user.clone());
+            let history = conversation::trim_history(&session.messages, &pinned, loadout.max_tokens as usize);
+            messages.extend(history);
+        }
+        messages.push(user);
+
+        Ok(PreparedRequest { loadout, session, message, messages })
     }
 
-    std::fs::read_to_string(loadout_path)
-        .map_err(|e| format!("Failed to load loadout: {}", e))
-}
+    fn conversation_id(&self) -> Option<&str> {
+        self.session.as_ref().map(|session| session.id.as_str())
+    }
 
-pub async fn save_loadout(name: String, config: String) -> Result<(), String> {
-    let loadout_path = get_loadout_path(&name);
-    
-    std::fs::write(loadout_path, config)
-        .map_err(|e| format!("Failed to save loadout: {}", e))
+    // Append the exchange to the conversation, if there is one
+    fn finish(self, reply: &str) -> Result<(), String> {
+        if let Some(mut session) = self.session {
+            session.record_exchange(self.message, reply.to_string());
+            conversation::write_conversation(&session)?;
+        }
+        Ok(())
+    }
 }
 
-pub async fn list_loadouts() -> Result<Vec<String>, String> {
-    let loadouts_dir = get_loadouts_dir();
-    
-    if !loadouts_dir.exists() {
-        return Ok(Vec::new());
+// The loadout's system prompt with templates filled in and, if enabled, the
+// system context appended
+async fn build_system_prompt(loadout: &AILoadout) -> Result<RenderedPrompt, String> {
+    let mut prompt = prompts::render(&loadout.system_prompt, &loadout.prompt_variables).await?;
+    prompt.text = prompt.text.trim().to_string();
+
+    if let Some(settings) = &loadout.context {
+        let system_context = context::build(settings).await;
+        if !system_context.is_empty() {
+            prompt.text = format!("{}\n\n{}", prompt.text, system_context).trim().to_string();
+        }
     }
 
-    let entries = std::fs::read_dir(loadouts_dir)
-        .map_err(|e| format!("Failed to read loadouts directory: {}", e))?;
+    Ok(prompt)
+}
+
+pub async fn refresh_index() -> Result<IndexStats, String> {
+    retrieval::refresh().await
+}
+
+pub async fn search_index(
+    query: String,
+    sources: Option<Vec<String>>,
+    limit: usize,
+) -> Result<Vec<Snippet>, String> {
+    let sources = sources.unwrap_or_else(|| retrieval::SOURCES.iter().map(|s| s.to_string()).collect());
+    retrieval::search(query, sources, limit).await
+}
+
+// What a chat under `loadout` would send as its system prompt
+pub async fn preview_system_prompt(loadout: String) -> Result<RenderedPrompt, String> {
+    build_system_prompt(&loadout::read_loadout(&loadout)?).await
+}
+
+pub async fn send_message(
+    window: tauri::Window,
+    message: String,
+    loadout: String,
+    conversation: Option<String>,
+) -> Result<Reply, String> {
+    let request = PreparedRequest::new(message, &loadout, conversation).await?;
+
+    // Tool approval prompts still need an ID to correlate against
+    let request_id = format!("req-{}", chrono::Local::now().timestamp_millis());
+    let reply = run_exchange(&request, &window, &request_id, false).await?;
+
+    request.finish(&reply.content)?;
+    Ok(reply)
+}
+
+// Drive the model until it gives a final answer, running the tool calls it
+// makes along the way. Mutating tools prompt the user through `window`.
+async fn run_exchange(
+    request: &PreparedRequest,
+    window: &tauri::Window,
+    request_id: &str,
+    streaming: bool,
+) -> Result<Reply, AIError> {
+    let endpoint = Endpoint::for_loadout(&request.loadout).await;
+    let schemas = tools::schemas_for(&request.loadout.tools_enabled);
+    let mut messages = request.messages.clone();
+
+    // Nothing leaves the machine without passing through the redactor
+    let mut redactor = redact::Redactor::load().map_err(AIError::Redaction)?;
+    let found = redactor.redact_messages(&mut messages);
+    report_redactions(window, request_id, found);
 
-    let mut loadouts = Vec::new();
-    for entry in entries {
-        if let Ok(entry) = entry {
-            if let Some(name) = entry.file_name().to_str() {
-                if name.ends_with(".nd2133-loadout") {
-                    loadouts.push(name.trim_end_matches(".nd2133-loadout").to_string());
+    for _ in 0..MAX_TOOL_ROUNDS {
+        // Every round is a new paid request, so the budget is checked each time
+        usage::check_budget(&request.loadout)?;
+
+        let completion = if streaming {
+            // Chunks show the real values, as the final reply does
+            let mut held = String::new();
+            let completion = client::chat_completion_stream(&endpoint, &request.loadout, &messages, &schemas, |delta| {
+                let ready = redactor.restore_delta(&mut held, delta);
+                if !ready.is_empty() {
+                    stream::emit_chunk(window, request_id, &ready);
                 }
+            })
+            .await?;
+            // Whatever was held back never became a placeholder
+            if !held.is_empty() {
+                stream::emit_chunk(window, request_id, &redactor.restore(&held));
             }
+            completion
+        } else {
+            client::chat_completion(&endpoint, &request.loadout, &messages, &schemas).await?
+        };
+
+        if let Some(tokens) = completion.usage {
+            // Accounting is best-effort; a ledger problem shouldn't lose the reply
+            let _ = usage::record(&request.loadout, &completion.model, request.conversation_id(), tokens);
+        }
+
+        let turn = completion.message;
+        if turn.requested_tools().is_empty() {
+            return Ok(Reply {
+                content: redactor.restore(&turn.content),
+                model: completion.model,
+            });
+        }
+
+        let calls = turn.requested_tools().to_vec();
+        messages.push(turn);
+        for mut call in calls {
+            // Tools run locally, so they get the real values back
+            call.function.arguments = redactor.restore(&call.function.arguments);
+            let output = tools::run_call(window, request_id, &request.loadout, &call).await;
+
+            let (output, found) = redactor.redact(&output, "tool");
+            report_redactions(window, request_id, found);
+            messages.push(ChatMessage::tool(call.id, output));
         }
     }
 
-    Ok(loadouts)
+    Err(AIError::ToolRoundsExceeded(MAX_TOOL_ROUNDS))
+}
+
+fn report_redactions(window: &tauri::Window, request_id: &str, redactions: Vec<redact::Redaction>) {
+    if redactions.is_empty() {
+        return;
+    }
+    let _ = window.emit(redact::EVENT_REDACTIONS, redact::RedactionEvent {
+        request_id: request_id.to_string(),
+        redactions,
+    });
 }
 
-fn get_loadouts_dir() -> PathBuf {
-    let home = dirs::home_dir().expect("Could not find home directory");
-    home.join(".nixdeck").join("loadouts")
+// Step kinds that need confirmation in operations started under `loadout`
+pub fn risky_steps(loadout: Option<&str>) -> Result<Vec<String>, String> {
+    let loadout = loadout::read_loadout(loadout.unwrap_or(loadout::DEFAULT_LOADOUT))?;
+    Ok(loadout.risky_steps.unwrap_or_else(loadout::default_risky_steps))
 }
 
-fn get_loadout_path(name: &str) -> PathBuf {
-    get_loadouts_dir().join(format!("{}.nd2133-loadout", name))
+pub async fn run_command(
+    name: &str,
+    args: &HashMap<String, String>,
+    loadout: Option<&str>,
+) -> Result<String, String> {
+    tools::run_command(name, args, loadout.unwrap_or(loadout::DEFAULT_LOADOUT)).await
 }
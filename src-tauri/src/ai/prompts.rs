@@ -0,0 +1,214 @@
+// NixDeck 2133 - Prompt Templates
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+pub const TEMPLATE_EXTENSION: &str = "md";
+
+// `{{> name}}` includes nest at most this deep, which also stops cycles
+const MAX_INCLUDE_DEPTH: usize = 8;
+
+pub const BUILTIN_VARIABLES: &[&str] = &["hostname", "distro", "kernel", "user", "date", "active_container"];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RenderedPrompt {
+    pub text: String,
+    // Placeholders with no value; they are left in the text as written
+    pub missing: Vec<String>,
+}
+
+pub async fn list_templates() -> Result<Vec<String>, String> {
+    let prompts_dir = get_prompts_dir();
+
+    if !prompts_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let entries = fs::read_dir(prompts_dir)
+        .map_err(|e| format!("Failed to read prompts directory: {}", e))?;
+
+    let mut templates = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some(TEMPLATE_EXTENSION) {
+            continue;
+        }
+        if let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) {
+            templates.push(name.to_string());
+        }
+    }
+
+    templates.sort();
+    Ok(templates)
+}
+
+pub async fn load_template(name: String) -> Result<String, String> {
+    read_template(&name)
+}
+
+pub async fn save_template(name: String, content: String) -> Result<(), String> {
+    let template_path = get_template_path(&name)?;
+    if let Some(parent) = template_path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create prompts directory: {}", e))?;
+    }
+
+    fs::write(template_path, content)
+        .map_err(|e| format!("Failed to save prompt template: {}", e))
+}
+
+pub async fn render_template(
+    name: String,
+    variables: BTreeMap<String, String>,
+) -> Result<RenderedPrompt, String> {
+    render(&read_template(&name)?, &variables).await
+}
+
+// Fill `{{variable}}` placeholders and `{{> template}}` includes in `text`.
+// Caller-supplied variables override the built-ins.
+pub async fn render(text: &str, variables: &BTreeMap<String, String>) -> Result<RenderedPrompt, String> {
+    let expanded = expand_includes(text, 0)?;
+
+    let mut values = BTreeMap::new();
+    if placeholders(&expanded).iter().any(|name| BUILTIN_VARIABLES.contains(&name.as_str())) {
+        values = builtin_values().await;
+    }
+    values.extend(variables.iter().map(|(key, value)| (key.clone(), value.clone())));
+
+    let mut missing = Vec::new();
+    let mut output = String::with_capacity(expanded.len());
+    let mut rest = expanded.as_str();
+
+    while let Some(open) = rest.find("{{") {
+        let close = match rest[open..].find("}}") {
+            Some(offset) => open + offset,
+            None => break,
+        };
+        output.push_str(&rest[..open]);
+
+        let name = rest[open + 2..close].trim();
+        match values.get(name) {
+            Some(value) => output.push_str(value),
+            None => {
+                if !missing.iter().any(|m| m == name) {
+                    missing.push(name.to_string());
+                }
+                output.push_str(&rest[open..close + 2]);
+            }
+        }
+        rest = &rest[close + 2..];
+    }
+    output.push_str(rest);
+
+    Ok(RenderedPrompt { text: output, missing })
+}
+
+fn expand_includes(text: &str, depth: usize) -> Result<String, String> {
+    if !text.contains("{{>") {
+        return Ok(text.to_string());
+    }
+    if depth >= MAX_INCLUDE_DEPTH {
+        return Err("Prompt template includes nest too deeply (is there a cycle?)".to_string());
+    }
+
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(open) = rest.find("{{>") {
+        let close = match rest[open..].find("}}") {
+            Some(offset) => open + offset,
+            None => break,
+        };
+        output.push_str(&rest[..open]);
+
+        let name = rest[open + 3..close].trim();
+        output.push_str(&expand_includes(&read_template(name)?, depth + 1)?);
+        rest = &rest[close + 2..];
+    }
+    output.push_str(rest);
+
+    Ok(output)
+}
+
+// Names of the templates `text` includes directly
+pub(super) fn includes(text: &str) -> Vec<String> {
+    placeholders(text)
+        .into_iter()
+        .filter_map(|name| name.strip_prefix('>').map(|name| name.trim().to_string()))
+        .collect()
+}
+
+// Point `{{> old}}` includes at `new` instead
+pub(super) fn rename_includes(text: &str, old: &str, new: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(open) = rest.find("{{>") {
+        let close = match rest[open..].find("}}") {
+            Some(offset) => open + offset,
+            None => break,
+        };
+        output.push_str(&rest[..open]);
+
+        if rest[open + 3..close].trim() == old {
+            output.push_str(&format!("{{{{> {}}}}}", new));
+        } else {
+            output.push_str(&rest[open..close + 2]);
+        }
+        rest = &rest[close + 2..];
+    }
+    output.push_str(rest);
+
+    output
+}
+
+fn placeholders(text: &str) -> Vec<String> {
+    text.split("{{")
+        .skip(1)
+        .filter_map(|part| part.split_once("}}").map(|(name, _)| name.trim().to_string()))
+        .collect()
+}
+
+async fn builtin_values() -> BTreeMap<String, String> {
+    let mut values = BTreeMap::new();
+
+    if let Ok(info) = crate::commands::get_system_info().await {
+        values.insert("hostname".to_string(), info.hostname);
+        values.insert("distro".to_string(), info.distro);
+        values.insert("kernel".to_string(), info.kernel);
+    }
+    if let Ok(user) = std::env::var("USER") {
+        values.insert("user".to_string(), user);
+    }
+    values.insert("date".to_string(), chrono::Local::now().format("%Y-%m-%d").to_string());
+    values.insert(
+        "active_container".to_string(),
+        crate::container::active().unwrap_or_else(|| "none".to_string()),
+    );
+
+    values
+}
+
+pub(super) fn read_template(name: &str) -> Result<String, String> {
+    let template_path = get_template_path(name)?;
+
+    if !template_path.exists() {
+        return Err(format!("Prompt template '{}' not found", name));
+    }
+
+    fs::read_to_string(template_path)
+        .map_err(|e| format!("Failed to read prompt template '{}': {}", name, e))
+}
+
+fn get_prompts_dir() -> PathBuf {
+    let home = dirs::home_dir().expect("Could not find home directory");
+    home.join(".nixdeck").join("prompts")
+}
+
+pub(super) fn get_template_path(name: &str) -> Result<PathBuf, String> {
+    if name.trim().is_empty() || name.contains('/') || name.contains('\\') || name.starts_with('.') {
+        return Err(format!("Invalid prompt template name '{}'", name));
+    }
+    Ok(get_prompts_dir().join(format!("{}.{}", name, TEMPLATE_EXTENSION)))
+}
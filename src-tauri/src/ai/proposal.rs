@@ -0,0 +1,79 @@
+// NixDeck 2133 - AI Rice Proposals
+use serde::Serialize;
+
+use super::PreparedRequest;
+
+// A suggested replacement config. Nothing is written until the user passes
+// `content` to apply_rice_config.
+#[derive(Debug, Clone, Serialize)]
+pub struct RiceProposal {
+    pub component: String,
+    pub content: String,
+    pub diff: crate::diff::FileDiff,
+    pub rationale: String,
+    // The model that wrote the proposal
+    pub model: String,
+}
+
+pub async fn propose_rice(
+    window: tauri::Window,
+    component: String,
+    request: String,
+    loadout: String,
+) -> Result<RiceProposal, String> {
+    let current = crate::rice::get_config(component.clone()).await?;
+
+    let prompt = format!(
+        "Here is my current {component} config:\n\n```\n{current}\n```\n\n\
+         Change it as follows: {request}\n\n\
+         Reply with a short explanation of what you changed and why, followed by \
+         the complete new config in a single fenced code block. Keep everything \
+         that the change doesn't touch, including comments.",
+    );
+
+    let mut prepared = PreparedRequest::new(prompt, &loadout, None).await?;
+    // A proposal only describes a change; it never gets tools to make one
+    prepared.loadout.tools_enabled.clear();
+
+    let request_id = format!("proposal-{}", chrono::Local::now().timestamp_millis());
+    let reply = super::run_exchange(&prepared, &window, &request_id, false).await?;
+
+    let (rationale, content) = split_reply(&reply.content)
+        .ok_or_else(|| "The model's reply did not contain a config block".to_string())?;
+
+    let diff = crate::diff::compare(
+        &current,
+        &content,
+        &format!("{} (current)", component),
+        &format!("{} (proposed)", component),
+        &crate::diff::DiffOptions::default(),
+    );
+
+    Ok(RiceProposal {
+        component,
+        content,
+        diff,
+        rationale,
+        model: reply.model,
+    })
+}
+
+// Split a reply into the prose around it and the last fenced code block
+fn split_reply(reply: &str) -> Option<(String, String)> {
+    let close = reply.rfind("```")?;
+    let open = reply[..close].rfind("```")?;
+
+    // Skip the info string (e.g. ```json) on the opening fence
+    let body_start = reply[open..].find('\n').map(|offset| open + offset + 1)?;
+    if body_start > close {
+        return None;
+    }
+
+    let mut content = reply[body_start..close].to_string();
+    if !content.ends_with('\n') {
+        content.push('\n');
+    }
+
+    let rationale = format!("{}{}", &reply[..open], &reply[close + 3..]).trim().to_string();
+    Some((rationale, content))
+}
@@ -0,0 +1,60 @@
+// NixDeck 2133 - AI Provider Backends
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::catalog::ModelInfo;
+use super::client::{AIError, ChatParams, Completion, StreamDelta};
+
+mod ollama;
+mod openai;
+
+// Wire-format adapter for one family of chat APIs. The HTTP round-trip itself
+// lives in the client; providers only shape requests and read responses.
+pub trait Provider: Send + Sync {
+    // Credential store entry holding this provider's API key
+    fn credential(&self) -> &'static str;
+    fn requires_key(&self) -> bool;
+    fn default_endpoint(&self) -> &'static str;
+    fn chat_path(&self) -> &'static str;
+
+    fn extra_headers(&self) -> &'static [(&'static str, &'static str)] {
+        &[]
+    }
+
+    fn request_body(&self, params: &ChatParams) -> Value;
+    fn parse_response(&self, body: Value) -> Result<Completion, AIError>;
+
+    // Parse one line of a streaming response; `None` for lines to skip
+    fn parse_stream_line(&self, line: &str) -> Result<Option<StreamDelta>, AIError>;
+
+    fn models_path(&self) -> &'static str;
+    fn parse_models(&self, body: Value) -> Result<Vec<ModelInfo>, AIError>;
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderKind {
+    #[default]
+    OpenRouter,
+    // Any server speaking the OpenAI chat-completions API (OpenAI, llama.cpp, vLLM...)
+    OpenAI,
+    Ollama,
+}
+
+impl ProviderKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ProviderKind::OpenRouter => "openrouter",
+            ProviderKind::OpenAI => "openai",
+            ProviderKind::Ollama => "ollama",
+        }
+    }
+
+    pub fn backend(self) -> &'static dyn Provider {
+        match self {
+            ProviderKind::OpenRouter => &openai::OPENROUTER,
+            ProviderKind::OpenAI => &openai::OPENAI_COMPATIBLE,
+            ProviderKind::Ollama => &ollama::Ollama,
+        }
+    }
+}
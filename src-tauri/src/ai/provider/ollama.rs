@@ -0,0 +1,203 @@
+// NixDeck 2133 - Ollama Provider (native /api/chat)
+use serde_json::{json, Value};
+
+use crate::ai::catalog::{ModelInfo, ModelPrice};
+use crate::ai::client::{
+    function_kind, AIError, ChatMessage, ChatParams, Completion, FunctionCall, StreamDelta,
+    ToolCall, ToolCallDelta, Usage,
+};
+use super::Provider;
+
+pub struct Ollama;
+
+impl Provider for Ollama {
+    // Plain Ollama needs no key; one is only sent if stored (e.g. behind a proxy)
+    fn credential(&self) -> &'static str {
+        "ollama"
+    }
+
+    fn requires_key(&self) -> bool {
+        false
+    }
+
+    fn default_endpoint(&self) -> &'static str {
+        "http://localhost:11434"
+    }
+
+    fn chat_path(&self) -> &'static str {
+        "api/chat"
+    }
+
+    fn request_body(&self, params: &ChatParams) -> Value {
+        let messages: Vec<Value> = params.messages.iter().map(to_ollama_message).collect();
+
+        let mut body = json!({
+            "model": params.model,
+            "messages": messages,
+            "stream": params.stream,
+            "options": {
+                "temperature": params.temperature,
+                "num_predict": params.max_tokens,
+            },
+        });
+        if !params.tools.is_empty() {
+            body["tools"] = json!(params.tools);
+        }
+        body
+    }
+
+    fn parse_response(&self, body: Value) -> Result<Completion, AIError> {
+        if let Some(error) = body.get("error").and_then(Value::as_str) {
+            return Err(AIError::InvalidResponse(error.to_string()));
+        }
+
+        let message = body
+            .get("message")
+            .ok_or_else(|| AIError::InvalidResponse("response contained no message".to_string()))?;
+
+        let mut reply = ChatMessage::assistant(
+            message.get("content").and_then(Value::as_str).unwrap_or_default(),
+        );
+        // Ollama tool calls carry no ID, so one is minted per call
+        let stamp = chrono::Local::now().timestamp_millis();
+        let calls: Vec<ToolCall> = tool_calls(message)
+            .into_iter()
+            .enumerate()
+            .map(|(index, (name, arguments))| ToolCall {
+                id: format!("call_{}_{}", stamp, index),
+                kind: function_kind(),
+                function: FunctionCall { name, arguments },
+            })
+            .collect();
+        if !calls.is_empty() {
+            reply.tool_calls = Some(calls);
+        }
+
+        Ok(Completion { message: reply, usage: usage(&body), model: String::new() })
+    }
+
+    // Newline-delimited JSON; every line carries a partial message and the
+    // last one has `"done": true`
+    fn parse_stream_line(&self, line: &str) -> Result<Option<StreamDelta>, AIError> {
+        if line.is_empty() {
+            return Ok(None);
+        }
+
+        let chunk: Value = serde_json::from_str(line)
+            .map_err(|e| AIError::InvalidResponse(e.to_string()))?;
+
+        if let Some(error) = chunk.get("error").and_then(Value::as_str) {
+            return Err(AIError::InvalidResponse(error.to_string()));
+        }
+
+        let mut delta = StreamDelta {
+            done: chunk.get("done").and_then(Value::as_bool).unwrap_or(false),
+            usage: usage(&chunk),
+            ..Default::default()
+        };
+
+        if let Some(message) = chunk.get("message") {
+            delta.content = message.get("content").and_then(Value::as_str).map(str::to_string);
+            // Ollama sends each tool call complete in a single line, without
+            // an ID; the client numbers them across the whole stream
+            delta.tool_calls = tool_calls(message)
+                .into_iter()
+                .map(|(name, arguments)| ToolCallDelta {
+                    index: None,
+                    id: None,
+                    name: Some(name),
+                    arguments: Some(arguments),
+                })
+                .collect();
+        }
+
+        Ok(Some(delta))
+    }
+
+    fn models_path(&self) -> &'static str {
+        "api/tags"
+    }
+
+    // /api/tags lists locally pulled models; they cost nothing to run
+    fn parse_models(&self, body: Value) -> Result<Vec<ModelInfo>, AIError> {
+        let models = body
+            .get("models")
+            .and_then(Value::as_array)
+            .ok_or_else(|| AIError::InvalidResponse("model listing has no 'models' array".to_string()))?;
+
+        Ok(models
+            .iter()
+            .filter_map(|model| model.get("name")?.as_str())
+            .map(|name| ModelInfo {
+                id: name.to_string(),
+                name: None,
+                context_length: None,
+                pricing: Some(ModelPrice { prompt: 0.0, completion: 0.0 }),
+                supports_tools: false,
+            })
+            .collect())
+    }
+}
+
+// Token counts only appear on the final (`done`) response
+fn usage(body: &Value) -> Option<Usage> {
+    let prompt_tokens = body.get("prompt_eval_count").and_then(Value::as_u64);
+    let completion_tokens = body.get("eval_count").and_then(Value::as_u64);
+
+    if prompt_tokens.is_none() && completion_tokens.is_none() {
+        return None;
+    }
+
+    Some(Usage {
+        prompt_tokens: prompt_tokens.unwrap_or(0),
+        completion_tokens: completion_tokens.unwrap_or(0),
+    })
+}
+
+// Ollama takes tool arguments as JSON objects, not encoded strings
+fn to_ollama_message(message: &ChatMessage) -> Value {
+    let mut value = json!({
+        "role": message.role,
+        "content": message.content,
+    });
+
+    let calls: Vec<Value> = message
+        .requested_tools()
+        .iter()
+        .map(|call| {
+            let arguments: Value = serde_json::from_str(&call.function.arguments)
+                .unwrap_or_else(|_| json!({}));
+            json!({ "function": { "name": call.function.name, "arguments": arguments } })
+        })
+        .collect();
+    if !calls.is_empty() {
+        value["tool_calls"] = Value::from(calls);
+    }
+
+    value
+}
+
+// Name and encoded arguments of each tool call in a message
+fn tool_calls(message: &Value) -> Vec<(String, String)> {
+    message
+        .get("tool_calls")
+        .and_then(Value::as_array)
+        .map(|calls| {
+            calls
+                .iter()
+                .filter_map(|call| {
+                    let function = call.get("function")?;
+                    let name = function.get("name")?.as_str()?.to_string();
+                    let arguments = function
+                        .get("arguments")
+                        .map(|args| match args {
+                            Value::String(s) => s.clone(),
+                            other => other.to_string(),
+                        })
+                        .unwrap_or_else(|| "{}".to_string());
+                    Some((name, arguments))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
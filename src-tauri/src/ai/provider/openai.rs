@@ -0,0 +1,242 @@
+// NixDeck 2133 - OpenAI-Compatible Providers (OpenRouter, OpenAI, llama.cpp)
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::ai::catalog::{ModelInfo, ModelPrice};
+use crate::ai::client::{
+    AIError, ChatMessage, ChatParams, Completion, ErrorDetail, StreamDelta, ToolCallDelta, Usage,
+};
+use crate::ai::tools::ToolSchema;
+use super::Provider;
+
+pub struct OpenAICompatible {
+    credential: &'static str,
+    default_endpoint: &'static str,
+    requires_key: bool,
+    headers: &'static [(&'static str, &'static str)],
+}
+
+pub const OPENROUTER: OpenAICompatible = OpenAICompatible {
+    credential: "openrouter",
+    default_endpoint: "https://openrouter.ai/api/v1",
+    requires_key: true,
+    headers: &[("X-Title", "NixDeck 2133")],
+};
+
+// Local servers such as llama.cpp usually run without a key
+pub const OPENAI_COMPATIBLE: OpenAICompatible = OpenAICompatible {
+    credential: "openai",
+    default_endpoint: "https://api.openai.com/v1",
+    requires_key: false,
+    headers: &[],
+};
+
+#[derive(Debug, Serialize)]
+struct ChatRequest<'a> {
+    model: &'a str,
+    messages: &'a [ChatMessage],
+    temperature: f32,
+    max_tokens: u32,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    tools: &'a [ToolSchema],
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
+    // Ask for a final usage chunk when streaming
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_options: Option<StreamOptions>,
+}
+
+#[derive(Debug, Serialize)]
+struct StreamOptions {
+    include_usage: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatResponse {
+    choices: Vec<ChatChoice>,
+    usage: Option<Usage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatChoice {
+    message: ChatMessage,
+}
+
+#[derive(Debug, Deserialize)]
+struct StreamChunk {
+    #[serde(default)]
+    choices: Vec<StreamChoice>,
+    usage: Option<Usage>,
+    error: Option<ErrorDetail>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StreamChoice {
+    #[serde(default)]
+    delta: ChoiceDelta,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ChoiceDelta {
+    content: Option<String>,
+    #[serde(default)]
+    tool_calls: Vec<ToolCallFragment>,
+}
+
+// Tool calls arrive in fragments keyed by index; the name and ID come first
+// and the JSON arguments are spread over the following chunks
+#[derive(Debug, Deserialize)]
+struct ToolCallFragment {
+    index: usize,
+    id: Option<String>,
+    function: Option<FunctionFragment>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FunctionFragment {
+    name: Option<String>,
+    arguments: Option<String>,
+}
+
+// `/models` listing. Plain OpenAI-compatible servers only return IDs;
+// OpenRouter adds context length, pricing (decimal strings, USD per token)
+// and the request parameters each model accepts.
+#[derive(Debug, Deserialize)]
+struct ModelList {
+    data: Vec<ModelEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelEntry {
+    id: String,
+    name: Option<String>,
+    context_length: Option<u64>,
+    pricing: Option<ModelPricing>,
+    #[serde(default)]
+    supported_parameters: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelPricing {
+    prompt: String,
+    completion: String,
+}
+
+impl Provider for OpenAICompatible {
+    fn credential(&self) -> &'static str {
+        self.credential
+    }
+
+    fn requires_key(&self) -> bool {
+        self.requires_key
+    }
+
+    fn default_endpoint(&self) -> &'static str {
+        self.default_endpoint
+    }
+
+    fn chat_path(&self) -> &'static str {
+        "chat/completions"
+    }
+
+    fn extra_headers(&self) -> &'static [(&'static str, &'static str)] {
+        self.headers
+    }
+
+    fn request_body(&self, params: &ChatParams) -> Value {
+        serde_json::to_value(ChatRequest {
+            model: params.model,
+            messages: params.messages,
+            temperature: params.temperature,
+            max_tokens: params.max_tokens,
+            tools: params.tools,
+            stream: params.stream,
+            stream_options: params.stream.then_some(StreamOptions { include_usage: true }),
+        })
+        .unwrap_or(Value::Null)
+    }
+
+    fn parse_response(&self, body: Value) -> Result<Completion, AIError> {
+        let parsed: ChatResponse = serde_json::from_value(body)
+            .map_err(|e| AIError::InvalidResponse(e.to_string()))?;
+
+        let message = parsed
+            .choices
+            .into_iter()
+            .next()
+            .map(|choice| choice.message)
+            .ok_or_else(|| AIError::InvalidResponse("response contained no choices".to_string()))?;
+
+        Ok(Completion { message, usage: parsed.usage, model: String::new() })
+    }
+
+    // Server-sent events: `data: {json}` lines, `: comment` keep-alives and a
+    // final `data: [DONE]`
+    fn parse_stream_line(&self, line: &str) -> Result<Option<StreamDelta>, AIError> {
+        let data = match line.strip_prefix("data:") {
+            Some(data) => data.trim(),
+            None => return Ok(None),
+        };
+
+        if data == "[DONE]" {
+            return Ok(Some(StreamDelta { done: true, ..Default::default() }));
+        }
+
+        let chunk: StreamChunk = serde_json::from_str(data)
+            .map_err(|e| AIError::InvalidResponse(e.to_string()))?;
+
+        if let Some(error) = chunk.error {
+            return Err(AIError::InvalidResponse(error.message));
+        }
+
+        let mut delta = StreamDelta {
+            usage: chunk.usage,
+            ..Default::default()
+        };
+        for choice in chunk.choices {
+            if let Some(content) = choice.delta.content {
+                delta.content.get_or_insert_with(String::new).push_str(&content);
+            }
+            for fragment in choice.delta.tool_calls {
+                let (name, arguments) = match fragment.function {
+                    Some(function) => (function.name, function.arguments),
+                    None => (None, None),
+                };
+                delta.tool_calls.push(ToolCallDelta {
+                    index: Some(fragment.index),
+                    id: fragment.id,
+                    name,
+                    arguments,
+                });
+            }
+        }
+
+        Ok(Some(delta))
+    }
+
+    fn models_path(&self) -> &'static str {
+        "models"
+    }
+
+    fn parse_models(&self, body: Value) -> Result<Vec<ModelInfo>, AIError> {
+        let listing: ModelList = serde_json::from_value(body)
+            .map_err(|e| AIError::InvalidResponse(e.to_string()))?;
+
+        Ok(listing
+            .data
+            .into_iter()
+            .map(|model| ModelInfo {
+                pricing: model.pricing.and_then(|p| {
+                    Some(ModelPrice {
+                        prompt: p.prompt.parse().ok()?,
+                        completion: p.completion.parse().ok()?,
+                    })
+                }),
+                supports_tools: model.supported_parameters.iter().any(|p| p == "tools"),
+                id: model.id,
+                name: model.name,
+                context_length: model.context_length,
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,487 @@
+// NixDeck 2133 - Secret Redaction
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use super::client::ChatMessage;
+
+pub const EVENT_REDACTIONS: &str = "ai://redactions";
+
+const PLACEHOLDER_PREFIX: &str = "[REDACTED:";
+// Longer than any `[REDACTED:<rule>:<n>]` a sane rule name produces
+const MAX_PLACEHOLDER_LEN: usize = 96;
+
+// Characters that can make up a high-entropy token such as an API key
+const TOKEN_CHARS: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/_-=.";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedactionRules {
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+    pub patterns: Vec<PatternRule>,
+    pub entropy: EntropyRule,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PatternRule {
+    pub name: String,
+    pub pattern: String,
+    // Capture group holding the secret; 0 replaces the whole match
+    #[serde(default)]
+    pub group: usize,
+}
+
+// Flags long random-looking tokens that no pattern caught
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EntropyRule {
+    pub enabled: bool,
+    pub min_length: usize,
+    // Shannon entropy in bits per character
+    pub threshold: f64,
+}
+
+// What was hidden, for display; never includes the secret itself
+#[derive(Debug, Clone, Serialize)]
+pub struct Redaction {
+    pub rule: String,
+    pub placeholder: String,
+    pub role: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RedactionEvent {
+    pub request_id: String,
+    pub redactions: Vec<Redaction>,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+impl Default for RedactionRules {
+    fn default() -> Self {
+        let pattern = |name: &str, pattern: &str, group: usize| PatternRule {
+            name: name.to_string(),
+            pattern: pattern.to_string(),
+            group,
+        };
+
+        RedactionRules {
+            enabled: true,
+            patterns: vec![
+                pattern(
+                    "private_key",
+                    r"-----BEGIN [A-Z ]*PRIVATE KEY-----[\s\S]*?-----END [A-Z ]*PRIVATE KEY-----",
+                    0,
+                ),
+                pattern(
+                    "api_token",
+                    r"\b(?:sk-[A-Za-z0-9_\-]{16,}|gh[pousr]_[A-Za-z0-9]{20,}|xox[abpr]-[A-Za-z0-9\-]{10,}|AKIA[0-9A-Z]{16})\b",
+                    0,
+                ),
+                // KEY=value and key: value, including systemd Environment= lines.
+                // The keyword ends the key, so `max_tokens = 2048` is left alone,
+                // and a trailing comma from JSON or a list isn't part of the value.
+                pattern(
+                    "credential_assignment",
+                    r#"(?i)\b[A-Z0-9_.\-]*(?:PASSWORD|PASSWD|SECRET|TOKEN|API_?KEY|ACCESS_?KEY|PRIVATE_?KEY|SECRET_?KEY)["']?\s*[=:]\s*["']?([^\s"']*[^\s"',])"#,
+                    1,
+                ),
+                // Space-separated settings such as kitty's `remote_control_password`:
+                // a lowercase key and a single value alone on the line, so prose
+                // that merely mentions a token isn't taken for one
+                pattern(
+                    "credential_setting",
+                    r#"(?m)^[ \t]*[a-z_]*(?:password|secret|token)[ \t]+"?([^\s"]+)"?[ \t]*$"#,
+                    1,
+                ),
+                pattern("url_credentials", r"[a-zA-Z][a-zA-Z0-9+.\-]*://[^\s:/@]+:([^\s@/]+)@", 1),
+                pattern(
+                    "authorization_header",
+                    r#"(?i)authorization:\s*(?:bearer|basic|token)\s+([^\s'"]+)"#,
+                    1,
+                ),
+                pattern("basic_auth_flag", r"(?:\s-u|--user)\s+[^\s:]+:(\S+)", 1),
+            ],
+            entropy: EntropyRule {
+                enabled: true,
+                min_length: 24,
+                threshold: 4.0,
+            },
+        }
+    }
+}
+
+impl RedactionRules {
+    pub fn validate(&self) -> Result<(), String> {
+        for rule in &self.patterns {
+            let regex = Regex::new(&rule.pattern)
+                .map_err(|e| format!("Invalid redaction pattern '{}': {}", rule.name, e))?;
+            if rule.group >= regex.captures_len() {
+                return Err(format!(
+                    "Redaction pattern '{}' has no capture group {}",
+                    rule.name, rule.group
+                ));
+            }
+        }
+        if self.entropy.min_length < 8 {
+            return Err("Entropy rule min_length must be at least 8".to_string());
+        }
+        Ok(())
+    }
+}
+
+// Redacts the outgoing messages of one exchange. The same secret always maps
+// to the same placeholder so the model can still tell values apart, and
+// placeholders in the model's tool calls and reply are mapped back locally.
+pub struct Redactor {
+    enabled: bool,
+    patterns: Vec<(String, Regex, usize)>,
+    entropy: EntropyRule,
+    // secret -> placeholder
+    secrets: HashMap<String, String>,
+}
+
+impl Redactor {
+    pub fn load() -> Result<Self, String> {
+        let rules = load_rules()?;
+
+        let mut patterns = Vec::new();
+        for rule in rules.patterns {
+            // Fail closed: a broken rule must not let secrets through
+            let regex = Regex::new(&rule.pattern)
+                .map_err(|e| format!("Invalid redaction pattern '{}': {}", rule.name, e))?;
+            patterns.push((rule.name, regex, rule.group));
+        }
+
+        Ok(Redactor {
+            enabled: rules.enabled,
+            patterns,
+            entropy: rules.entropy,
+            secrets: HashMap::new(),
+        })
+    }
+
+    pub fn redact_messages(&mut self, messages: &mut [ChatMessage]) -> Vec<Redaction> {
+        let mut redactions = Vec::new();
+        for message in messages {
+            let (content, found) = self.redact(&message.content, &message.role);
+            message.content = content;
+            redactions.extend(found);
+
+            // Earlier turns' tool calls carry the real values they ran with
+            for call in message.tool_calls.iter_mut().flatten() {
+                let (arguments, found) = self.redact(&call.function.arguments, &message.role);
+                call.function.arguments = arguments;
+                redactions.extend(found);
+            }
+        }
+        redactions
+    }
+
+    // Replace secrets in `text`, reporting only ones not seen before
+    pub fn redact(&mut self, text: &str, role: &str) -> (String, Vec<Redaction>) {
+        if !self.enabled || text.is_empty() {
+            return (text.to_string(), Vec::new());
+        }
+
+        let mut redactions = Vec::new();
+        let mut text = text.to_string();
+
+        for index in 0..self.patterns.len() {
+            let (name, regex, group) = &self.patterns[index];
+            // Earlier rules' placeholders, e.g. `api_token:1` looks like a setting
+            let placeholders = placeholder_spans(&text);
+            let spans: Vec<(usize, usize)> = regex
+                .captures_iter(&text)
+                .filter_map(|captures| captures.get(*group))
+                .map(|m| (m.start(), m.end()))
+                .filter(|(start, end)| !placeholders.iter().any(|(p_start, p_end)| start < p_end && p_start < end))
+                .collect();
+
+            let name = name.clone();
+            text = self.replace_spans(&text, &spans, &name, role, &mut redactions);
+        }
+
+        if self.entropy.enabled {
+            let spans = high_entropy_spans(&text, &self.entropy);
+            text = self.replace_spans(&text, &spans, "high_entropy", role, &mut redactions);
+        }
+
+        (text, redactions)
+    }
+
+    // Put the original values back into text produced by the model
+    pub fn restore(&self, text: &str) -> String {
+        let mut restored = text.to_string();
+        for (secret, placeholder) in &self.secrets {
+            restored = restored.replace(placeholder.as_str(), secret);
+        }
+        restored
+    }
+
+    // Restore a reply that arrives in pieces. A placeholder can be split
+    // across deltas, so a trailing piece that may be the start of one is kept
+    // in `held` until the next delta completes it; flush `held` with restore()
+    // once the reply ends.
+    pub fn restore_delta(&self, held: &mut String, delta: &str) -> String {
+        held.push_str(delta);
+        let ready_len = match held.rfind('[') {
+            Some(start) if may_be_placeholder(&held[start..]) => start,
+            _ => held.len(),
+        };
+        let ready: String = held.drain(..ready_len).collect();
+        self.restore(&ready)
+    }
+
+    fn replace_spans(
+        &mut self,
+        text: &str,
+        spans: &[(usize, usize)],
+        rule: &str,
+        role: &str,
+        redactions: &mut Vec<Redaction>,
+    ) -> String {
+        let mut result = String::with_capacity(text.len());
+        let mut last = 0;
+
+        for &(start, end) in spans {
+            // Overlapping capture groups: the earlier span already covered this
+            if start < last || start == end {
+                continue;
+            }
+            let secret = &text[start..end];
+
+            let placeholder = match self.secrets.get(secret) {
+                Some(placeholder) => placeholder.clone(),
+                None => {
+                    let placeholder = format!("{}{}:{}]", PLACEHOLDER_PREFIX, rule, self.secrets.len() + 1);
+                    self.secrets.insert(secret.to_string(), placeholder.clone());
+                    redactions.push(Redaction {
+                        rule: rule.to_string(),
+                        placeholder: placeholder.clone(),
+                        role: role.to_string(),
+                    });
+                    placeholder
+                }
+            };
+
+            result.push_str(&text[last..start]);
+            result.push_str(&placeholder);
+            last = end;
+        }
+
+        result.push_str(&text[last..]);
+        result
+    }
+}
+
+pub async fn get_rules() -> Result<RedactionRules, String> {
+    load_rules()
+}
+
+pub async fn save_rules(rules: RedactionRules) -> Result<(), String> {
+    rules.validate()?;
+
+    let rules_path = get_rules_path();
+    if let Some(parent) = rules_path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create config directory: {}", e))?;
+    }
+
+    let json = serde_json::to_string_pretty(&rules)
+        .map_err(|e| format!("Failed to serialize redaction rules: {}", e))?;
+
+    fs::write(rules_path, json)
+        .map_err(|e| format!("Failed to save redaction rules: {}", e))
+}
+
+fn load_rules() -> Result<RedactionRules, String> {
+    let rules_path = get_rules_path();
+
+    if !rules_path.exists() {
+        return Ok(RedactionRules::default());
+    }
+
+    let content = fs::read_to_string(&rules_path)
+        .map_err(|e| format!("Failed to read redaction rules: {}", e))?;
+
+    serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse redaction rules: {}", e))
+}
+
+// Byte spans of the placeholders already in `text`
+fn placeholder_spans(text: &str) -> Vec<(usize, usize)> {
+    text.match_indices(PLACEHOLDER_PREFIX)
+        .filter_map(|(start, _)| {
+            let end = text[start..].find(']')?;
+            Some((start, start + end + 1))
+        })
+        .collect()
+}
+
+// An unfinished placeholder: a prefix of `[REDACTED:`, or that followed by
+// a rule name and number with no closing bracket yet
+fn may_be_placeholder(tail: &str) -> bool {
+    if tail.len() <= PLACEHOLDER_PREFIX.len() {
+        return PLACEHOLDER_PREFIX.starts_with(tail);
+    }
+    tail.starts_with(PLACEHOLDER_PREFIX)
+        && tail.len() <= MAX_PLACEHOLDER_LEN
+        && tail[PLACEHOLDER_PREFIX.len()..]
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ':')
+}
+
+// Byte spans of tokens that are long, mix letters and digits, and look random.
+// Paths and URLs are skipped; they are long but rarely secret.
+fn high_entropy_spans(text: &str, rule: &EntropyRule) -> Vec<(usize, usize)> {
+    let mut spans = Vec::new();
+    let mut start = None;
+
+    for (index, ch) in text.char_indices().chain(std::iter::once((text.len(), ' '))) {
+        if TOKEN_CHARS.contains(ch) {
+            start.get_or_insert(index);
+            continue;
+        }
+
+        if let Some(token_start) = start.take() {
+            let token = &text[token_start..index];
+            let looks_like_path = token.starts_with('/') || token.starts_with('.') || token.contains("//");
+            if token.len() >= rule.min_length
+                && !looks_like_path
+                && token.chars().any(|c| c.is_ascii_digit())
+                && token.chars().any(|c| c.is_ascii_alphabetic())
+                && shannon_entropy(token) >= rule.threshold
+            {
+                spans.push((token_start, index));
+            }
+        }
+    }
+
+    spans
+}
+
+fn shannon_entropy(token: &str) -> f64 {
+    let mut counts: HashMap<char, usize> = HashMap::new();
+    for ch in token.chars() {
+        *counts.entry(ch).or_insert(0) += 1;
+    }
+
+    let len = token.chars().count() as f64;
+    counts
+        .values()
+        .map(|&count| {
+            let p = count as f64 / len;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+fn get_rules_path() -> PathBuf {
+    let home = dirs::home_dir().expect("Could not find home directory");
+    home.join(".nixdeck").join("redaction.json")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::client::{FunctionCall, ToolCall};
+
+    fn redactor() -> Redactor {
+        let rules = RedactionRules::default();
+        Redactor {
+            enabled: true,
+            patterns: rules
+                .patterns
+                .into_iter()
+                .map(|rule| (rule.name, Regex::new(&rule.pattern).unwrap(), rule.group))
+                .collect(),
+            entropy: rules.entropy,
+            secrets: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn settings_need_a_lone_value() {
+        let mut redactor = redactor();
+
+        let (text, found) = redactor.redact("remote_control_password hunter2\n", "user");
+        assert_eq!(text, "remote_control_password [REDACTED:credential_setting:1]\n");
+        assert_eq!(found.len(), 1);
+
+        let prose = "Token expired yesterday, please refresh\nthe secret is out";
+        assert_eq!(redactor.redact(prose, "user").0, prose);
+    }
+
+    #[test]
+    fn assignments_need_the_keyword_to_end_the_key() {
+        let mut redactor = redactor();
+
+        for text in [
+            r#"{"max_tokens": 4096, "temperature": 0.7}"#,
+            "max_tokens = 2048",
+            "tokenizer: llama",
+            "secret_santa: alice",
+            "max_tokens 512\n",
+        ] {
+            assert_eq!(redactor.redact(text, "user").0, text);
+        }
+    }
+
+    #[test]
+    fn assignment_values_by_quoting() {
+        let mut redactor = redactor();
+
+        let cases = [
+            (r#"{"api_key": "hunter2", "model": "x"}"#, r#"{"api_key": "[REDACTED:credential_assignment:1]", "model": "x"}"#),
+            ("DB_PASSWORD=hunter3,", "DB_PASSWORD=[REDACTED:credential_assignment:2],"),
+            ("Environment=GITHUB_TOKEN=abc,def", "Environment=GITHUB_TOKEN=[REDACTED:credential_assignment:3]"),
+            ("secret_key: 'hunter4'", "secret_key: '[REDACTED:credential_assignment:4]'"),
+        ];
+        for (text, redacted) in cases {
+            assert_eq!(redactor.redact(text, "user").0, redacted);
+        }
+        assert_eq!(redactor.restore("[REDACTED:credential_assignment:3]"), "abc,def");
+    }
+
+    #[test]
+    fn history_tool_call_arguments_are_redacted() {
+        let mut redactor = redactor();
+        let mut call_turn = ChatMessage::assistant("");
+        call_turn.tool_calls = Some(vec![ToolCall {
+            id: "call_1".to_string(),
+            kind: "function".to_string(),
+            function: FunctionCall {
+                name: "shell".to_string(),
+                arguments: r#"{"command":"curl -H 'Authorization: Bearer abcdef123456' x"}"#.to_string(),
+            },
+        }]);
+        let mut messages = vec![call_turn];
+
+        let found = redactor.redact_messages(&mut messages);
+        let arguments = &messages[0].requested_tools()[0].function.arguments;
+        assert_eq!(found.len(), 1);
+        assert!(!arguments.contains("abcdef123456"), "{}", arguments);
+        assert!(serde_json::from_str::<serde_json::Value>(arguments).is_ok());
+        assert!(redactor.restore(arguments).contains("abcdef123456"));
+    }
+
+    #[test]
+    fn split_placeholders_are_restored_across_deltas() {
+        let mut redactor = redactor();
+        let (text, _) = redactor.redact("token sk-abcdefghijklmnopqrstuvwx", "user");
+        assert_eq!(text, "token [REDACTED:api_token:1]");
+
+        let mut held = String::new();
+        let mut shown = String::new();
+        for delta in ["Use [RED", "ACTED:api_tok", "en:1] [not a placeholder", "] and [REDAC"] {
+            shown.push_str(&redactor.restore_delta(&mut held, delta));
+        }
+        assert_eq!(held, "[REDAC");
+        shown.push_str(&redactor.restore(&held));
+        assert_eq!(shown, "Use sk-abcdefghijklmnopqrstuvwx [not a placeholder] and [REDAC");
+    }
+}
@@ -0,0 +1,422 @@
+// NixDeck 2133 - Local Retrieval Index
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::UNIX_EPOCH;
+
+// Where indexed files come from; a loadout can narrow retrieval to some of them
+pub const SOURCES: &[&str] = &["rice", "containers", "units"];
+
+pub const TOP_K_RANGE: (usize, usize) = (1, 20);
+
+// Bumped whenever chunking or tokenizing changes, which invalidates the index
+const INDEX_VERSION: u32 = 1;
+
+const CHUNK_LINES: usize = 40;
+const MAX_FILE_BYTES: u64 = 256 * 1024;
+const MAX_WALK_DEPTH: usize = 6;
+
+// BM25 parameters
+const K1: f64 = 1.2;
+const B: f64 = 0.75;
+
+const STOPWORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "be", "by", "do", "does", "for", "from", "how", "in", "is",
+    "it", "my", "of", "on", "or", "so", "that", "the", "this", "to", "what", "when", "why",
+    "with",
+];
+
+// Per-loadout opt-in for attaching relevant local files to the prompt
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetrievalSettings {
+    #[serde(default = "default_sources")]
+    pub sources: Vec<String>,
+    // How many snippets to attach
+    #[serde(default = "default_top_k")]
+    pub top_k: usize,
+}
+
+fn default_sources() -> Vec<String> {
+    SOURCES.iter().map(|source| source.to_string()).collect()
+}
+
+fn default_top_k() -> usize {
+    4
+}
+
+impl RetrievalSettings {
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        for source in &self.sources {
+            if !SOURCES.contains(&source.as_str()) {
+                problems.push(format!("unknown retrieval source '{}'", source));
+            }
+        }
+        let (min_k, max_k) = TOP_K_RANGE;
+        if !(min_k..=max_k).contains(&self.top_k) {
+            problems.push(format!("retrieval top_k {} is outside {}-{}", self.top_k, min_k, max_k));
+        }
+
+        problems
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Snippet {
+    pub path: String,
+    pub source: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub text: String,
+    pub score: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexStats {
+    pub files: usize,
+    pub chunks: usize,
+    // Files read this time round; the rest were unchanged since the last build
+    pub reindexed: usize,
+    pub removed: usize,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Index {
+    version: u32,
+    // Keyed by path
+    documents: BTreeMap<String, Document>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Document {
+    source: String,
+    modified_ms: u64,
+    size: u64,
+    chunks: Vec<Chunk>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Chunk {
+    start_line: usize,
+    text: String,
+    terms: HashMap<String, u32>,
+    length: u32,
+}
+
+// Bring the index up to date with the files on disk. Only files whose mtime
+// or size changed are read again.
+pub async fn refresh() -> Result<IndexStats, String> {
+    blocking(refresh_index).await?
+}
+
+// The `top_k` chunks that best match `query`, from the given sources
+pub async fn search(query: String, sources: Vec<String>, top_k: usize) -> Result<Vec<Snippet>, String> {
+    blocking(move || search_index(&query, &sources, top_k)).await?
+}
+
+// A prompt block of the snippets relevant to `message`; empty if none match.
+// An index problem is noted in the block rather than failing the request.
+pub async fn build(settings: &RetrievalSettings, message: &str) -> String {
+    let searched = search(message.to_string(), settings.sources.clone(), settings.top_k).await;
+    let snippets = match searched {
+        Ok(snippets) => snippets,
+        Err(e) => return format!("# Relevant local files\n(unavailable: {})", e),
+    };
+    if snippets.is_empty() {
+        return String::new();
+    }
+
+    let blocks: Vec<String> = snippets
+        .iter()
+        .map(|snippet| {
+            format!(
+                "## {} (lines {}-{})\n```\n{}\n```",
+                snippet.path,
+                snippet.start_line,
+                snippet.end_line,
+                snippet.text.trim_end()
+            )
+        })
+        .collect();
+
+    format!(
+        "# Relevant local files\nExcerpts from this machine's config that may bear on the question.\n\n{}",
+        blocks.join("\n\n")
+    )
+}
+
+// Indexing walks and stats every source file, so it stays off the async runtime
+async fn blocking<T: Send + 'static>(work: impl FnOnce() -> T + Send + 'static) -> Result<T, String> {
+    tauri::async_runtime::spawn_blocking(work)
+        .await
+        .map_err(|e| format!("Retrieval index task failed: {}", e))
+}
+
+// The index as of the last refresh. It is read from disk once per run and
+// kept here after that; the lock also keeps two refreshes from racing.
+fn cached_index() -> &'static Mutex<Option<Index>> {
+    static INDEX: OnceLock<Mutex<Option<Index>>> = OnceLock::new();
+    INDEX.get_or_init(|| Mutex::new(None))
+}
+
+fn search_index(query: &str, sources: &[String], top_k: usize) -> Result<Vec<Snippet>, String> {
+    let mut cache = cached_index().lock().map_err(|e| format!("Lock error: {}", e))?;
+    refresh_cached(&mut cache)?;
+    Ok(cache.as_ref().map(|index| rank(index, query, sources, top_k)).unwrap_or_default())
+}
+
+fn refresh_index() -> Result<IndexStats, String> {
+    let mut cache = cached_index().lock().map_err(|e| format!("Lock error: {}", e))?;
+    refresh_cached(&mut cache)
+}
+
+fn refresh_cached(cache: &mut Option<Index>) -> Result<IndexStats, String> {
+    let mut index = cache.take().unwrap_or_else(read_index);
+    let stats = update(&mut index);
+    let written = if stats.reindexed > 0 || stats.removed > 0 { write_index(&index) } else { Ok(()) };
+    // Kept even if the write failed; the next run reindexes what the file lacks
+    *cache = Some(index);
+    written.map(|_| stats)
+}
+
+fn update(index: &mut Index) -> IndexStats {
+    if index.version != INDEX_VERSION {
+        *index = Index { version: INDEX_VERSION, ..Index::default() };
+    }
+
+    let mut seen = BTreeMap::new();
+    for (source, path) in source_files() {
+        seen.insert(path.to_string_lossy().to_string(), (source, path));
+    }
+
+    let stale: Vec<String> = index
+        .documents
+        .keys()
+        .filter(|path| !seen.contains_key(*path))
+        .cloned()
+        .collect();
+    for path in &stale {
+        index.documents.remove(path);
+    }
+
+    let mut reindexed = 0;
+    for (key, (source, path)) in seen {
+        let metadata = match fs::metadata(&path) {
+            Ok(metadata) => metadata,
+            Err(_) => continue,
+        };
+        let modified_ms = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|elapsed| elapsed.as_millis() as u64)
+            .unwrap_or(0);
+
+        let unchanged = index.documents.get(&key).is_some_and(|doc| {
+            doc.modified_ms == modified_ms && doc.size == metadata.len() && doc.source == source
+        });
+        if unchanged {
+            continue;
+        }
+
+        // Unreadable files are kept with no chunks so they aren't retried every time
+        reindexed += 1;
+        let chunks = read_text(&path, metadata.len())
+            .map(|text| chunk(&path, &text))
+            .unwrap_or_default();
+        index.documents.insert(key, Document {
+            source: source.to_string(),
+            modified_ms,
+            size: metadata.len(),
+            chunks,
+        });
+    }
+
+    IndexStats {
+        files: index.documents.len(),
+        chunks: index.documents.values().map(|doc| doc.chunks.len()).sum(),
+        reindexed,
+        removed: stale.len(),
+    }
+}
+
+fn rank(index: &Index, query: &str, sources: &[String], top_k: usize) -> Vec<Snippet> {
+    let mut query_terms = tokenize(query);
+    query_terms.sort();
+    query_terms.dedup();
+    if query_terms.is_empty() {
+        return Vec::new();
+    }
+
+    let chunks: Vec<(&String, &Document, &Chunk)> = index
+        .documents
+        .iter()
+        .filter(|(_, doc)| sources.iter().any(|source| source == &doc.source))
+        .flat_map(|(path, doc)| doc.chunks.iter().map(move |chunk| (path, doc, chunk)))
+        .collect();
+    if chunks.is_empty() {
+        return Vec::new();
+    }
+
+    let total = chunks.len() as f64;
+    let average_length = chunks.iter().map(|(_, _, c)| c.length as f64).sum::<f64>() / total;
+
+    let idf: HashMap<&str, f64> = query_terms
+        .iter()
+        .map(|term| {
+            let df = chunks.iter().filter(|(_, _, c)| c.terms.contains_key(term)).count() as f64;
+            (term.as_str(), ((total - df + 0.5) / (df + 0.5) + 1.0).ln())
+        })
+        .collect();
+
+    let mut scored: Vec<Snippet> = chunks
+        .iter()
+        .filter_map(|(path, doc, chunk)| {
+            let norm = K1 * (1.0 - B + B * chunk.length as f64 / average_length.max(1.0));
+            let score: f64 = query_terms
+                .iter()
+                .filter_map(|term| {
+                    let tf = *chunk.terms.get(term)? as f64;
+                    Some(idf[term.as_str()] * tf * (K1 + 1.0) / (tf + norm))
+                })
+                .sum();
+
+            (score > 0.0).then(|| Snippet {
+                path: path.to_string(),
+                source: doc.source.clone(),
+                start_line: chunk.start_line,
+                end_line: chunk.start_line + chunk.text.lines().count().saturating_sub(1),
+                text: chunk.text.clone(),
+                score,
+            })
+        })
+        .collect();
+
+    scored.sort_by(|a, b| b.score.total_cmp(&a.score));
+    scored.truncate(top_k);
+    scored
+}
+
+// Split on anything but letters and digits, so `modules-left` and
+// `modules_left` both yield "modules" and "left"
+fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|token| token.len() > 1)
+        .map(str::to_lowercase)
+        .filter(|token| !STOPWORDS.contains(&token.as_str()))
+        .collect()
+}
+
+// Every chunk also carries the terms of its file's path, so "polybar" finds
+// polybar/config.ini even where the text never names it
+fn chunk(path: &Path, text: &str) -> Vec<Chunk> {
+    let path_terms = tokenize(&path.to_string_lossy());
+    let lines: Vec<&str> = text.lines().collect();
+
+    lines
+        .chunks(CHUNK_LINES)
+        .enumerate()
+        .filter_map(|(n, lines)| {
+            let text = lines.join("\n");
+            let mut tokens = tokenize(&text);
+            if tokens.is_empty() {
+                return None;
+            }
+            tokens.extend(path_terms.iter().cloned());
+
+            let mut terms = HashMap::new();
+            for token in &tokens {
+                *terms.entry(token.clone()).or_insert(0) += 1;
+            }
+            Some(Chunk {
+                start_line: n * CHUNK_LINES + 1,
+                text,
+                terms,
+                length: tokens.len() as u32,
+            })
+        })
+        .collect()
+}
+
+// Text files only; anything large or binary is left out of the index
+fn read_text(path: &Path, size: u64) -> Option<String> {
+    if size > MAX_FILE_BYTES {
+        return None;
+    }
+    let bytes = fs::read(path).ok()?;
+    if bytes.contains(&0) {
+        return None;
+    }
+    String::from_utf8(bytes).ok()
+}
+
+fn source_files() -> Vec<(&'static str, PathBuf)> {
+    let mut files = Vec::new();
+
+    for component in crate::rice::components() {
+        if let Some(path) = component.resolve().ok().and_then(|resolution| resolution.active) {
+            files.push(("rice", path));
+        }
+        for path in component.extra_files() {
+            files.push(("rice", path));
+        }
+    }
+
+    for (_, dir) in crate::container::snapshot_dirs() {
+        walk(&dir, 0, &mut |path| files.push(("containers", path)));
+    }
+
+    for dir in crate::daemon::get_user_unit_dirs().unwrap_or_default() {
+        walk(&dir, 0, &mut |path| files.push(("units", path)));
+    }
+
+    files
+}
+
+fn walk(dir: &Path, depth: usize, found: &mut dyn FnMut(PathBuf)) {
+    if depth > MAX_WALK_DEPTH {
+        return;
+    }
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            walk(&path, depth + 1, found);
+        } else if path.is_file() {
+            found(path);
+        }
+    }
+}
+
+fn read_index() -> Index {
+    let path = get_index_path();
+    // Indexes from older versions were written world-readable
+    let _ = fs::set_permissions(&path, fs::Permissions::from_mode(0o600));
+
+    fs::read_to_string(&path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+// Written atomically so a reader never sees half an index. It holds excerpts
+// of config files, so it is readable by this user only.
+fn write_index(index: &Index) -> Result<(), String> {
+    let json = serde_json::to_string(index)
+        .map_err(|e| format!("Failed to serialize retrieval index: {}", e))?;
+    crate::atomic::write(&get_index_path(), json.as_bytes(), Some(0o600))
+        .map_err(|e| format!("Failed to write retrieval index: {}", e))
+}
+
+fn get_index_path() -> PathBuf {
+    let home = dirs::home_dir().expect("Could not find home directory");
+    home.join(".nixdeck").join("cache").join("retrieval-index.json")
+}
@@ -0,0 +1,270 @@
+// NixDeck 2133 - AI Request Retries
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::RandomState;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::time::{Duration, Instant};
+
+use super::client::AIError;
+
+pub const MAX_ATTEMPTS_RANGE: (u32, u32) = (1, 10);
+
+// How hard to try before giving up on a model. Missing fields take the defaults.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    // Attempts per model, including the first
+    pub max_attempts: u32,
+    pub initial_delay_ms: u64,
+    pub max_delay_ms: u64,
+    pub multiplier: f64,
+    // No new attempt starts once this much time has passed, fallbacks included
+    pub max_elapsed_secs: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            initial_delay_ms: 500,
+            max_delay_ms: 8_000,
+            multiplier: 2.0,
+            max_elapsed_secs: 60,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        let (min_attempts, max_attempts) = MAX_ATTEMPTS_RANGE;
+        if !(min_attempts..=max_attempts).contains(&self.max_attempts) {
+            problems.push(format!(
+                "retry max_attempts {} is outside {}-{}",
+                self.max_attempts, min_attempts, max_attempts
+            ));
+        }
+        if self.initial_delay_ms > self.max_delay_ms {
+            problems.push("retry initial_delay_ms must not exceed max_delay_ms".to_string());
+        }
+        if !self.multiplier.is_finite() || self.multiplier < 1.0 {
+            problems.push(format!("retry multiplier {} must be at least 1", self.multiplier));
+        }
+
+        problems
+    }
+
+    // Wait before retry number `retry` (0-based). A server's Retry-After wins;
+    // otherwise the exponential delay is jittered so clients don't retry in step.
+    fn delay(&self, retry: u32, retry_after: Option<u64>) -> Duration {
+        if let Some(secs) = retry_after {
+            return Duration::from_secs(secs);
+        }
+
+        let exponential = self.initial_delay_ms as f64 * self.multiplier.powi(retry as i32);
+        let capped = exponential.min(self.max_delay_ms as f64) as u64;
+        let half = capped / 2;
+        Duration::from_millis(half + jitter(capped - half))
+    }
+}
+
+// Try `models` in order, retrying each on transient failures. A model that
+// stays rate limited or keeps returning 5xx hands over to the next one.
+// Returns the result together with the model that produced it.
+pub async fn run<T, F, Fut>(policy: &RetryPolicy, models: &[String], mut attempt: F) -> Result<(T, String), AIError>
+where
+    F: FnMut(String) -> Fut,
+    Fut: Future<Output = Result<T, AIError>>,
+{
+    let started = Instant::now();
+    let max_elapsed = Duration::from_secs(policy.max_elapsed_secs);
+    let mut last_error = None;
+
+    for model in models {
+        if last_error.is_some() && started.elapsed() >= max_elapsed {
+            break;
+        }
+
+        for retry in 0..policy.max_attempts.max(1) {
+            let err = match attempt(model.clone()).await {
+                Ok(value) => return Ok((value, model.clone())),
+                Err(err) => err,
+            };
+            if !is_transient(&err) {
+                return Err(err);
+            }
+
+            let out_of_attempts = retry + 1 >= policy.max_attempts;
+            let delay = policy.delay(retry, retry_after(&err));
+            last_error = Some(err);
+
+            // Waiting past the deadline is pointless; try the next model instead
+            if out_of_attempts || started.elapsed() + delay > max_elapsed {
+                break;
+            }
+            tokio::time::sleep(delay).await;
+        }
+
+        if !last_error.as_ref().is_some_and(falls_back) {
+            break;
+        }
+    }
+
+    Err(last_error.unwrap_or_else(|| AIError::InvalidModel("no model configured".to_string())))
+}
+
+// Failures worth trying again
+fn is_transient(err: &AIError) -> bool {
+    matches!(err, AIError::Network(_)) || falls_back(err)
+}
+
+// Failures another model might not have: the network being down is not one
+fn falls_back(err: &AIError) -> bool {
+    match err {
+        AIError::RateLimited { .. } => true,
+        AIError::Http { status, .. } => *status >= 500,
+        _ => false,
+    }
+}
+
+fn retry_after(err: &AIError) -> Option<u64> {
+    match err {
+        AIError::RateLimited { retry_after, .. } => *retry_after,
+        _ => None,
+    }
+}
+
+// A random duration below `max_ms`, without pulling in a RNG crate
+fn jitter(max_ms: u64) -> u64 {
+    if max_ms == 0 {
+        return 0;
+    }
+    RandomState::new().build_hasher().finish() % max_ms
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    fn quick() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            initial_delay_ms: 1,
+            max_delay_ms: 2,
+            multiplier: 2.0,
+            max_elapsed_secs: 10,
+        }
+    }
+
+    fn models(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    // Runs the policy over `models`, failing each attempt with the next of
+    // `errors` until they run out; returns the outcome and the models tried
+    async fn attempts(policy: &RetryPolicy, names: &[&str], errors: Vec<AIError>) -> (Result<String, AIError>, Vec<String>) {
+        let errors = RefCell::new(errors.into_iter());
+        let tried = RefCell::new(Vec::new());
+        let result = run(policy, &models(names), |model| {
+            tried.borrow_mut().push(model.clone());
+            let next = errors.borrow_mut().next();
+            async move {
+                match next {
+                    Some(err) => Err(err),
+                    None => Ok(format!("answer from {}", model)),
+                }
+            }
+        })
+        .await
+        .map(|(value, _)| value);
+        (result, tried.into_inner())
+    }
+
+    fn server_error() -> AIError {
+        AIError::Http { status: 502, message: "bad gateway".to_string() }
+    }
+
+    #[test]
+    fn delay_grows_jittered_up_to_the_cap() {
+        let policy = RetryPolicy {
+            initial_delay_ms: 100,
+            max_delay_ms: 1_000,
+            multiplier: 3.0,
+            ..RetryPolicy::default()
+        };
+
+        for _ in 0..50 {
+            let first = policy.delay(0, None).as_millis();
+            assert!((50..100).contains(&first), "{}", first);
+            let second = policy.delay(1, None).as_millis();
+            assert!((150..300).contains(&second), "{}", second);
+            let capped = policy.delay(10, None).as_millis();
+            assert!((500..1_000).contains(&capped), "{}", capped);
+        }
+    }
+
+    #[test]
+    fn retry_after_overrides_backoff() {
+        assert_eq!(RetryPolicy::default().delay(5, Some(7)), Duration::from_secs(7));
+        assert_eq!(RetryPolicy::default().delay(0, Some(0)), Duration::ZERO);
+    }
+
+    #[test]
+    fn invalid_policies_are_reported() {
+        assert!(RetryPolicy::default().problems().is_empty());
+
+        let policy = RetryPolicy {
+            max_attempts: 0,
+            initial_delay_ms: 10,
+            max_delay_ms: 5,
+            multiplier: 0.5,
+            ..RetryPolicy::default()
+        };
+        assert_eq!(policy.problems().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn transient_failures_are_retried_on_the_same_model() {
+        let (result, tried) = attempts(&quick(), &["a", "b"], vec![server_error(), AIError::Network("reset".to_string())]).await;
+        assert_eq!(result.unwrap(), "answer from a");
+        assert_eq!(tried, ["a", "a", "a"]);
+    }
+
+    #[tokio::test]
+    async fn exhausted_model_falls_back_to_the_next() {
+        let errors = vec![
+            AIError::RateLimited { retry_after: Some(0), message: "busy".to_string() },
+            server_error(),
+            server_error(),
+        ];
+        let (result, tried) = attempts(&quick(), &["a", "b"], errors).await;
+        assert_eq!(result.unwrap(), "answer from b");
+        assert_eq!(tried, ["a", "a", "a", "b"]);
+    }
+
+    #[tokio::test]
+    async fn network_failures_do_not_fall_back() {
+        let errors = (0..3).map(|_| AIError::Network("down".to_string())).collect();
+        let (result, tried) = attempts(&quick(), &["a", "b"], errors).await;
+        assert!(matches!(result, Err(AIError::Network(_))));
+        assert_eq!(tried, ["a", "a", "a"]);
+    }
+
+    #[tokio::test]
+    async fn permanent_failures_stop_at_once() {
+        let (result, tried) = attempts(&quick(), &["a", "b"], vec![AIError::Unauthorized("no".to_string())]).await;
+        assert!(matches!(result, Err(AIError::Unauthorized(_))));
+        assert_eq!(tried, ["a"]);
+    }
+
+    #[tokio::test]
+    async fn retry_past_the_deadline_moves_to_the_next_model() {
+        let policy = RetryPolicy { max_elapsed_secs: 1, ..quick() };
+        let errors = vec![AIError::RateLimited { retry_after: Some(30), message: "later".to_string() }];
+        let (result, tried) = attempts(&policy, &["a", "b"], errors).await;
+        assert_eq!(result.unwrap(), "answer from b");
+        assert_eq!(tried, ["a", "b"]);
+    }
+}
@@ -0,0 +1,123 @@
+// NixDeck 2133 - Streaming AI Responses
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use tokio::sync::Notify;
+
+use super::client::AIError;
+
+pub const EVENT_CHUNK: &str = "ai://chunk";
+pub const EVENT_DONE: &str = "ai://done";
+pub const EVENT_ERROR: &str = "ai://error";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChunkEvent {
+    pub request_id: String,
+    pub delta: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DoneEvent {
+    pub request_id: String,
+    pub content: String,
+    pub model: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrorEvent {
+    pub request_id: String,
+    pub message: String,
+    pub cancelled: bool,
+}
+
+// In-flight streams, keyed by request ID
+fn active_streams() -> &'static Mutex<HashMap<String, Arc<Notify>>> {
+    static STREAMS: OnceLock<Mutex<HashMap<String, Arc<Notify>>>> = OnceLock::new();
+    STREAMS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+// Every way a stream ends is reported to the UI: `ai://done` with the reply
+// or `ai://error`, including failures before the first chunk
+pub async fn stream_message(
+    window: tauri::Window,
+    request_id: String,
+    message: String,
+    loadout: String,
+    conversation: Option<String>,
+) -> Result<super::Reply, String> {
+    let request = match super::PreparedRequest::new(message, &loadout, conversation).await {
+        Ok(request) => request,
+        Err(e) => return Err(emit_error(&window, &request_id, e, false)),
+    };
+
+    let cancel = Arc::new(Notify::new());
+    {
+        let mut streams = match active_streams().lock() {
+            Ok(streams) => streams,
+            Err(e) => return Err(emit_error(&window, &request_id, e.to_string(), false)),
+        };
+        if streams.contains_key(&request_id) {
+            let message = format!("Request '{}' is already streaming", request_id);
+            return Err(emit_error(&window, &request_id, message, false));
+        }
+        streams.insert(request_id.clone(), cancel.clone());
+    }
+
+    let result = tokio::select! {
+        result = super::run_exchange(&request, &window, &request_id, true) => result,
+        _ = cancel.notified() => Err(AIError::Cancelled),
+    };
+
+    if let Ok(mut streams) = active_streams().lock() {
+        streams.remove(&request_id);
+    }
+
+    match result {
+        Ok(reply) => {
+            if let Err(e) = request.finish(&reply.content) {
+                return Err(emit_error(&window, &request_id, e, false));
+            }
+            let _ = window.emit(EVENT_DONE, DoneEvent {
+                request_id,
+                content: reply.content.clone(),
+                model: reply.model.clone(),
+            });
+            Ok(reply)
+        }
+        Err(err) => {
+            let cancelled = matches!(err, AIError::Cancelled);
+            Err(emit_error(&window, &request_id, err.to_string(), cancelled))
+        }
+    }
+}
+
+// Tell the UI the stream failed; returns the message for the command result
+fn emit_error(window: &tauri::Window, request_id: &str, message: String, cancelled: bool) -> String {
+    let _ = window.emit(EVENT_ERROR, ErrorEvent {
+        request_id: request_id.to_string(),
+        message: message.clone(),
+        cancelled,
+    });
+    message
+}
+
+pub(super) fn emit_chunk(window: &tauri::Window, request_id: &str, delta: &str) {
+    let _ = window.emit(EVENT_CHUNK, ChunkEvent {
+        request_id: request_id.to_string(),
+        delta: delta.to_string(),
+    });
+}
+
+pub async fn cancel_stream(request_id: String) -> Result<(), String> {
+    let streams = active_streams().lock().map_err(|e| e.to_string())?;
+
+    match streams.get(&request_id) {
+        Some(cancel) => {
+            // notify_one stores a permit, so a cancel that races ahead of the
+            // select! still lands
+            cancel.notify_one();
+            Ok(())
+        }
+        None => Err(format!("No active stream for request '{}'", request_id)),
+    }
+}
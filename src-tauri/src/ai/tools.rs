@@ -0,0 +1,519 @@
+// NixDeck 2133 - AI Tool Calling
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+use super::client::ToolCall;
+use super::AILoadout;
+
+pub const EVENT_TOOL_APPROVAL: &str = "ai://tool-approval";
+pub const EVENT_TOOL_APPROVAL_CANCELLED: &str = "ai://tool-approval-cancelled";
+
+// Unanswered approval prompts are treated as a denial after this long
+const APPROVAL_TIMEOUT: Duration = Duration::from_secs(300);
+
+// Cap on file contents handed back to the model
+const MAX_FILE_BYTES: usize = 64 * 1024;
+
+struct ToolDef {
+    // Value in `AILoadout.tools_enabled` that unlocks this tool
+    group: &'static str,
+    name: &'static str,
+    description: &'static str,
+    params: &'static [(&'static str, &'static str)],
+    // Mutating tools wait for explicit user approval before running
+    mutating: bool,
+}
+
+const TOOLS: &[ToolDef] = &[
+    ToolDef {
+        group: "rice",
+        name: "rice_get_config",
+        description: "Read the current config file of a rice component, by its registry name (e.g. waybar, polybar, kitty, rofi).",
+        params: &[("component", "Component name, e.g. waybar")],
+        mutating: false,
+    },
+    ToolDef {
+        group: "rice",
+        name: "rice_apply_config",
+        description: "Replace the config file of a rice component. The new config is validated first and rejected with line-numbered errors if invalid. A backup of the current file is kept.",
+        params: &[
+            ("component", "Component name, e.g. waybar"),
+            ("config", "Full new contents of the config file"),
+        ],
+        mutating: true,
+    },
+    ToolDef {
+        group: "daemon",
+        name: "daemon_list_services",
+        description: "List the user's systemd services.",
+        params: &[],
+        mutating: false,
+    },
+    ToolDef {
+        group: "daemon",
+        name: "daemon_start_service",
+        description: "Start a systemd user service.",
+        params: &[("name", "Unit name, e.g. waybar.service")],
+        mutating: true,
+    },
+    ToolDef {
+        group: "cron",
+        name: "cron_list_jobs",
+        description: "List the user's crontab entries.",
+        params: &[],
+        mutating: false,
+    },
+    ToolDef {
+        group: "cron",
+        name: "cron_create_job",
+        description: "Append a job to the user's crontab.",
+        params: &[
+            ("schedule", "Five-field cron schedule, e.g. */5 * * * *"),
+            ("command", "Shell command to run"),
+        ],
+        mutating: true,
+    },
+    ToolDef {
+        group: "container",
+        name: "container_list",
+        description: "List saved desktop containers (full configuration world states).",
+        params: &[],
+        mutating: false,
+    },
+    ToolDef {
+        group: "container",
+        name: "container_load",
+        description: "Load a desktop container, replacing the current configs with the saved ones.",
+        params: &[("name", "Container name")],
+        mutating: true,
+    },
+    ToolDef {
+        group: "file",
+        name: "file_read",
+        description: "Read a text file. Rice config directories are readable directly; other paths need the user's approval, and NixDeck's own data and ~/.ssh are never readable.",
+        params: &[("path", "Absolute path, or a path starting with ~/")],
+        mutating: false,
+    },
+    ToolDef {
+        group: "file",
+        name: "file_list",
+        description: "List the entries of a directory. Rice config directories are listable directly; other paths need the user's approval, and NixDeck's own data and ~/.ssh are never listable.",
+        params: &[("path", "Absolute path, or a path starting with ~/")],
+        mutating: false,
+    },
+    ToolDef {
+        group: "operations",
+        name: "operation_create",
+        description: "Queue a multi-step operation for the user to review and start. Each step runs one of these tools (kind \"command\") or a shell command (kind \"shell\").",
+        params: &[
+            ("title", "Short title for the operation"),
+            (
+                "steps",
+                "JSON array of steps, e.g. [{\"description\": \"Reload waybar\", \"kind\": \"shell\", \"command\": \"pkill -USR2 waybar\", \"retries\": 1}] or {\"kind\": \"command\", \"name\": \"daemon_start_service\", \"args\": {\"name\": \"x.service\"}}",
+            ),
+        ],
+        mutating: true,
+    },
+];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolSchema {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    function: FunctionSchema,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct FunctionSchema {
+    name: &'static str,
+    description: &'static str,
+    parameters: Value,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ApprovalEvent {
+    pub approval_id: String,
+    pub request_id: String,
+    pub tool: String,
+    pub arguments: Value,
+}
+
+// An approval prompt nobody answered in time, or whose request ended first
+#[derive(Debug, Clone, Serialize)]
+pub struct ApprovalCancelledEvent {
+    pub approval_id: String,
+    pub request_id: String,
+}
+
+// Build the function-calling schemas for the tool groups a loadout enables
+pub fn schemas_for(tools_enabled: &[String]) -> Vec<ToolSchema> {
+    TOOLS
+        .iter()
+        .filter(|tool| tools_enabled.iter().any(|group| group == tool.group))
+        .map(|tool| {
+            let mut properties = Map::new();
+            for (param, description) in tool.params {
+                properties.insert(
+                    param.to_string(),
+                    json!({ "type": "string", "description": description }),
+                );
+            }
+            let required: Vec<&str> = tool.params.iter().map(|(param, _)| *param).collect();
+
+            ToolSchema {
+                kind: "function",
+                function: FunctionSchema {
+                    name: tool.name,
+                    description: tool.description,
+                    parameters: json!({
+                        "type": "object",
+                        "properties": properties,
+                        "required": required,
+                    }),
+                },
+            }
+        })
+        .collect()
+}
+
+pub fn is_known_group(group: &str) -> bool {
+    TOOLS.iter().any(|tool| tool.group == group)
+}
+
+pub fn is_known_command(name: &str) -> bool {
+    TOOLS.iter().any(|tool| tool.name == name)
+}
+
+// Tools that change the system; operation steps running them need confirmation
+// unless the loadout says otherwise
+pub fn mutating_commands() -> Vec<String> {
+    TOOLS
+        .iter()
+        .filter(|tool| tool.mutating)
+        .map(|tool| tool.name.to_string())
+        .collect()
+}
+
+// Run a tool outside a chat exchange, for operation steps the user has queued.
+// There is nobody to ask, so file tools stay inside the rice config directories.
+pub async fn run_command(name: &str, args: &HashMap<String, String>, loadout: &str) -> Result<String, String> {
+    if !is_known_command(name) {
+        return Err(format!("unknown tool '{}'", name));
+    }
+    execute(name, args, loadout, false).await
+}
+
+// Run one tool call on behalf of the model. Failures are reported back to the
+// model as text rather than aborting the exchange.
+pub async fn run_call(
+    window: &tauri::Window,
+    request_id: &str,
+    loadout: &AILoadout,
+    call: &ToolCall,
+) -> String {
+    let tool = match TOOLS.iter().find(|tool| tool.name == call.function.name) {
+        Some(tool) if loadout.tools_enabled.iter().any(|group| group == tool.group) => tool,
+        _ => return format!("Error: tool '{}' is not available", call.function.name),
+    };
+
+    let args = match parse_arguments(&call.function.arguments) {
+        Ok(args) => args,
+        Err(e) => return format!("Error: {}", e),
+    };
+
+    // File tools outside the rice config directories ask like mutating tools do
+    let access = match tool.group {
+        "file" => match arg(&args, "path") {
+            Ok(path) => file_access(&expand_home(&path)),
+            Err(e) => return format!("Error: {}", e),
+        },
+        _ => FileAccess::Open,
+    };
+    if let FileAccess::Denied(reason) = access {
+        return format!("Error: {}", reason);
+    }
+    let needs_approval = tool.mutating || matches!(access, FileAccess::Ask);
+    if needs_approval && !request_approval(window, request_id, call, &args).await {
+        return "Error: the user declined this action".to_string();
+    }
+
+    match execute(tool.name, &args, &loadout.name, needs_approval).await {
+        Ok(output) => output,
+        Err(e) => format!("Error: {}", e),
+    }
+}
+
+pub async fn resolve_approval(approval_id: String, approved: bool) -> Result<(), String> {
+    let sender = pending_approvals()
+        .lock()
+        .map_err(|e| e.to_string())?
+        .remove(&approval_id)
+        .ok_or_else(|| format!("No pending approval '{}'", approval_id))?;
+
+    sender
+        .send(approved)
+        .map_err(|_| format!("Approval '{}' is no longer waiting", approval_id))
+}
+
+// Approval prompts waiting on the user, keyed by approval ID
+fn pending_approvals() -> &'static Mutex<HashMap<String, oneshot::Sender<bool>>> {
+    static PENDING: OnceLock<Mutex<HashMap<String, oneshot::Sender<bool>>>> = OnceLock::new();
+    PENDING.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+async fn request_approval(
+    window: &tauri::Window,
+    request_id: &str,
+    call: &ToolCall,
+    args: &HashMap<String, String>,
+) -> bool {
+    let approval_id = format!("{}:{}", request_id, call.id);
+    let (sender, receiver) = oneshot::channel();
+
+    match pending_approvals().lock() {
+        Ok(mut pending) => {
+            pending.insert(approval_id.clone(), sender);
+        }
+        Err(_) => return false,
+    }
+    let _pending = PendingApproval {
+        window,
+        approval_id: approval_id.clone(),
+        request_id: request_id.to_string(),
+    };
+
+    let event = ApprovalEvent {
+        approval_id: approval_id.clone(),
+        request_id: request_id.to_string(),
+        tool: call.function.name.clone(),
+        arguments: json!(args),
+    };
+
+    if window.emit(EVENT_TOOL_APPROVAL, event).is_ok() {
+        matches!(tokio::time::timeout(APPROVAL_TIMEOUT, receiver).await, Ok(Ok(true)))
+    } else {
+        false
+    }
+}
+
+// Takes the approval off the pending list however the wait ends, including a
+// cancelled stream dropping it mid-wait. One still listed was never answered,
+// so the UI is told to dismiss its prompt.
+struct PendingApproval<'a> {
+    window: &'a tauri::Window,
+    approval_id: String,
+    request_id: String,
+}
+
+impl Drop for PendingApproval<'_> {
+    fn drop(&mut self) {
+        let unanswered = pending_approvals()
+            .lock()
+            .map(|mut pending| pending.remove(&self.approval_id).is_some())
+            .unwrap_or(false);
+        if unanswered {
+            let _ = self.window.emit(EVENT_TOOL_APPROVAL_CANCELLED, ApprovalCancelledEvent {
+                approval_id: self.approval_id.clone(),
+                request_id: self.request_id.clone(),
+            });
+        }
+    }
+}
+
+fn parse_arguments(raw: &str) -> Result<HashMap<String, String>, String> {
+    if raw.trim().is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let values: HashMap<String, Value> = serde_json::from_str(raw)
+        .map_err(|e| format!("invalid tool arguments: {}", e))?;
+
+    Ok(values
+        .into_iter()
+        .map(|(key, value)| match value {
+            Value::String(s) => (key, s),
+            other => (key, other.to_string()),
+        })
+        .collect())
+}
+
+fn arg(args: &HashMap<String, String>, name: &str) -> Result<String, String> {
+    args.get(name)
+        .cloned()
+        .ok_or_else(|| format!("missing argument '{}'", name))
+}
+
+// `approved` says the user allowed this call, which opens file paths that
+// would otherwise need asking
+async fn execute(name: &str, args: &HashMap<String, String>, loadout: &str, approved: bool) -> Result<String, String> {
+    match name {
+        "rice_get_config" => crate::rice::get_config(arg(args, "component")?).await,
+        "rice_apply_config" => {
+            let component = arg(args, "component")?;
+            crate::rice::apply_config(component.clone(), arg(args, "config")?, false).await?;
+            Ok(format!("Applied new {} config", component))
+        }
+        "daemon_list_services" => Ok(crate::daemon::list_services().await?.join("\n")),
+        "daemon_start_service" => {
+            let service = arg(args, "name")?;
+            crate::daemon::start_service(service.clone()).await?;
+            Ok(format!("Started {}", service))
+        }
+        "cron_list_jobs" => {
+            let jobs = crate::cron_mod::list_jobs().await?;
+            Ok(if jobs.is_empty() { "(no cron jobs)".to_string() } else { jobs.join("\n") })
+        }
+        "cron_create_job" => {
+            crate::cron_mod::create_job(arg(args, "schedule")?, arg(args, "command")?).await?;
+            Ok("Cron job created".to_string())
+        }
+        "container_list" => Ok(crate::container::list().await?.join("\n")),
+        "container_load" => {
+            let container = arg(args, "name")?;
+            crate::container::load(container.clone()).await?;
+            Ok(format!("Loaded container {}", container))
+        }
+        "file_read" => read_file(&readable_path(args, approved)?),
+        "file_list" => {
+            let path = readable_path(args, approved)?;
+            let entries = std::fs::read_dir(&path)
+                .map_err(|e| format!("Failed to read directory {}: {}", path.display(), e))?;
+            let mut names: Vec<String> = entries
+                .flatten()
+                .map(|entry| {
+                    let name = entry.file_name().to_string_lossy().to_string();
+                    if entry.path().is_dir() { format!("{}/", name) } else { name }
+                })
+                .collect();
+            names.sort();
+            Ok(names.join("\n"))
+        }
+        "operation_create" => {
+            let steps: Vec<crate::operations::StepPlan> = serde_json::from_str(&arg(args, "steps")?)
+                .map_err(|e| format!("invalid steps: {}", e))?;
+            let operation = crate::operations::create(arg(args, "title")?, steps, Some(loadout.to_string())).await?;
+            Ok(format!(
+                "Queued operation {} with {} steps; the user will review and start it",
+                operation.id,
+                operation.steps.len()
+            ))
+        }
+        _ => Err(format!("unknown tool '{}'", name)),
+    }
+}
+
+fn read_file(path: &Path) -> Result<String, String> {
+    let bytes = std::fs::read(path)
+        .map_err(|e| format!("Failed to read file {}: {}", path.display(), e))?;
+
+    let truncated = bytes.len() > MAX_FILE_BYTES;
+    let mut content = String::from_utf8_lossy(&bytes[..bytes.len().min(MAX_FILE_BYTES)]).to_string();
+    if truncated {
+        content.push_str(&format!("\n[... truncated, {} bytes total]", bytes.len()));
+    }
+    Ok(content)
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+enum FileAccess {
+    // Inside a rice config directory
+    Open,
+    // Anywhere else; the user decides
+    Ask,
+    Denied(String),
+}
+
+// The `path` argument of a file tool, checked again at the point of use
+fn readable_path(args: &HashMap<String, String>, approved: bool) -> Result<PathBuf, String> {
+    let path = expand_home(&arg(args, "path")?);
+    match file_access(&path) {
+        FileAccess::Open => Ok(path),
+        FileAccess::Ask if approved => Ok(path),
+        FileAccess::Ask => Err(format!("{} is outside the rice config directories", path.display())),
+        FileAccess::Denied(reason) => Err(reason),
+    }
+}
+
+// NixDeck's own data (credentials included) and ssh keys are never handed to
+// the model; the registry's config locations are, and anything else is asked
+// about. Links and `..` are resolved first, so neither leads out of a config
+// directory.
+fn file_access(path: &Path) -> FileAccess {
+    let Some(home) = dirs::home_dir() else {
+        return FileAccess::Denied("Could not find home directory".to_string());
+    };
+    if !path.is_absolute() {
+        return FileAccess::Denied(format!("{} is not an absolute path", path.display()));
+    }
+    let path = std::fs::canonicalize(path).unwrap_or_else(|_| normalize(path));
+    let home_real = std::fs::canonicalize(&home).unwrap_or_else(|_| home.clone());
+
+    for private in [".nixdeck", ".ssh"] {
+        if path.starts_with(home.join(private)) || path.starts_with(home_real.join(private)) {
+            return FileAccess::Denied(format!("~/{} is never readable by the assistant", private));
+        }
+    }
+
+    if rice_roots().iter().any(|root| path.starts_with(root)) {
+        FileAccess::Open
+    } else {
+        FileAccess::Ask
+    }
+}
+
+// Every registered component's config directory, snapshot directory and
+// extra files. A config kept straight in the home or config directory, like
+// ~/.conkyrc, or a system-wide default opens only itself, not its parent.
+fn rice_roots() -> Vec<PathBuf> {
+    let shared: Vec<PathBuf> = [dirs::home_dir(), crate::paths::config_home().ok()]
+        .into_iter()
+        .flatten()
+        .collect();
+
+    let mut roots = Vec::new();
+    for component in crate::rice::components() {
+        if let Ok(resolution) = component.resolve() {
+            for config in resolution.active.into_iter().chain([resolution.target]) {
+                match config.parent() {
+                    Some(dir) if crate::paths::is_user_path(dir) && !shared.iter().any(|shared| shared == dir) => {
+                        roots.push(dir.to_path_buf())
+                    }
+                    _ => roots.push(config),
+                }
+            }
+        }
+        roots.extend(component.extra_files());
+        roots.extend(component.snapshot_dir().map(|(_, dir)| dir));
+    }
+
+    roots
+        .into_iter()
+        .map(|root| std::fs::canonicalize(&root).unwrap_or(root))
+        .collect()
+}
+
+// `..` and `.` removed without touching the filesystem, for paths that don't exist
+fn normalize(path: &Path) -> PathBuf {
+    let mut normal = PathBuf::new();
+    for part in path.components() {
+        match part {
+            Component::ParentDir => {
+                normal.pop();
+            }
+            Component::CurDir => {}
+            other => normal.push(other),
+        }
+    }
+    normal
+}
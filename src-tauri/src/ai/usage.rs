@@ -0,0 +1,194 @@
+// NixDeck 2133 - AI Usage Ledger
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+
+use super::catalog;
+use super::client::{AIError, Usage};
+use super::provider::ProviderKind;
+use super::AILoadout;
+
+// One provider request, appended as a JSON line to the ledger
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageEntry {
+    pub timestamp: String,
+    pub loadout: String,
+    pub provider: ProviderKind,
+    pub model: String,
+    pub conversation: Option<String>,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    // None when the model's price isn't known
+    pub cost_usd: Option<f64>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct UsageBucket {
+    pub key: String,
+    pub requests: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub cost_usd: f64,
+    pub unpriced_requests: u64,
+}
+
+pub fn record(
+    loadout: &AILoadout,
+    model: &str,
+    conversation: Option<&str>,
+    usage: Usage,
+) -> Result<(), String> {
+    let cost_usd = catalog::price_for(loadout, model).map(|price| {
+        usage.prompt_tokens as f64 * price.prompt + usage.completion_tokens as f64 * price.completion
+    });
+
+    let entry = UsageEntry {
+        timestamp: chrono::Local::now().to_rfc3339(),
+        loadout: loadout.name.clone(),
+        provider: loadout.provider,
+        model: model.to_string(),
+        conversation: conversation.map(str::to_string),
+        prompt_tokens: usage.prompt_tokens,
+        completion_tokens: usage.completion_tokens,
+        cost_usd,
+    };
+
+    let ledger_path = get_ledger_path();
+    if let Some(parent) = ledger_path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create usage directory: {}", e))?;
+    }
+
+    let line = serde_json::to_string(&entry)
+        .map_err(|e| format!("Failed to serialize usage entry: {}", e))?;
+
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(ledger_path)
+        .map_err(|e| format!("Failed to open usage ledger: {}", e))?;
+
+    writeln!(file, "{}", line)
+        .map_err(|e| format!("Failed to write usage ledger: {}", e))
+}
+
+// Aggregate the ledger by "day", "month", "loadout", "model" or "conversation".
+// `since`/`until` are inclusive YYYY-MM-DD bounds.
+pub async fn summarize(
+    group_by: String,
+    since: Option<String>,
+    until: Option<String>,
+) -> Result<Vec<UsageBucket>, String> {
+    let key_of: fn(&UsageEntry) -> String = match group_by.as_str() {
+        "day" => |entry| entry.timestamp.chars().take(10).collect(),
+        "month" => |entry| entry.timestamp.chars().take(7).collect(),
+        "loadout" => |entry| entry.loadout.clone(),
+        "model" => |entry| entry.model.clone(),
+        "conversation" => |entry| entry.conversation.clone().unwrap_or_else(|| "(none)".to_string()),
+        other => return Err(format!("Unknown grouping: {}", other)),
+    };
+
+    let mut buckets: BTreeMap<String, UsageBucket> = BTreeMap::new();
+    for entry in read_ledger()? {
+        let day = &entry.timestamp[..entry.timestamp.len().min(10)];
+        if since.as_deref().is_some_and(|since| day < since) {
+            continue;
+        }
+        if until.as_deref().is_some_and(|until| day > until) {
+            continue;
+        }
+
+        let key = key_of(&entry);
+        let bucket = buckets.entry(key.clone()).or_insert_with(|| UsageBucket {
+            key,
+            ..Default::default()
+        });
+        bucket.requests += 1;
+        bucket.prompt_tokens += entry.prompt_tokens;
+        bucket.completion_tokens += entry.completion_tokens;
+        match entry.cost_usd {
+            Some(cost) => bucket.cost_usd += cost,
+            None => bucket.unpriced_requests += 1,
+        }
+    }
+
+    Ok(buckets.into_values().collect())
+}
+
+// Refuse to send once the loadout has spent its budget for the calendar
+// month. Spending on a model with no known price can't be bounded, so that
+// is refused too; ledger entries recorded before a price was known are
+// costed at today's price.
+pub fn check_budget(loadout: &AILoadout) -> Result<(), AIError> {
+    let budget = match loadout.monthly_budget {
+        Some(budget) => budget,
+        None => return Ok(()),
+    };
+
+    let unpriced = |model: &str| AIError::BudgetUnpriced {
+        loadout: loadout.name.clone(),
+        model: model.to_string(),
+    };
+    if let Some(model) = loadout.models().iter().find(|model| catalog::price_for(loadout, model).is_none()) {
+        return Err(unpriced(model));
+    }
+
+    // Uncosted entries are usually for the same few models
+    let mut prices = HashMap::new();
+    let month = chrono::Local::now().format("%Y-%m").to_string();
+    let mut spent = 0.0;
+    // Recording usage is best-effort, but enforcing the budget isn't: spending
+    // that can't be read isn't taken to be nothing
+    let ledger = read_ledger().map_err(|reason| AIError::BudgetUnavailable {
+        loadout: loadout.name.clone(),
+        reason,
+    })?;
+    for entry in ledger {
+        if entry.loadout != loadout.name || !entry.timestamp.starts_with(&month) {
+            continue;
+        }
+        spent += match entry.cost_usd {
+            Some(cost) => cost,
+            None => {
+                let price = *prices
+                    .entry(entry.model.clone())
+                    .or_insert_with(|| catalog::price_for(loadout, &entry.model));
+                let price = price.ok_or_else(|| unpriced(&entry.model))?;
+                entry.prompt_tokens as f64 * price.prompt + entry.completion_tokens as f64 * price.completion
+            }
+        };
+    }
+
+    if spent >= budget {
+        return Err(AIError::BudgetExceeded {
+            loadout: loadout.name.clone(),
+            spent,
+            budget,
+        });
+    }
+
+    Ok(())
+}
+
+fn read_ledger() -> Result<Vec<UsageEntry>, String> {
+    // Only a ledger that isn't there yet is empty; exists() would also say
+    // false for one behind a directory that can't be read
+    let content = match fs::read_to_string(get_ledger_path()) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read usage ledger: {}", e)),
+    };
+
+    // A torn final line from a crash shouldn't hide the rest of the history
+    Ok(content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+fn get_ledger_path() -> PathBuf {
+    let home = dirs::home_dir().expect("Could not find home directory");
+    home.join(".nixdeck").join("usage").join("ledger.jsonl")
+}
@@ -0,0 +1,116 @@
+// NixDeck 2133 - Atomic File Writes
+//
+// Every file NixDeck rewrites in place goes through a temp file beside it:
+// written, synced, then renamed over the original. A rename is atomic, so a
+// crash leaves either the old file or the new one, never half of either.
+// Scratch work in the shared temp directory gets a private directory of its own.
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// New content waiting beside the file it will replace
+pub struct Staged {
+    pub path: PathBuf,
+    tmp: PathBuf,
+}
+
+impl Staged {
+    // Rename the new content into place; on failure the temp file is removed
+    // and the original is untouched
+    pub fn commit(&self) -> Result<(), String> {
+        if let Err(e) = fs::rename(&self.tmp, &self.path) {
+            self.discard();
+            return Err(format!("Failed to replace {}: {}", self.path.display(), e));
+        }
+
+        // Make the rename itself durable
+        if let Some(dir) = self.path.parent() {
+            if let Ok(dir) = fs::File::open(dir) {
+                let _ = dir.sync_all();
+            }
+        }
+        Ok(())
+    }
+
+    pub fn discard(&self) {
+        let _ = fs::remove_file(&self.tmp);
+    }
+}
+
+// Replace `path` with `content` in one step. See `stage` for `mode`.
+pub fn write(path: &Path, content: &[u8], mode: Option<u32>) -> Result<(), String> {
+    stage(path, content, mode)?.commit()
+}
+
+// Write `content` to a temp file beside `path` and sync it, ready to be
+// committed. The file gets `mode` when one is given; otherwise it keeps the
+// permissions of the file it replaces, or the umask's for a new one.
+pub fn stage(path: &Path, content: &[u8], mode: Option<u32>) -> Result<Staged, String> {
+    let dir = path.parent().ok_or_else(|| format!("{} has no parent directory", path.display()))?;
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+
+    // Writers in one process, or two running instances, each get their own temp file
+    static WRITES: AtomicUsize = AtomicUsize::new(0);
+    let file_name = path.file_name().and_then(|name| name.to_str()).unwrap_or("file");
+    let tmp = dir.join(format!(
+        ".{}.nixdeck-{}-{}.tmp",
+        file_name,
+        std::process::id(),
+        WRITES.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let permissions = match mode {
+        Some(mode) => Some(fs::Permissions::from_mode(mode)),
+        None => fs::metadata(path).ok().map(|metadata| metadata.permissions()),
+    };
+
+    let written = (|| -> std::io::Result<()> {
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        // Created with the mode rather than chmod-ed afterwards, so a private
+        // file is never readable by others, even for a moment
+        if let Some(mode) = mode {
+            options.mode(mode);
+        }
+        let mut file = options.open(&tmp)?;
+
+        // mode() only applies on creation; set it on a leftover temp file too
+        if let Some(permissions) = permissions {
+            file.set_permissions(permissions)?;
+        }
+        file.write_all(content)?;
+        file.sync_all()
+    })();
+    if let Err(e) = written {
+        let _ = fs::remove_file(&tmp);
+        return Err(format!("Failed to write {}: {}", tmp.display(), e));
+    }
+
+    Ok(Staged { path: path.to_path_buf(), tmp })
+}
+
+// A new directory under the system temp directory that only this user can
+// enter. It is never one that already existed: another user could have
+// created that, and planted symlinks in it for our writes to follow.
+pub fn private_dir(prefix: &str) -> Result<PathBuf, String> {
+    static DIRS: AtomicUsize = AtomicUsize::new(0);
+
+    for _ in 0..100 {
+        let dir = std::env::temp_dir().join(format!(
+            "nixdeck-{}-{}-{}-{}",
+            prefix,
+            std::process::id(),
+            chrono::Local::now().timestamp_millis(),
+            DIRS.fetch_add(1, Ordering::Relaxed)
+        ));
+        match fs::DirBuilder::new().mode(0o700).create(&dir) {
+            Ok(()) => return Ok(dir),
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(format!("Failed to create temporary directory: {}", e)),
+        }
+    }
+
+    Err("Failed to create temporary directory: every name tried was taken".to_string())
+}
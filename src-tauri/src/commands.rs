@@ -85,42 +85,298 @@ pub async fn list_directory(path: String) -> Result<Vec<String>, String> {
 // ============================================================================
 
 #[tauri::command]
-pub async fn send_ai_message(message: String, loadout: String) -> Result<String, String> {
-    crate::ai::send_message(message, loadout).await
+pub async fn send_ai_message(
+    window: tauri::Window,
+    message: String,
+    loadout: String,
+    request_id: Option<String>,
+    conversation: Option<String>,
+) -> Result<crate::ai::Reply, String> {
+    // Passing a request ID switches to streaming mode (ai://chunk events)
+    match request_id {
+        Some(request_id) => {
+            crate::ai::stream_message(window, request_id, message, loadout, conversation).await
+        }
+        None => crate::ai::send_message(window, message, loadout, conversation).await,
+    }
+}
+
+#[tauri::command]
+pub async fn respond_ai_tool_call(approval_id: String, approved: bool) -> Result<(), String> {
+    crate::ai::resolve_approval(approval_id, approved).await
+}
+
+#[tauri::command]
+pub async fn cancel_ai_message(request_id: String) -> Result<(), String> {
+    crate::ai::cancel_stream(request_id).await
+}
+
+#[tauri::command]
+pub async fn create_ai_conversation(title: Option<String>, loadout: String) -> Result<crate::ai::Conversation, String> {
+    crate::ai::create_conversation(title, loadout).await
+}
+
+#[tauri::command]
+pub async fn list_ai_conversations() -> Result<Vec<crate::ai::ConversationSummary>, String> {
+    crate::ai::list_conversations().await
+}
+
+#[tauri::command]
+pub async fn load_ai_conversation(id: String) -> Result<crate::ai::Conversation, String> {
+    crate::ai::load_conversation(id).await
+}
+
+#[tauri::command]
+pub async fn delete_ai_conversation(id: String) -> Result<(), String> {
+    crate::ai::delete_conversation(id).await
+}
+
+#[tauri::command]
+pub async fn rename_ai_conversation(id: String, title: String) -> Result<(), String> {
+    crate::ai::rename_conversation(id, title).await
+}
+
+#[tauri::command]
+pub async fn get_ai_usage_summary(
+    group_by: String,
+    since: Option<String>,
+    until: Option<String>,
+) -> Result<Vec<crate::ai::UsageBucket>, String> {
+    crate::ai::summarize_usage(group_by, since, until).await
 }
 
 #[tauri::command]
-pub async fn load_ai_loadout(name: String) -> Result<String, String> {
+pub async fn load_ai_loadout(name: String) -> Result<crate::ai::AILoadout, String> {
     crate::ai::load_loadout(name).await
 }
 
 #[tauri::command]
-pub async fn save_ai_loadout(name: String, config: String) -> Result<(), String> {
+pub async fn save_ai_loadout(name: String, config: crate::ai::AILoadout) -> Result<Vec<String>, String> {
     crate::ai::save_loadout(name, config).await
 }
 
+#[tauri::command]
+pub async fn delete_ai_loadout(name: String) -> Result<(), String> {
+    crate::ai::delete_loadout(name).await
+}
+
+#[tauri::command]
+pub async fn rename_ai_loadout(name: String, new_name: String) -> Result<(), String> {
+    crate::ai::rename_loadout(name, new_name).await
+}
+
+#[tauri::command]
+pub async fn duplicate_ai_loadout(name: String, new_name: String) -> Result<(), String> {
+    crate::ai::duplicate_loadout(name, new_name).await
+}
+
 #[tauri::command]
 pub async fn list_ai_loadouts() -> Result<Vec<String>, String> {
     crate::ai::list_loadouts().await
 }
 
+#[tauri::command]
+pub async fn validate_ai_loadout(name: String) -> Result<Vec<String>, String> {
+    crate::ai::check_loadout(name).await
+}
+
+#[tauri::command]
+pub async fn list_ai_models(
+    provider: crate::ai::ProviderKind,
+    endpoint: Option<String>,
+    refresh: Option<bool>,
+) -> Result<crate::ai::ModelCatalog, String> {
+    crate::ai::list_models(provider, endpoint, refresh.unwrap_or(false)).await
+}
+
+// Ask the loadout for a replacement config; returns it for review without writing
+#[tauri::command]
+pub async fn propose_rice_config(
+    window: tauri::Window,
+    component: String,
+    request: String,
+    loadout: String,
+) -> Result<crate::ai::RiceProposal, String> {
+    crate::ai::propose_rice(window, component, request, loadout).await
+}
+
+#[tauri::command]
+pub async fn export_ai_loadout_bundle(loadouts: Vec<String>, path: String) -> Result<(), String> {
+    crate::ai::export_bundle(loadouts, path).await
+}
+
+#[tauri::command]
+pub async fn inspect_ai_loadout_bundle(path: String) -> Result<Vec<crate::ai::BundleItem>, String> {
+    crate::ai::inspect_bundle(path).await
+}
+
+#[tauri::command]
+pub async fn import_ai_loadout_bundle(
+    path: String,
+    resolutions: Option<std::collections::BTreeMap<String, crate::ai::Resolution>>,
+) -> Result<Vec<crate::ai::BundleItem>, String> {
+    crate::ai::import_bundle(path, resolutions.unwrap_or_default()).await
+}
+
+#[tauri::command]
+pub async fn list_prompt_templates() -> Result<Vec<String>, String> {
+    crate::ai::list_templates().await
+}
+
+#[tauri::command]
+pub async fn load_prompt_template(name: String) -> Result<String, String> {
+    crate::ai::load_template(name).await
+}
+
+#[tauri::command]
+pub async fn save_prompt_template(name: String, content: String) -> Result<(), String> {
+    crate::ai::save_template(name, content).await
+}
+
+#[tauri::command]
+pub async fn render_prompt_template(
+    name: String,
+    variables: Option<std::collections::BTreeMap<String, String>>,
+) -> Result<crate::ai::RenderedPrompt, String> {
+    crate::ai::render_template(name, variables.unwrap_or_default()).await
+}
+
+#[tauri::command]
+pub async fn preview_ai_system_prompt(loadout: String) -> Result<crate::ai::RenderedPrompt, String> {
+    crate::ai::preview_system_prompt(loadout).await
+}
+
+#[tauri::command]
+pub async fn refresh_ai_index() -> Result<crate::ai::IndexStats, String> {
+    crate::ai::refresh_index().await
+}
+
+#[tauri::command]
+pub async fn search_ai_index(
+    query: String,
+    sources: Option<Vec<String>>,
+    limit: Option<usize>,
+) -> Result<Vec<crate::ai::Snippet>, String> {
+    crate::ai::search_index(query, sources, limit.unwrap_or(5)).await
+}
+
+#[tauri::command]
+pub async fn get_ai_redaction_rules() -> Result<crate::ai::RedactionRules, String> {
+    crate::ai::get_redaction_rules().await
+}
+
+#[tauri::command]
+pub async fn save_ai_redaction_rules(rules: crate::ai::RedactionRules) -> Result<(), String> {
+    crate::ai::save_redaction_rules(rules).await
+}
+
+// ============================================================================
+// CREDENTIAL COMMANDS
+// ============================================================================
+
+#[tauri::command]
+pub async fn set_api_key(provider: String, key: String) -> Result<(), String> {
+    crate::credentials::set_key(provider, key).await
+}
+
+#[tauri::command]
+pub async fn get_api_key_status(provider: String) -> Result<crate::credentials::CredentialStatus, String> {
+    crate::credentials::get_status(provider).await
+}
+
+#[tauri::command]
+pub async fn clear_api_key(provider: String) -> Result<(), String> {
+    crate::credentials::clear_key(provider).await
+}
+
 // ============================================================================
 // RICE COMMANDS
 // ============================================================================
 
+#[tauri::command]
+pub async fn list_rice_components() -> Result<crate::rice::Registry, String> {
+    crate::rice::list_components().await
+}
+
 #[tauri::command]
 pub async fn get_rice_config(component: String) -> Result<String, String> {
     crate::rice::get_config(component).await
 }
 
 #[tauri::command]
-pub async fn apply_rice_config(component: String, config: String) -> Result<(), String> {
-    crate::rice::apply_config(component, config).await
+pub async fn get_rice_config_tree(component: String) -> Result<crate::rice::ConfigTree, String> {
+    crate::rice::get_config_tree(component).await
+}
+
+#[tauri::command]
+pub async fn resolve_rice_config(component: String) -> Result<crate::rice::Resolution, String> {
+    crate::rice::resolve_config(component).await
+}
+
+#[tauri::command]
+pub async fn apply_rice_config(component: String, config: String, force: Option<bool>) -> Result<(), String> {
+    crate::rice::apply_config(component, config, force.unwrap_or(false)).await
+}
+
+#[tauri::command]
+pub async fn apply_rice_files(
+    component: String,
+    files: Vec<crate::rice::FileChange>,
+    force: Option<bool>,
+) -> Result<(), String> {
+    crate::rice::apply_files(component, files, force.unwrap_or(false)).await
+}
+
+#[tauri::command]
+pub async fn validate_rice_config(component: String, config: String) -> Result<crate::rice::Validation, String> {
+    crate::rice::validate_config(component, config).await
+}
+
+#[tauri::command]
+pub async fn list_rice_backups(component: String) -> Result<Vec<crate::rice::Backup>, String> {
+    crate::rice::list_backups(component).await
+}
+
+#[tauri::command]
+pub async fn diff_rice_backup(
+    component: String,
+    id: String,
+    word_highlight: Option<bool>,
+) -> Result<Vec<crate::diff::FileDiff>, String> {
+    crate::rice::diff_backup(component, id, word_highlight.unwrap_or(true)).await
+}
+
+#[tauri::command]
+pub async fn restore_rice_backup(component: String, id: String) -> Result<(), String> {
+    crate::rice::restore_backup(component, id).await
+}
+
+#[tauri::command]
+pub async fn reload_rice_component(component: String) -> Result<crate::rice::ReloadReport, String> {
+    crate::rice::reload_component(component).await
 }
 
 #[tauri::command]
-pub async fn preview_rice_config(component: String, config: String) -> Result<String, String> {
-    crate::rice::preview_config(component, config).await
+pub async fn get_rice_value(component: String, key_path: String) -> Result<serde_json::Value, String> {
+    crate::rice::get_value(component, key_path).await
+}
+
+#[tauri::command]
+pub async fn set_rice_value(
+    component: String,
+    key_path: String,
+    value: serde_json::Value,
+) -> Result<(), String> {
+    crate::rice::set_value(component, key_path, value).await
+}
+
+#[tauri::command]
+pub async fn preview_rice_config(
+    component: String,
+    config: String,
+    word_highlight: Option<bool>,
+) -> Result<crate::diff::FileDiff, String> {
+    crate::rice::preview_config(component, config, word_highlight.unwrap_or(true)).await
 }
 
 // ============================================================================
@@ -205,6 +461,14 @@ pub async fn delete_container(name: String) -> Result<(), String> {
     crate::container::delete(name).await
 }
 
+#[tauri::command]
+pub async fn compare_container(
+    name: String,
+    other: Option<String>,
+) -> Result<Vec<crate::diff::FileDiff>, String> {
+    crate::container::compare(name, other).await
+}
+
 #[tauri::command]
 pub async fn export_container(name: String, path: String) -> Result<(), String> {
     crate::container::export(name, path).await
@@ -253,6 +517,49 @@ pub async fn save_theme(name: String, content: String) -> Result<(), String> {
     crate::theme::save_theme(name, content).await
 }
 
+// ============================================================================
+// OPERATION COMMANDS
+// ============================================================================
+
+#[tauri::command]
+pub async fn create_operation(
+    title: String,
+    steps: Vec<crate::operations::StepPlan>,
+    loadout: Option<String>,
+) -> Result<crate::operations::Operation, String> {
+    crate::operations::create(title, steps, loadout).await
+}
+
+#[tauri::command]
+pub async fn list_operations() -> Result<Vec<crate::operations::Operation>, String> {
+    crate::operations::list().await
+}
+
+#[tauri::command]
+pub async fn get_operation(id: String) -> Result<crate::operations::Operation, String> {
+    crate::operations::get(id).await
+}
+
+#[tauri::command]
+pub async fn start_operation(window: tauri::Window, id: String) -> Result<(), String> {
+    crate::operations::start(window, id).await
+}
+
+#[tauri::command]
+pub async fn confirm_operation_step(window: tauri::Window, id: String, approved: bool) -> Result<(), String> {
+    crate::operations::confirm_step(window, id, approved).await
+}
+
+#[tauri::command]
+pub async fn abort_operation(window: tauri::Window, id: String) -> Result<(), String> {
+    crate::operations::abort(window, id).await
+}
+
+#[tauri::command]
+pub async fn delete_operation(id: String) -> Result<(), String> {
+    crate::operations::delete(id).await
+}
+
 // ============================================================================
 // HELPER FUNCTIONS
 // ============================================================================
@@ -22,14 +22,14 @@ pub async fn create(name: String) -> Result<(), String> {
         .map_err(|e| format!("Failed to create container directory: {}", e))?;
     
     // Snapshot current configuration
-    snapshot_configs(&container_dir).await?;
+    let components = snapshot_configs(&container_dir).await?;
     
     // Create metadata
     let metadata = Container {
         name: name.clone(),
         created: chrono::Local::now().to_rfc3339(),
         description: String::new(),
-        components: get_snapshot_components(),
+        components,
     };
     
     let metadata_path = container_dir.join("metadata.json");
@@ -39,7 +39,8 @@ pub async fn create(name: String) -> Result<(), String> {
     fs::write(metadata_path, metadata_json)
         .map_err(|e| format!("Failed to write metadata: {}", e))?;
     
-    Ok(())
+    // The desktop now matches this container
+    set_active(&name)
 }
 
 pub async fn load(name: String) -> Result<(), String> {
@@ -52,7 +53,95 @@ pub async fn load(name: String) -> Result<(), String> {
     // Restore all configs from container
     restore_configs(&container_dir).await?;
     
-    Ok(())
+    set_active(&name)
+}
+
+// Name of the container last created or loaded, if it still exists
+pub fn active() -> Option<String> {
+    let name = fs::read_to_string(get_active_path()).ok()?;
+    let name = name.trim();
+    
+    if name.is_empty() || !get_container_path(name).exists() {
+        return None;
+    }
+    
+    Some(name.to_string())
+}
+
+// Each container's snapshotted config directory, by container name
+pub fn snapshot_dirs() -> Vec<(String, PathBuf)> {
+    let entries = match fs::read_dir(get_containers_dir()) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_str()?.to_string();
+            let config = entry.path().join("config");
+            config.is_dir().then_some((name, config))
+        })
+        .collect()
+}
+
+// Diff a container's snapshot against another container, or against the
+// live $XDG_CONFIG_HOME when `other` is None. Only files that differ are returned.
+pub async fn compare(name: String, other: Option<String>) -> Result<Vec<crate::diff::FileDiff>, String> {
+    let snapshot = get_container_path(&name).join("config");
+    if !snapshot.exists() {
+        return Err(format!("Container '{}' not found", name));
+    }
+
+    let (other_root, other_label) = match &other {
+        Some(other) => {
+            let other_snapshot = get_container_path(other).join("config");
+            if !other_snapshot.exists() {
+                return Err(format!("Container '{}' not found", other));
+            }
+            (other_snapshot, other.clone())
+        }
+        None => (crate::paths::config_home()?, "current".to_string()),
+    };
+
+    // Against the live config only the snapshotted components are compared
+    let mut files = Vec::new();
+    collect_relative(&snapshot, &snapshot, &mut files);
+    if other.is_some() {
+        collect_relative(&other_root, &other_root, &mut files);
+    } else {
+        for component in snapshot_entries(&snapshot) {
+            collect_relative(&other_root, &other_root.join(component), &mut files);
+        }
+    }
+    files.sort();
+    files.dedup();
+
+    let options = crate::diff::DiffOptions::default();
+    let mut diffs = Vec::new();
+    for relative in files {
+        // Binary files can't be diffed line by line; they are skipped
+        let (Some(old), Some(new)) = (
+            read_text_or_empty(&snapshot.join(&relative)),
+            read_text_or_empty(&other_root.join(&relative)),
+        ) else {
+            continue;
+        };
+
+        let path = relative.to_string_lossy();
+        let diff = crate::diff::compare(
+            &old,
+            &new,
+            &format!("{}/{}", name, path),
+            &format!("{}/{}", other_label, path),
+            &options,
+        );
+        if !diff.is_empty() {
+            diffs.push(diff);
+        }
+    }
+
+    Ok(diffs)
 }
 
 pub async fn list() -> Result<Vec<String>, String> {
@@ -112,42 +201,27 @@ pub async fn export(name: String, path: String) -> Result<(), String> {
 
 // Helper functions
 
-async fn snapshot_configs(container_dir: &PathBuf) -> Result<(), String> {
-    let home = dirs::home_dir().ok_or("Could not find home directory")?;
-    let config_dir = home.join(".config");
-    
-    let components = vec![
-        "waybar",
-        "polybar",
-        "eww",
-        "conky",
-        "kitty",
-        "alacritty",
-        "picom",
-        "dunst",
-        "rofi",
-        "gtk-3.0",
-        "gtk-4.0",
-    ];
-    
-    for component in components {
-        let src = config_dir.join(component);
+// Copy each registered component's config directory; returns the ones found
+async fn snapshot_configs(container_dir: &PathBuf) -> Result<Vec<String>, String> {
+    let mut snapshotted = Vec::new();
+    
+    for (component, src) in crate::rice::snapshot_dirs() {
         if src.exists() {
-            let dst = container_dir.join("config").join(component);
+            let dst = container_dir.join("config").join(&component);
             if let Some(parent) = dst.parent() {
                 fs::create_dir_all(parent)
                     .map_err(|e| format!("Failed to create directory: {}", e))?;
             }
             copy_recursive(&src, &dst)?;
+            snapshotted.push(component);
         }
     }
     
-    Ok(())
+    Ok(snapshotted)
 }
 
 async fn restore_configs(container_dir: &PathBuf) -> Result<(), String> {
-    let home = dirs::home_dir().ok_or("Could not find home directory")?;
-    let config_dir = home.join(".config");
+    let config_dir = crate::paths::config_home()?;
     let source_config = container_dir.join("config");
     
     if !source_config.exists() {
@@ -200,6 +274,47 @@ fn copy_recursive(src: &PathBuf, dst: &PathBuf) -> Result<(), String> {
     Ok(())
 }
 
+// Paths of all files under `dir`, relative to `root`
+fn collect_relative(root: &PathBuf, dir: &PathBuf, files: &mut Vec<PathBuf>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_relative(root, &path, files);
+        } else if let Ok(relative) = path.strip_prefix(root) {
+            files.push(relative.to_path_buf());
+        }
+    }
+}
+
+fn snapshot_entries(snapshot: &PathBuf) -> Vec<std::ffi::OsString> {
+    fs::read_dir(snapshot)
+        .map(|entries| entries.flatten().map(|entry| entry.file_name()).collect())
+        .unwrap_or_default()
+}
+
+// A missing file reads as empty; None means the file isn't text
+fn read_text_or_empty(path: &PathBuf) -> Option<String> {
+    if !path.exists() {
+        return Some(String::new());
+    }
+    fs::read(path).ok().and_then(|bytes| String::from_utf8(bytes).ok())
+}
+
+fn set_active(name: &str) -> Result<(), String> {
+    fs::write(get_active_path(), name)
+        .map_err(|e| format!("Failed to record active container: {}", e))
+}
+
+fn get_active_path() -> PathBuf {
+    let home = dirs::home_dir().expect("Could not find home directory");
+    home.join(".nixdeck").join("active-container")
+}
+
 fn get_containers_dir() -> PathBuf {
     let home = dirs::home_dir().expect("Could not find home directory");
     home.join(".nixdeck").join("containers")
@@ -208,19 +323,3 @@ fn get_containers_dir() -> PathBuf {
 fn get_container_path(name: &str) -> PathBuf {
     get_containers_dir().join(name)
 }
-
-fn get_snapshot_components() -> Vec<String> {
-    vec![
-        "waybar".to_string(),
-        "polybar".to_string(),
-        "eww".to_string(),
-        "conky".to_string(),
-        "kitty".to_string(),
-        "alacritty".to_string(),
-        "picom".to_string(),
-        "dunst".to_string(),
-        "rofi".to_string(),
-        "gtk-3.0".to_string(),
-        "gtk-4.0".to_string(),
-    ]
-}
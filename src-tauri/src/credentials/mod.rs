@@ -0,0 +1,466 @@
+// NixDeck 2133 - Credential Storage Module
+//
+// API keys live in the Secret Service keyring when one is running. Without
+// it they go to ~/.nixdeck/credentials.enc, encrypted with a random key kept
+// beside it in credentials.key. Anyone who can read both files can decrypt
+// the store, so the file fallback is obfuscation: it keeps keys out of plain
+// sight and out of backups of the store alone, and the 0600 permissions on
+// both files are the real protection.
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::{Mutex, MutexGuard};
+use tokio::io::AsyncWriteExt;
+
+// Attribute used to find NixDeck entries in the Secret Service keyring
+const KEYRING_SERVICE: &str = "nixdeck-2133";
+
+// Forces a backend ("keyring" or "file"); CI sets "file" to run without a keyring daemon
+const BACKEND_ENV: &str = "NIXDECK_CREDENTIAL_BACKEND";
+
+const KNOWN_PROVIDERS: &[&str] = &["openrouter", "openai", "ollama"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    Keyring,
+    File,
+    Env,
+}
+
+// What the webview is allowed to see: never the key itself
+#[derive(Debug, Serialize)]
+pub struct CredentialStatus {
+    pub provider: String,
+    pub is_set: bool,
+    pub backend: Option<Backend>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct EncryptedStore {
+    version: u32,
+    nonce: String,
+    data: String,
+}
+
+pub async fn set_key(provider: String, key: String) -> Result<(), String> {
+    validate_provider(&provider)?;
+
+    let key = key.trim();
+    if key.is_empty() {
+        return Err("API key cannot be empty".to_string());
+    }
+
+    if keyring_enabled().await {
+        keyring_store(&provider, key).await?;
+        // Don't leave a stale copy behind in the fallback file
+        return file_remove(&provider);
+    }
+
+    let _guard = store_lock();
+    let mut keys = file_read_all()?;
+    keys.insert(provider, key.to_string());
+    file_write_all(&keys)
+}
+
+pub async fn get_status(provider: String) -> Result<CredentialStatus, String> {
+    validate_provider(&provider)?;
+
+    let backend = lookup(&provider).await.map(|(_, backend)| backend);
+
+    Ok(CredentialStatus {
+        provider,
+        is_set: backend.is_some(),
+        backend,
+    })
+}
+
+pub async fn clear_key(provider: String) -> Result<(), String> {
+    validate_provider(&provider)?;
+
+    if keyring_enabled().await {
+        keyring_clear(&provider).await?;
+    }
+    file_remove(&provider)
+}
+
+// Backend-only accessor used when building provider requests
+pub async fn get_key(provider: &str) -> Option<String> {
+    lookup(provider).await.map(|(key, _)| key)
+}
+
+async fn lookup(provider: &str) -> Option<(String, Backend)> {
+    if keyring_enabled().await {
+        if let Some(key) = keyring_lookup(provider).await {
+            return Some((key, Backend::Keyring));
+        }
+    }
+
+    if let Some(key) = file_read_all().ok().and_then(|mut keys| keys.remove(provider)) {
+        return Some((key, Backend::File));
+    }
+
+    // Environment variables, e.g. OPENROUTER_API_KEY, as a last resort; they
+    // name a provider, so never stand in for a per-host key
+    if !KNOWN_PROVIDERS.contains(&provider) {
+        return None;
+    }
+    std::env::var(format!("{}_API_KEY", provider.to_uppercase()))
+        .ok()
+        .filter(|key| !key.trim().is_empty())
+        .map(|key| (key, Backend::Env))
+}
+
+// A provider, or `provider@host[:port]` for a key that only goes to that host
+fn validate_provider(provider: &str) -> Result<(), String> {
+    let (name, host) = match provider.split_once('@') {
+        Some((name, host)) => (name, Some(host)),
+        None => (provider, None),
+    };
+    if !KNOWN_PROVIDERS.contains(&name) {
+        return Err(format!("Unknown provider: {}", provider));
+    }
+    if let Some(host) = host {
+        if host.is_empty() || !host.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | ':' | '[' | ']')) {
+            return Err(format!("Invalid host in credential name: {}", provider));
+        }
+    }
+    Ok(())
+}
+
+// ============================================================================
+// SECRET SERVICE KEYRING (via libsecret's secret-tool)
+// ============================================================================
+//
+// secret-tool runs through tokio::process, since keys are looked up on the
+// async runtime for every exchange.
+
+async fn keyring_enabled() -> bool {
+    // Probed once per run; a keyring daemon doesn't come and go mid-session
+    static AVAILABLE: tokio::sync::OnceCell<bool> = tokio::sync::OnceCell::const_new();
+
+    match std::env::var(BACKEND_ENV).as_deref() {
+        Ok("file") => false,
+        Ok("keyring") => true,
+        _ => *AVAILABLE.get_or_init(keyring_available).await,
+    }
+}
+
+async fn keyring_available() -> bool {
+    // A lookup for a missing item exits 1 with no output; a missing daemon or
+    // D-Bus session fails with an error on stderr
+    match secret_tool(&["lookup", "service", KEYRING_SERVICE, "probe", "availability"])
+        .output()
+        .await
+    {
+        Ok(output) => output.status.success() || output.stderr.is_empty(),
+        Err(_) => false,
+    }
+}
+
+fn secret_tool(args: &[&str]) -> tokio::process::Command {
+    let mut command = tokio::process::Command::new("secret-tool");
+    command.args(args).stdin(Stdio::null()).kill_on_drop(true);
+    command
+}
+
+async fn keyring_store(provider: &str, key: &str) -> Result<(), String> {
+    let label = format!("--label=NixDeck 2133 ({})", provider);
+    let mut child = secret_tool(&["store", &label, "service", KEYRING_SERVICE, "provider", provider])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to spawn secret-tool: {}", e))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(key.as_bytes())
+            .await
+            .map_err(|e| format!("Failed to write to secret-tool: {}", e))?;
+    }
+
+    let output = child.wait_with_output()
+        .await
+        .map_err(|e| format!("Failed to wait for secret-tool: {}", e))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "Failed to store key in keyring: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(())
+}
+
+async fn keyring_lookup(provider: &str) -> Option<String> {
+    let output = secret_tool(&["lookup", "service", KEYRING_SERVICE, "provider", provider])
+        .output()
+        .await
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    let key = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if key.is_empty() { None } else { Some(key) }
+}
+
+async fn keyring_clear(provider: &str) -> Result<(), String> {
+    let output = secret_tool(&["clear", "service", KEYRING_SERVICE, "provider", provider])
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run secret-tool: {}", e))?;
+
+    if !output.status.success() && !output.stderr.is_empty() {
+        return Err(format!(
+            "Failed to clear key from keyring: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(())
+}
+
+// ============================================================================
+// ENCRYPTED FILE FALLBACK
+// ============================================================================
+
+// Held across every read-modify-write of the store, so two saves at once
+// can't each drop the other's key
+fn store_lock() -> MutexGuard<'static, ()> {
+    static STORE: Mutex<()> = Mutex::new(());
+    STORE.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn file_read_all() -> Result<BTreeMap<String, String>, String> {
+    let store_path = get_store_path();
+
+    if !store_path.exists() {
+        return Ok(BTreeMap::new());
+    }
+
+    let content = fs::read_to_string(&store_path)
+        .map_err(|e| format!("Failed to read credential store: {}", e))?;
+
+    let store: EncryptedStore = serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse credential store: {}", e))?;
+
+    let nonce = hex::decode(&store.nonce)
+        .map_err(|e| format!("Corrupt credential store nonce: {}", e))?;
+    let data = hex::decode(&store.data)
+        .map_err(|e| format!("Corrupt credential store data: {}", e))?;
+    if nonce.len() != 12 {
+        return Err("Corrupt credential store nonce".to_string());
+    }
+
+    let plaintext = cipher()?
+        .decrypt(Nonce::from_slice(&nonce), data.as_ref())
+        .map_err(|_| "Failed to decrypt credential store (wrong key or tampered file)".to_string())?;
+
+    serde_json::from_slice(&plaintext)
+        .map_err(|e| format!("Failed to parse decrypted credentials: {}", e))
+}
+
+fn file_write_all(keys: &BTreeMap<String, String>) -> Result<(), String> {
+    let store_path = get_store_path();
+
+    if keys.is_empty() {
+        if store_path.exists() {
+            fs::remove_file(&store_path)
+                .map_err(|e| format!("Failed to remove credential store: {}", e))?;
+        }
+        return Ok(());
+    }
+
+    let plaintext = serde_json::to_vec(keys)
+        .map_err(|e| format!("Failed to serialize credentials: {}", e))?;
+
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let data = cipher()?
+        .encrypt(&nonce, plaintext.as_ref())
+        .map_err(|_| "Failed to encrypt credentials".to_string())?;
+
+    let store = EncryptedStore {
+        version: 1,
+        nonce: hex::encode(nonce),
+        data: hex::encode(data),
+    };
+    let json = serde_json::to_string_pretty(&store)
+        .map_err(|e| format!("Failed to serialize credential store: {}", e))?;
+
+    write_private(&store_path, json.as_bytes())
+}
+
+fn file_remove(provider: &str) -> Result<(), String> {
+    let _guard = store_lock();
+    let mut keys = file_read_all()?;
+    if keys.remove(provider).is_some() {
+        file_write_all(&keys)?;
+    }
+    Ok(())
+}
+
+// Per-install random key kept next to the store with owner-only permissions
+fn cipher() -> Result<ChaCha20Poly1305, String> {
+    let key_path = get_key_path();
+
+    let key_bytes = if key_path.exists() {
+        // Tighten a key file something else loosened before using it again
+        let metadata = fs::metadata(&key_path)
+            .map_err(|e| format!("Failed to read credential key: {}", e))?;
+        if metadata.permissions().mode() & 0o777 != 0o600 {
+            fs::set_permissions(&key_path, fs::Permissions::from_mode(0o600))
+                .map_err(|e| format!("Failed to restrict credential key permissions: {}", e))?;
+        }
+
+        let bytes = fs::read(&key_path)
+            .map_err(|e| format!("Failed to read credential key: {}", e))?;
+        if bytes.len() != 32 {
+            return Err("Credential key file is corrupt".to_string());
+        }
+        bytes
+    } else {
+        let key = ChaCha20Poly1305::generate_key(&mut OsRng);
+        write_private(&key_path, &key)?;
+        key.to_vec()
+    };
+
+    Ok(ChaCha20Poly1305::new(Key::from_slice(&key_bytes)))
+}
+
+// Both files are owner-only from the moment they exist
+fn write_private(path: &Path, content: &[u8]) -> Result<(), String> {
+    crate::atomic::write(path, content, Some(0o600))
+}
+
+fn get_store_path() -> PathBuf {
+    let home = dirs::home_dir().expect("Could not find home directory");
+    home.join(".nixdeck").join("credentials.enc")
+}
+
+fn get_key_path() -> PathBuf {
+    let home = dirs::home_dir().expect("Could not find home directory");
+    home.join(".nixdeck").join("credentials.key")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // HOME and the backend are process-wide, so tests take turns
+    static ENV: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+    struct TempHome {
+        dir: PathBuf,
+        _turn: tokio::sync::MutexGuard<'static, ()>,
+    }
+
+    impl Drop for TempHome {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    async fn temp_home(name: &str) -> TempHome {
+        let turn = ENV.lock().await;
+        let dir = std::env::temp_dir().join(format!("nixdeck-credentials-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        std::env::set_var("HOME", &dir);
+        std::env::set_var(BACKEND_ENV, "file");
+        for provider in KNOWN_PROVIDERS {
+            std::env::remove_var(format!("{}_API_KEY", provider.to_uppercase()));
+        }
+        TempHome { dir, _turn: turn }
+    }
+
+    fn mode(path: &Path) -> u32 {
+        fs::metadata(path).unwrap().permissions().mode() & 0o777
+    }
+
+    #[tokio::test]
+    async fn set_get_and_delete() {
+        let _home = temp_home("roundtrip").await;
+
+        set_key("openrouter".into(), " sk-or-1 ".into()).await.unwrap();
+        set_key("ollama@gpu.lan:11434".into(), "ol-2".into()).await.unwrap();
+        assert_eq!(get_key("openrouter").await.as_deref(), Some("sk-or-1"));
+        assert_eq!(get_key("ollama@gpu.lan:11434").await.as_deref(), Some("ol-2"));
+
+        let status = get_status("openrouter".into()).await.unwrap();
+        assert!(status.is_set);
+        assert_eq!(status.backend, Some(Backend::File));
+
+        // The key is never written in the clear
+        let store = fs::read_to_string(get_store_path()).unwrap();
+        assert!(!store.contains("sk-or-1"));
+        assert_eq!(mode(&get_store_path()), 0o600);
+        assert_eq!(mode(&get_key_path()), 0o600);
+
+        clear_key("openrouter".into()).await.unwrap();
+        assert_eq!(get_key("openrouter").await, None);
+        assert_eq!(get_key("ollama@gpu.lan:11434").await.as_deref(), Some("ol-2"));
+
+        // Clearing the last key removes the store
+        clear_key("ollama@gpu.lan:11434".into()).await.unwrap();
+        assert!(!get_store_path().exists());
+    }
+
+    #[tokio::test]
+    async fn rejects_unknown_providers_and_empty_keys() {
+        let _home = temp_home("invalid").await;
+
+        assert!(set_key("nope".into(), "k".into()).await.is_err());
+        assert!(set_key("openai@bad/host".into(), "k".into()).await.is_err());
+        assert!(set_key("openai".into(), "  ".into()).await.is_err());
+        assert!(!get_store_path().exists());
+    }
+
+    #[tokio::test]
+    async fn corrupted_store_is_reported_not_overwritten() {
+        let _home = temp_home("corrupt").await;
+
+        set_key("openai".into(), "sk-1".into()).await.unwrap();
+        fs::write(get_store_path(), "not json").unwrap();
+
+        assert_eq!(get_key("openai").await, None);
+        assert!(!get_status("openai".into()).await.unwrap().is_set);
+        let error = set_key("openai".into(), "sk-2".into()).await.unwrap_err();
+        assert!(error.contains("parse credential store"), "{}", error);
+        assert_eq!(fs::read_to_string(get_store_path()).unwrap(), "not json");
+    }
+
+    #[tokio::test]
+    async fn tampered_store_fails_to_decrypt() {
+        let _home = temp_home("tampered").await;
+
+        set_key("openai".into(), "sk-1".into()).await.unwrap();
+        let mut store: EncryptedStore = serde_json::from_str(&fs::read_to_string(get_store_path()).unwrap()).unwrap();
+        let flipped = if store.data.starts_with('0') { "1" } else { "0" };
+        store.data.replace_range(..1, flipped);
+        fs::write(get_store_path(), serde_json::to_string(&store).unwrap()).unwrap();
+
+        assert_eq!(get_key("openai").await, None);
+        let error = clear_key("openai".into()).await.unwrap_err();
+        assert!(error.contains("decrypt"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn loosened_key_file_is_tightened_before_use() {
+        let _home = temp_home("permissions").await;
+
+        set_key("openai".into(), "sk-1".into()).await.unwrap();
+        fs::set_permissions(get_key_path(), fs::Permissions::from_mode(0o644)).unwrap();
+
+        assert_eq!(get_key("openai").await.as_deref(), Some("sk-1"));
+        assert_eq!(mode(&get_key_path()), 0o600);
+    }
+}
@@ -18,8 +18,7 @@ pub async fn list_services() -> Result<Vec<String>, String> {
 }
 
 pub async fn create_service(name: String, content: String) -> Result<(), String> {
-    let home = dirs::home_dir().ok_or("Could not find home directory")?;
-    let service_path = home.join(format!(".config/systemd/user/{}.service", name));
+    let service_path = get_user_units_dir()?.join(format!("{}.service", name));
     
     // Ensure directory exists
     if let Some(parent) = service_path.parent() {
@@ -39,6 +38,20 @@ pub async fn create_service(name: String, content: String) -> Result<(), String>
     Ok(())
 }
 
+// Where NixDeck writes user units
+pub fn get_user_units_dir() -> Result<std::path::PathBuf, String> {
+    Ok(crate::paths::config_home()?.join("systemd/user"))
+}
+
+// Every directory systemd loads user units from that the user owns, in its
+// precedence order; also indexed for AI retrieval
+pub fn get_user_unit_dirs() -> Result<Vec<std::path::PathBuf>, String> {
+    Ok(vec![
+        get_user_units_dir()?,
+        crate::paths::data_home()?.join("systemd/user"),
+    ])
+}
+
 pub async fn enable_service(name: String) -> Result<(), String> {
     let output = Command::new("systemctl")
         .args(&["--user", "enable", &name])
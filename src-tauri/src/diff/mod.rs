@@ -0,0 +1,496 @@
+// NixDeck 2133 - Text Diff Module
+use serde::Serialize;
+
+// Lines of unchanged context around each change
+pub const DEFAULT_CONTEXT: usize = 3;
+
+#[derive(Debug, Clone, Copy)]
+pub struct DiffOptions {
+    pub context: usize,
+    // Mark the changed words inside each replaced line
+    pub word_highlight: bool,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        DiffOptions {
+            context: DEFAULT_CONTEXT,
+            word_highlight: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileDiff {
+    pub old_name: String,
+    pub new_name: String,
+    pub hunks: Vec<Hunk>,
+    pub added: usize,
+    pub removed: usize,
+    // Every change only moves whitespace around
+    pub whitespace_only: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Hunk {
+    // 1-based; an empty range is numbered from the line before it, as in diff(1)
+    pub old_start: usize,
+    pub old_count: usize,
+    pub new_start: usize,
+    pub new_count: usize,
+    pub lines: Vec<DiffLine>,
+    pub whitespace_only: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineKind {
+    Context,
+    Added,
+    Removed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiffLine {
+    pub kind: LineKind,
+    pub text: String,
+    pub old_line: Option<usize>,
+    pub new_line: Option<usize>,
+    // For a line replaced by another: the line split into changed and
+    // unchanged runs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub segments: Option<Vec<Segment>>,
+    // The last line of a file that doesn't end in a newline
+    pub no_newline: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Segment {
+    pub text: String,
+    pub changed: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Equal,
+    Delete,
+    Insert,
+}
+
+impl FileDiff {
+    pub fn is_empty(&self) -> bool {
+        self.hunks.is_empty()
+    }
+}
+
+// Line-level Myers diff of `old` against `new`, grouped into hunks
+pub fn compare(old: &str, new: &str, old_name: &str, new_name: &str, options: &DiffOptions) -> FileDiff {
+    // Lines keep their endings, so adding or dropping the final newline is a
+    // change to the last line
+    let old_lines: Vec<&str> = old.split_inclusive('\n').collect();
+    let new_lines: Vec<&str> = new.split_inclusive('\n').collect();
+    let script = edit_script(&old_lines, &new_lines);
+
+    let mut hunks = Vec::new();
+
+    // Group changes whose context windows overlap or touch into one hunk;
+    // split, they would print as two hunks with nothing between them
+    let changes: Vec<usize> = (0..script.len()).filter(|&i| script[i].0 != Op::Equal).collect();
+    let mut start = 0;
+    while start < changes.len() {
+        let mut end = start;
+        while end + 1 < changes.len() && changes[end + 1] - changes[end] <= 2 * options.context + 1 {
+            end += 1;
+        }
+
+        let from = changes[start].saturating_sub(options.context);
+        let to = (changes[end] + options.context + 1).min(script.len());
+        hunks.push(build_hunk(&script[from..to], &old_lines, &new_lines, options));
+
+        start = end + 1;
+    }
+
+    let count = |kind| {
+        hunks
+            .iter()
+            .flat_map(|hunk: &Hunk| &hunk.lines)
+            .filter(|line| line.kind == kind)
+            .count()
+    };
+
+    FileDiff {
+        old_name: old_name.to_string(),
+        new_name: new_name.to_string(),
+        added: count(LineKind::Added),
+        removed: count(LineKind::Removed),
+        whitespace_only: !hunks.is_empty() && hunks.iter().all(|hunk| hunk.whitespace_only),
+        hunks,
+    }
+}
+
+fn build_hunk(ops: &[(Op, usize, usize)], old_lines: &[&str], new_lines: &[&str], options: &DiffOptions) -> Hunk {
+    let (_, old_start, new_start) = ops[0];
+    let old_count = ops.iter().filter(|(op, _, _)| *op != Op::Insert).count();
+    let new_count = ops.iter().filter(|(op, _, _)| *op != Op::Delete).count();
+
+    let mut lines: Vec<DiffLine> = ops
+        .iter()
+        .map(|&(op, old_index, new_index)| match op {
+            Op::Equal => DiffLine {
+                kind: LineKind::Context,
+                text: line_text(old_lines[old_index]).to_string(),
+                old_line: Some(old_index + 1),
+                new_line: Some(new_index + 1),
+                segments: None,
+                no_newline: !old_lines[old_index].ends_with('\n'),
+            },
+            Op::Delete => DiffLine {
+                kind: LineKind::Removed,
+                text: line_text(old_lines[old_index]).to_string(),
+                old_line: Some(old_index + 1),
+                new_line: None,
+                segments: None,
+                no_newline: !old_lines[old_index].ends_with('\n'),
+            },
+            Op::Insert => DiffLine {
+                kind: LineKind::Added,
+                text: line_text(new_lines[new_index]).to_string(),
+                old_line: None,
+                new_line: Some(new_index + 1),
+                segments: None,
+                no_newline: !new_lines[new_index].ends_with('\n'),
+            },
+        })
+        .collect();
+
+    let removed: Vec<&str> = lines.iter().filter(|l| l.kind == LineKind::Removed).map(|l| l.text.as_str()).collect();
+    let added: Vec<&str> = lines.iter().filter(|l| l.kind == LineKind::Added).map(|l| l.text.as_str()).collect();
+    let whitespace_only = strip_whitespace(&removed.concat()) == strip_whitespace(&added.concat());
+
+    if options.word_highlight {
+        highlight_words(&mut lines);
+    }
+
+    Hunk {
+        old_start: if old_count == 0 { old_start } else { old_start + 1 },
+        old_count,
+        new_start: if new_count == 0 { new_start } else { new_start + 1 },
+        new_count,
+        lines,
+        whitespace_only,
+    }
+}
+
+// Pair each run of removed lines with the added lines that follow it, line by
+// line, and mark which words differ within each pair
+fn highlight_words(lines: &mut [DiffLine]) {
+    let mut i = 0;
+    while i < lines.len() {
+        if lines[i].kind != LineKind::Removed {
+            i += 1;
+            continue;
+        }
+
+        let removed_start = i;
+        while i < lines.len() && lines[i].kind == LineKind::Removed {
+            i += 1;
+        }
+        let added_start = i;
+        while i < lines.len() && lines[i].kind == LineKind::Added {
+            i += 1;
+        }
+
+        let pairs = (added_start - removed_start).min(i - added_start);
+        for n in 0..pairs {
+            let (old_segments, new_segments) =
+                word_segments(&lines[removed_start + n].text, &lines[added_start + n].text);
+            lines[removed_start + n].segments = Some(old_segments);
+            lines[added_start + n].segments = Some(new_segments);
+        }
+    }
+}
+
+fn word_segments(old: &str, new: &str) -> (Vec<Segment>, Vec<Segment>) {
+    let old_words = words(old);
+    let new_words = words(new);
+    let script = edit_script(&old_words, &new_words);
+
+    let mut old_segments = Vec::new();
+    let mut new_segments = Vec::new();
+    for (op, old_index, new_index) in script {
+        match op {
+            Op::Equal => {
+                push_segment(&mut old_segments, old_words[old_index], false);
+                push_segment(&mut new_segments, new_words[new_index], false);
+            }
+            Op::Delete => push_segment(&mut old_segments, old_words[old_index], true),
+            Op::Insert => push_segment(&mut new_segments, new_words[new_index], true),
+        }
+    }
+    (old_segments, new_segments)
+}
+
+// Merge into the previous segment when it has the same state
+fn push_segment(segments: &mut Vec<Segment>, text: &str, changed: bool) {
+    match segments.last_mut() {
+        Some(last) if last.changed == changed => last.text.push_str(text),
+        _ => segments.push(Segment { text: text.to_string(), changed }),
+    }
+}
+
+// Runs of word characters, runs of whitespace, and single punctuation marks
+fn words(line: &str) -> Vec<&str> {
+    let class = |c: char| {
+        if c.is_alphanumeric() || c == '_' {
+            0
+        } else if c.is_whitespace() {
+            1
+        } else {
+            2
+        }
+    };
+
+    let mut words = Vec::new();
+    let mut start = 0;
+    let mut previous = None;
+    for (index, c) in line.char_indices() {
+        let current = class(c);
+        if index > start && (previous != Some(current) || current == 2) {
+            words.push(&line[start..index]);
+            start = index;
+        }
+        previous = Some(current);
+    }
+    if start < line.len() {
+        words.push(&line[start..]);
+    }
+    words
+}
+
+// A line without its ending
+fn line_text(line: &str) -> &str {
+    let line = line.strip_suffix('\n').unwrap_or(line);
+    line.strip_suffix('\r').unwrap_or(line)
+}
+
+fn strip_whitespace(text: &str) -> String {
+    text.chars().filter(|c| !c.is_whitespace()).collect()
+}
+
+// Myers' O(ND) shortest edit script. Each entry carries the position in both
+// inputs at which it applies; removals come before insertions, as in diff(1).
+fn edit_script<T: PartialEq>(old: &[T], new: &[T]) -> Vec<(Op, usize, usize)> {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let max = n + m;
+    let offset = max as usize;
+
+    // v[k] = furthest x reached on diagonal k. After each edit distance d only
+    // the 2d+1 diagonals it reached are kept, so the trace is O(D²), not O((N+M)·D).
+    let mut v = vec![0isize; 2 * offset + 2];
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+
+    'search: for d in 0..=max {
+        for k in (-d..=d).step_by(2) {
+            let index = (k + max) as usize;
+            let mut x = if k == -d || (k != d && v[index - 1] < v[index + 1]) {
+                v[index + 1]
+            } else {
+                v[index - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[index] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+        }
+        trace.push(v[(max - d) as usize..=(max + d) as usize].to_vec());
+    }
+
+    // Walk back from the end: the move that reached diagonal k at distance d
+    // came from k±1 at distance d - 1
+    let mut script = Vec::with_capacity((n + m) as usize);
+    let (mut x, mut y) = (n, m);
+    for d in (1..=trace.len() as isize).rev() {
+        let v = &trace[d as usize - 1];
+        // Diagonal k of distance d - 1, stored from -(d - 1)
+        let furthest = |k: isize| v[(k + d - 1) as usize];
+        let k = x - y;
+
+        let previous_k = if k == -d || (k != d && furthest(k - 1) < furthest(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let previous_x = furthest(previous_k);
+        let previous_y = previous_x - previous_k;
+
+        while x > previous_x && y > previous_y {
+            x -= 1;
+            y -= 1;
+            script.push((Op::Equal, x as usize, y as usize));
+        }
+        if x == previous_x {
+            script.push((Op::Insert, x as usize, previous_y as usize));
+        } else {
+            script.push((Op::Delete, previous_x as usize, y as usize));
+        }
+        x = previous_x;
+        y = previous_y;
+    }
+    // Whatever is left is the run of equal items both inputs start with
+    while x > 0 && y > 0 {
+        x -= 1;
+        y -= 1;
+        script.push((Op::Equal, x as usize, y as usize));
+    }
+
+    script.reverse();
+    script
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diff(old: &str, new: &str) -> FileDiff {
+        compare(old, new, "old", "new", &DiffOptions::default())
+    }
+
+    fn lines(hunk: &Hunk) -> Vec<(LineKind, &str)> {
+        hunk.lines.iter().map(|line| (line.kind, line.text.as_str())).collect()
+    }
+
+    fn ranges(diff: &FileDiff) -> Vec<(usize, usize, usize, usize)> {
+        diff.hunks
+            .iter()
+            .map(|hunk| (hunk.old_start, hunk.old_count, hunk.new_start, hunk.new_count))
+            .collect()
+    }
+
+    fn segments(line: &DiffLine) -> Vec<(&str, bool)> {
+        line.segments
+            .iter()
+            .flatten()
+            .map(|segment| (segment.text.as_str(), segment.changed))
+            .collect()
+    }
+
+    // `count` numbered lines, with the ones at `replaced` (0-based) changed
+    fn numbered(count: usize, replaced: &[usize]) -> String {
+        (0..count)
+            .map(|i| if replaced.contains(&i) { format!("changed {}\n", i) } else { format!("line {}\n", i) })
+            .collect()
+    }
+
+    #[test]
+    fn identical_texts_have_no_hunks() {
+        assert!(diff("", "").is_empty());
+        assert!(diff("a\nb\n", "a\nb\n").is_empty());
+    }
+
+    #[test]
+    fn empty_against_non_empty() {
+        let added = diff("", "a\nb\n");
+        assert_eq!(ranges(&added), [(0, 0, 1, 2)]);
+        assert_eq!(lines(&added.hunks[0]), [(LineKind::Added, "a"), (LineKind::Added, "b")]);
+        assert_eq!((added.added, added.removed), (2, 0));
+
+        let removed = diff("a\nb\n", "");
+        assert_eq!(ranges(&removed), [(1, 2, 0, 0)]);
+        assert_eq!((removed.added, removed.removed), (0, 2));
+    }
+
+    #[test]
+    fn insert_only() {
+        let result = diff("a\nb\nc\n", "a\nb\nx\nc\n");
+        assert_eq!(ranges(&result), [(1, 3, 1, 4)]);
+        assert_eq!(
+            lines(&result.hunks[0]),
+            [(LineKind::Context, "a"), (LineKind::Context, "b"), (LineKind::Added, "x"), (LineKind::Context, "c")]
+        );
+
+        let inserted = &result.hunks[0].lines[2];
+        assert_eq!((inserted.old_line, inserted.new_line), (None, Some(3)));
+        assert!(inserted.segments.is_none());
+    }
+
+    #[test]
+    fn delete_only() {
+        let result = diff("a\nb\nc\n", "a\nc\n");
+        assert_eq!(ranges(&result), [(1, 3, 1, 2)]);
+        assert_eq!(lines(&result.hunks[0]), [(LineKind::Context, "a"), (LineKind::Removed, "b"), (LineKind::Context, "c")]);
+        assert_eq!(result.hunks[0].lines[1].old_line, Some(2));
+    }
+
+    #[test]
+    fn missing_final_newline_on_one_side() {
+        let result = diff("a\nb\n", "a\nb");
+        assert_eq!(lines(&result.hunks[0]), [(LineKind::Context, "a"), (LineKind::Removed, "b"), (LineKind::Added, "b")]);
+
+        let flags: Vec<bool> = result.hunks[0].lines.iter().map(|line| line.no_newline).collect();
+        assert_eq!(flags, [false, false, true]);
+        assert!(result.whitespace_only);
+
+        // Both sides without one is no change at all
+        assert!(diff("a\nb", "a\nb").is_empty());
+        let unchanged = diff("a\nb", "x\nb");
+        assert!(unchanged.hunks[0].lines.last().unwrap().no_newline);
+    }
+
+    #[test]
+    fn whitespace_only_changes_are_flagged() {
+        let spaced = diff("a = 1\n", "a  =  1\n");
+        assert!(spaced.whitespace_only);
+        assert!(spaced.hunks[0].whitespace_only);
+        assert_eq!(
+            segments(&spaced.hunks[0].lines[0]),
+            [("a", false), (" ", true), ("=", false), (" ", true), ("1", false)]
+        );
+
+        let changed = diff("a = 1\n", "a = 2\n");
+        assert!(!changed.whitespace_only);
+        assert_eq!(segments(&changed.hunks[0].lines[0]), [("a = ", false), ("1", true)]);
+        assert_eq!(segments(&changed.hunks[0].lines[1]), [("a = ", false), ("2", true)]);
+
+        // One hunk of real change makes the whole diff a real change
+        let old = numbered(20, &[]);
+        let new = old.replacen("line 1\n", "line  1\n", 1).replacen("line 15\n", "line 99\n", 1);
+        let mixed = diff(&old, &new);
+        assert_eq!(mixed.hunks.len(), 2);
+        assert!(mixed.hunks[0].whitespace_only && !mixed.hunks[1].whitespace_only);
+        assert!(!mixed.whitespace_only);
+    }
+
+    #[test]
+    fn hunks_merge_when_their_context_overlaps() {
+        let old = numbered(20, &[]);
+
+        // Six unchanged lines between: the two context windows cover them
+        let merged = diff(&old, &numbered(20, &[1, 8]));
+        assert_eq!(ranges(&merged), [(1, 12, 1, 12)]);
+
+        // Seven: one line belongs to neither, so the hunks stay apart
+        let apart = diff(&old, &numbered(20, &[1, 9]));
+        assert_eq!(ranges(&apart), [(1, 5, 1, 5), (7, 7, 7, 7)]);
+
+        let tail = diff(&old, &numbered(20, &[1, 15]));
+        assert_eq!(ranges(&tail), [(1, 5, 1, 5), (13, 7, 13, 7)]);
+        assert_eq!((tail.added, tail.removed), (2, 2));
+    }
+
+    #[test]
+    fn context_setting_widens_hunks() {
+        let old = numbered(20, &[]);
+        let new = numbered(20, &[10]);
+        let options = DiffOptions { context: 1, word_highlight: false };
+
+        let narrow = compare(&old, &new, "old", "new", &options);
+        assert_eq!(ranges(&narrow), [(10, 3, 10, 3)]);
+        assert!(narrow.hunks[0].lines.iter().all(|line| line.segments.is_none()));
+        assert_eq!(ranges(&diff(&old, &new)), [(8, 7, 8, 7)]);
+    }
+}
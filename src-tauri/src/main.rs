@@ -14,6 +14,11 @@ mod cron_mod;
 mod container;
 mod safety;
 mod theme;
+mod credentials;
+mod diff;
+mod operations;
+mod paths;
+mod atomic;
 mod commands;
 
 use commands::*;
@@ -37,12 +42,27 @@ fn main() {
         fs::create_dir_all(&loadouts_dir).expect("Failed to create loadouts directory");
     }
 
+    // Initialize conversations directory
+    let conversations_dir = config_dir.join("conversations");
+    if !conversations_dir.exists() {
+        fs::create_dir_all(&conversations_dir).expect("Failed to create conversations directory");
+    }
+
+    // Initialize prompt templates directory
+    let prompts_dir = config_dir.join("prompts");
+    if !prompts_dir.exists() {
+        fs::create_dir_all(&prompts_dir).expect("Failed to create prompts directory");
+    }
+
     tauri::Builder::default()
         .setup(|app| {
             // Set window properties
             let window = app.get_window("main").unwrap();
             window.set_title("NixDeck 2133 - BLACKSITE PROTOCOL ACTIVE").ok();
             
+            // Pick up operations that were running when the app last closed
+            tauri::async_runtime::spawn(operations::resume_interrupted(window));
+            
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
@@ -55,14 +75,56 @@ fn main() {
             
             // AI commands
             send_ai_message,
+            cancel_ai_message,
+            respond_ai_tool_call,
             load_ai_loadout,
             save_ai_loadout,
             list_ai_loadouts,
+            delete_ai_loadout,
+            rename_ai_loadout,
+            duplicate_ai_loadout,
+            validate_ai_loadout,
+            export_ai_loadout_bundle,
+            inspect_ai_loadout_bundle,
+            import_ai_loadout_bundle,
+            list_ai_models,
+            create_ai_conversation,
+            list_ai_conversations,
+            load_ai_conversation,
+            delete_ai_conversation,
+            rename_ai_conversation,
+            get_ai_usage_summary,
+            list_prompt_templates,
+            load_prompt_template,
+            save_prompt_template,
+            render_prompt_template,
+            preview_ai_system_prompt,
+            refresh_ai_index,
+            search_ai_index,
+            get_ai_redaction_rules,
+            save_ai_redaction_rules,
+            propose_rice_config,
+            
+            // Credential commands
+            set_api_key,
+            get_api_key_status,
+            clear_api_key,
             
             // Rice commands
+            list_rice_components,
             get_rice_config,
+            get_rice_config_tree,
+            resolve_rice_config,
             apply_rice_config,
+            apply_rice_files,
+            validate_rice_config,
+            list_rice_backups,
+            diff_rice_backup,
+            restore_rice_backup,
+            reload_rice_component,
             preview_rice_config,
+            get_rice_value,
+            set_rice_value,
             
             // Daemon commands
             list_systemd_services,
@@ -84,6 +146,7 @@ fn main() {
             list_containers,
             delete_container,
             export_container,
+            compare_container,
             
             // Safety commands
             create_snapshot,
@@ -95,6 +158,15 @@ fn main() {
             list_themes,
             load_theme,
             save_theme,
+            
+            // Operation commands
+            create_operation,
+            list_operations,
+            get_operation,
+            start_operation,
+            confirm_operation_step,
+            abort_operation,
+            delete_operation,
         ])
         .run(tauri::generate_context!())
         .expect("error while running NixDeck 2133");
@@ -0,0 +1,609 @@
+// NixDeck 2133 - Supervised Operations Module
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use tokio::sync::Notify;
+
+pub const EVENT_UPDATE: &str = "operation://update";
+
+// Output kept per log entry; long command output is cut to this
+const MAX_LOG_BYTES: usize = 8 * 1024;
+
+// Step kind that `risky_steps` uses for every shell step
+pub const SHELL_STEP: &str = "shell";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OperationStatus {
+    Queued,
+    Running,
+    AwaitingConfirmation,
+    Completed,
+    Failed,
+    Aborted,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepStatus {
+    Pending,
+    Running,
+    Succeeded,
+    Failed,
+    Skipped,
+}
+
+// What happens once a step has failed and used up its retries
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailurePolicy {
+    #[default]
+    Abort,
+    Continue,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum StepAction {
+    // A NixDeck command, named as in the AI tool list (e.g. rice_apply_config)
+    Command {
+        name: String,
+        #[serde(default)]
+        args: HashMap<String, String>,
+    },
+    Shell {
+        command: String,
+    },
+}
+
+impl StepAction {
+    fn kind_name(&self) -> &str {
+        match self {
+            StepAction::Command { name, .. } => name,
+            StepAction::Shell { .. } => SHELL_STEP,
+        }
+    }
+}
+
+// A step as submitted by the UI or the model
+#[derive(Debug, Clone, Deserialize)]
+pub struct StepPlan {
+    pub description: String,
+    #[serde(flatten)]
+    pub action: StepAction,
+    #[serde(default)]
+    pub retries: u32,
+    #[serde(default = "default_retry_delay")]
+    pub retry_delay_secs: u64,
+    #[serde(default)]
+    pub on_failure: FailurePolicy,
+}
+
+fn default_retry_delay() -> u64 {
+    5
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogEntry {
+    pub timestamp: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Step {
+    pub description: String,
+    pub action: StepAction,
+    pub status: StepStatus,
+    pub retries: u32,
+    pub retry_delay_secs: u64,
+    pub on_failure: FailurePolicy,
+    pub attempts: u32,
+    // Risky steps wait for the user before they run
+    pub requires_confirmation: bool,
+    pub confirmed: bool,
+    pub logs: Vec<LogEntry>,
+}
+
+impl Step {
+    fn log(&mut self, message: impl Into<String>) {
+        self.logs.push(LogEntry {
+            timestamp: chrono::Local::now().to_rfc3339(),
+            message: message.into(),
+        });
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Operation {
+    pub id: String,
+    pub title: String,
+    pub loadout: Option<String>,
+    pub created: String,
+    pub status: OperationStatus,
+    // Index of the next step to run; everything before it is finished
+    pub current_step: usize,
+    pub steps: Vec<Step>,
+}
+
+impl Operation {
+    fn is_finished(&self) -> bool {
+        matches!(
+            self.status,
+            OperationStatus::Completed | OperationStatus::Aborted
+        )
+    }
+}
+
+// Lets abort_operation reach a runner mid-step
+struct RunHandle {
+    aborted: AtomicBool,
+    wake: Notify,
+}
+
+// Operations with a runner task, keyed by ID
+fn runners() -> &'static Mutex<HashMap<String, Arc<RunHandle>>> {
+    static RUNNERS: OnceLock<Mutex<HashMap<String, Arc<RunHandle>>>> = OnceLock::new();
+    RUNNERS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+// Queue a plan. The loadout's `risky_steps` decides which steps must be
+// confirmed before they run.
+pub async fn create(title: String, plan: Vec<StepPlan>, loadout: Option<String>) -> Result<Operation, String> {
+    let risky = crate::ai::risky_steps(loadout.as_deref())?;
+
+    if title.trim().is_empty() {
+        return Err("Operation title cannot be empty".to_string());
+    }
+    if plan.is_empty() {
+        return Err("Operation has no steps".to_string());
+    }
+
+    let steps = plan
+        .into_iter()
+        .map(|step| {
+            if let StepAction::Command { name, .. } = &step.action {
+                if !crate::ai::is_known_command(name) {
+                    return Err(format!("Unknown NixDeck command '{}'", name));
+                }
+            }
+            Ok(Step {
+                requires_confirmation: risky.iter().any(|kind| kind == step.action.kind_name()),
+                description: step.description,
+                action: step.action,
+                status: StepStatus::Pending,
+                retries: step.retries,
+                retry_delay_secs: step.retry_delay_secs,
+                on_failure: step.on_failure,
+                attempts: 0,
+                confirmed: false,
+                logs: Vec::new(),
+            })
+        })
+        .collect::<Result<Vec<Step>, String>>()?;
+
+    // The counter keeps IDs apart when several plans arrive in one millisecond
+    static SEQUENCE: AtomicU64 = AtomicU64::new(0);
+    let now = chrono::Local::now();
+    let operation = Operation {
+        id: format!("op-{}-{}", now.timestamp_millis(), SEQUENCE.fetch_add(1, Ordering::Relaxed)),
+        title,
+        loadout,
+        created: now.to_rfc3339(),
+        status: OperationStatus::Queued,
+        current_step: 0,
+        steps,
+    };
+
+    write_operation(&operation)?;
+    Ok(operation)
+}
+
+pub async fn list() -> Result<Vec<Operation>, String> {
+    let operations_dir = get_operations_dir();
+
+    if !operations_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let entries = fs::read_dir(operations_dir)
+        .map_err(|e| format!("Failed to read operations directory: {}", e))?;
+
+    let mut operations = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+            continue;
+        }
+        if let Some(id) = path.file_stem().and_then(|stem| stem.to_str()) {
+            if let Ok(operation) = read_operation(id) {
+                operations.push(operation);
+            }
+        }
+    }
+
+    operations.sort_by(|a, b| a.created.cmp(&b.created));
+    Ok(operations)
+}
+
+pub async fn get(id: String) -> Result<Operation, String> {
+    read_operation(&id)
+}
+
+// Start a queued operation, or pick a failed one back up at the failed step
+pub async fn start(window: tauri::Window, id: String) -> Result<(), String> {
+    // Claimed before the record is read, so a second start can't run it twice
+    let handle = claim_runner(&id)?;
+    if let Err(e) = mark_started(&id) {
+        release_runner(&id);
+        return Err(e);
+    }
+    spawn_runner(window, id, handle);
+    Ok(())
+}
+
+fn mark_started(id: &str) -> Result<(), String> {
+    let mut operation = read_operation(id)?;
+
+    match operation.status {
+        OperationStatus::Queued | OperationStatus::Running => {}
+        OperationStatus::Failed => {
+            if let Some(step) = operation.steps.get_mut(operation.current_step) {
+                step.status = StepStatus::Pending;
+                step.attempts = 0;
+                step.log("Retrying after failure");
+            }
+        }
+        OperationStatus::AwaitingConfirmation => {
+            return Err(format!("Operation '{}' is waiting for confirmation", id));
+        }
+        OperationStatus::Completed | OperationStatus::Aborted => {
+            return Err(format!("Operation '{}' has already finished", id));
+        }
+    }
+
+    operation.status = OperationStatus::Running;
+    write_operation(&operation)
+}
+
+pub async fn confirm_step(window: tauri::Window, id: String, approved: bool) -> Result<(), String> {
+    let handle = claim_runner(&id)?;
+    match record_confirmation(&window, &id, approved) {
+        Ok(true) => {
+            spawn_runner(window, id, handle);
+            Ok(())
+        }
+        result => {
+            release_runner(&id);
+            result.map(|_| ())
+        }
+    }
+}
+
+// Whether the step was approved, so the operation runs on
+fn record_confirmation(window: &tauri::Window, id: &str, approved: bool) -> Result<bool, String> {
+    let mut operation = read_operation(id)?;
+
+    if operation.status != OperationStatus::AwaitingConfirmation {
+        return Err(format!("Operation '{}' is not waiting for confirmation", id));
+    }
+
+    let index = operation.current_step;
+    let step = operation
+        .steps
+        .get_mut(index)
+        .ok_or_else(|| format!("Operation '{}' has no step {}", id, index))?;
+
+    if !approved {
+        step.status = StepStatus::Skipped;
+        step.log("Declined by user");
+        operation.status = OperationStatus::Aborted;
+        write_operation(&operation)?;
+        emit(window, &operation);
+        return Ok(false);
+    }
+
+    step.confirmed = true;
+    step.log("Confirmed by user");
+    operation.status = OperationStatus::Running;
+    write_operation(&operation)?;
+    Ok(true)
+}
+
+pub async fn abort(window: tauri::Window, id: String) -> Result<(), String> {
+    let handle = runners()
+        .lock()
+        .map_err(|e| e.to_string())?
+        .get(&id)
+        .cloned();
+
+    // A live runner records the abort itself once the current step stops
+    if let Some(handle) = handle {
+        handle.aborted.store(true, Ordering::SeqCst);
+        handle.wake.notify_one();
+        return Ok(());
+    }
+
+    let mut operation = read_operation(&id)?;
+    if operation.is_finished() {
+        return Err(format!("Operation '{}' has already finished", id));
+    }
+
+    operation.status = OperationStatus::Aborted;
+    if let Some(step) = operation.steps.get_mut(operation.current_step) {
+        step.log("Aborted by user");
+    }
+    write_operation(&operation)?;
+    emit(&window, &operation);
+    Ok(())
+}
+
+pub async fn delete(id: String) -> Result<(), String> {
+    if runners().lock().map_err(|e| e.to_string())?.contains_key(&id) {
+        return Err(format!("Operation '{}' is running; abort it first", id));
+    }
+
+    let path = get_operation_path(&id)?;
+    if !path.exists() {
+        return Err(format!("Operation '{}' not found", id));
+    }
+
+    fs::remove_file(path)
+        .map_err(|e| format!("Failed to delete operation: {}", e))
+}
+
+// Called at startup: anything that was mid-run when the app closed carries on
+// from its last completed step. The interrupted step runs again, after a
+// fresh confirmation if it is risky.
+pub async fn resume_interrupted(window: tauri::Window) {
+    let operations = match list().await {
+        Ok(operations) => operations,
+        Err(_) => return,
+    };
+
+    for mut operation in operations {
+        if operation.status != OperationStatus::Running {
+            continue;
+        }
+        if let Some(step) = operation.steps.get_mut(operation.current_step) {
+            if step.status == StepStatus::Running {
+                step.status = StepStatus::Pending;
+                // The user agreed to one run, not to a repeat after a crash
+                if step.requires_confirmation {
+                    step.confirmed = false;
+                    step.log("Interrupted by restart; confirm to run it again");
+                } else {
+                    step.log("Interrupted by restart; running again");
+                }
+            }
+        }
+        let Ok(handle) = claim_runner(&operation.id) else {
+            continue;
+        };
+        match write_operation(&operation) {
+            Ok(()) => spawn_runner(window.clone(), operation.id, handle),
+            Err(_) => release_runner(&operation.id),
+        }
+    }
+}
+
+// Register a runner for `id`, refusing if one is already live. Done before
+// the record changes so two callers can't both mark it running.
+fn claim_runner(id: &str) -> Result<Arc<RunHandle>, String> {
+    let mut runners = runners().lock().map_err(|e| e.to_string())?;
+    if runners.contains_key(id) {
+        return Err(format!("Operation '{}' is already running", id));
+    }
+
+    let handle = Arc::new(RunHandle {
+        aborted: AtomicBool::new(false),
+        wake: Notify::new(),
+    });
+    runners.insert(id.to_string(), handle.clone());
+    Ok(handle)
+}
+
+fn release_runner(id: &str) {
+    if let Ok(mut runners) = runners().lock() {
+        runners.remove(id);
+    }
+}
+
+fn spawn_runner(window: tauri::Window, id: String, handle: Arc<RunHandle>) {
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = run(&window, &id, &handle).await {
+            record_stopped(&window, &id, &e);
+        }
+        release_runner(&id);
+    });
+}
+
+// Work through the remaining steps, persisting after every state change so a
+// restart never loses progress
+async fn run(window: &tauri::Window, id: &str, handle: &RunHandle) -> Result<(), String> {
+    loop {
+        let mut operation = read_operation(id)?;
+        let index = operation.current_step;
+
+        if handle.aborted.load(Ordering::SeqCst) {
+            return finish_aborted(window, operation);
+        }
+
+        if index >= operation.steps.len() {
+            operation.status = OperationStatus::Completed;
+            write_operation(&operation)?;
+            emit(window, &operation);
+            return Ok(());
+        }
+
+        let step = &mut operation.steps[index];
+        if step.requires_confirmation && !step.confirmed {
+            step.log("Waiting for confirmation");
+            operation.status = OperationStatus::AwaitingConfirmation;
+            write_operation(&operation)?;
+            emit(window, &operation);
+            return Ok(());
+        }
+
+        step.status = StepStatus::Running;
+        step.attempts += 1;
+        step.log(format!("Attempt {} of {}", step.attempts, step.retries + 1));
+        let action = step.action.clone();
+        operation.status = OperationStatus::Running;
+        write_operation(&operation)?;
+        emit(window, &operation);
+
+        let result = tokio::select! {
+            result = run_action(&action, operation.loadout.as_deref()) => result,
+            _ = handle.wake.notified() => {
+                let mut operation = read_operation(id)?;
+                operation.steps[index].status = StepStatus::Failed;
+                operation.steps[index].log("Aborted while running");
+                return finish_aborted(window, operation);
+            }
+        };
+
+        let step = &mut operation.steps[index];
+        match result {
+            Ok(output) => {
+                step.log(truncate(&output));
+                step.status = StepStatus::Succeeded;
+                operation.current_step += 1;
+            }
+            Err(e) => {
+                step.log(format!("Failed: {}", truncate(&e)));
+
+                if step.attempts <= step.retries {
+                    step.status = StepStatus::Pending;
+                    let delay = Duration::from_secs(step.retry_delay_secs);
+                    write_operation(&operation)?;
+                    emit(window, &operation);
+
+                    // An abort cuts the back-off short; the loop then records it
+                    tokio::select! {
+                        _ = tokio::time::sleep(delay) => {}
+                        _ = handle.wake.notified() => {}
+                    }
+                    continue;
+                }
+
+                step.status = StepStatus::Failed;
+                match step.on_failure {
+                    FailurePolicy::Continue => operation.current_step += 1,
+                    FailurePolicy::Abort => operation.status = OperationStatus::Failed,
+                }
+            }
+        }
+
+        write_operation(&operation)?;
+        emit(window, &operation);
+
+        if operation.status == OperationStatus::Failed {
+            return Ok(());
+        }
+    }
+}
+
+// The runner gave up because the record couldn't be read or saved. Mark the
+// operation failed, so start() can pick it up again, and tell the UI why.
+fn record_stopped(window: &tauri::Window, id: &str, error: &str) {
+    let Ok(mut operation) = read_operation(id) else {
+        return;
+    };
+    operation.status = OperationStatus::Failed;
+    if let Some(step) = operation.steps.get_mut(operation.current_step) {
+        if step.status == StepStatus::Running {
+            step.status = StepStatus::Failed;
+        }
+        step.log(format!("Stopped: {}", error));
+    }
+    let _ = write_operation(&operation);
+    emit(window, &operation);
+}
+
+fn finish_aborted(window: &tauri::Window, mut operation: Operation) -> Result<(), String> {
+    operation.status = OperationStatus::Aborted;
+    write_operation(&operation)?;
+    emit(window, &operation);
+    Ok(())
+}
+
+async fn run_action(action: &StepAction, loadout: Option<&str>) -> Result<String, String> {
+    match action {
+        StepAction::Command { name, args } => crate::ai::run_command(name, args, loadout).await,
+        StepAction::Shell { command } => {
+            // kill_on_drop: aborting mid-step drops this future and the child with it
+            let output = tokio::process::Command::new("sh")
+                .arg("-c")
+                .arg(command)
+                .kill_on_drop(true)
+                .output()
+                .await
+                .map_err(|e| format!("Failed to execute command: {}", e))?;
+
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            if output.status.success() {
+                Ok(format!("{}{}", stdout, stderr))
+            } else {
+                Err(format!("{} ({})\n{}{}", command, output.status, stdout, stderr))
+            }
+        }
+    }
+}
+
+fn emit(window: &tauri::Window, operation: &Operation) {
+    let _ = window.emit(EVENT_UPDATE, operation);
+}
+
+fn truncate(text: &str) -> String {
+    if text.len() <= MAX_LOG_BYTES {
+        return text.to_string();
+    }
+
+    let mut end = MAX_LOG_BYTES;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}\n[... truncated, {} bytes total]", &text[..end], text.len())
+}
+
+fn read_operation(id: &str) -> Result<Operation, String> {
+    let path = get_operation_path(id)?;
+
+    if !path.exists() {
+        return Err(format!("Operation '{}' not found", id));
+    }
+
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read operation: {}", e))?;
+
+    serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse operation '{}': {}", id, e))
+}
+
+// Written atomically so a crash mid-write can't corrupt the record a restart
+// resumes from
+fn write_operation(operation: &Operation) -> Result<(), String> {
+    let path = get_operation_path(&operation.id)?;
+    let json = serde_json::to_string_pretty(operation)
+        .map_err(|e| format!("Failed to serialize operation: {}", e))?;
+
+    crate::atomic::write(&path, json.as_bytes(), None)
+        .map_err(|e| format!("Failed to save operation: {}", e))
+}
+
+fn get_operations_dir() -> PathBuf {
+    let home = dirs::home_dir().expect("Could not find home directory");
+    home.join(".nixdeck").join("operations")
+}
+
+fn get_operation_path(id: &str) -> Result<PathBuf, String> {
+    if id.is_empty() || id.contains('/') || id.contains('\\') || id.starts_with('.') {
+        return Err(format!("Invalid operation ID '{}'", id));
+    }
+    Ok(get_operations_dir().join(format!("{}.json", id)))
+}
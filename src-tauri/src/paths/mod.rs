@@ -0,0 +1,141 @@
+// NixDeck 2133 - Config Path Resolution
+//
+// Where the XDG base directories point, and which of a tool's candidate config
+// files is the one it actually reads.
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Candidate {
+    pub path: PathBuf,
+    pub exists: bool,
+    // Under the home directory or $XDG_CONFIG_HOME, so NixDeck may write it
+    pub user: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Resolution {
+    // The file the tool reads today: the first candidate that exists
+    pub active: Option<PathBuf>,
+    // Where a new config goes: the active file when it is the user's own,
+    // otherwise the first user candidate, which then takes precedence
+    pub target: PathBuf,
+    // Every candidate in the tool's precedence order
+    pub candidates: Vec<Candidate>,
+}
+
+// $XDG_CONFIG_HOME, or ~/.config. The spec says relative values are ignored.
+pub fn config_home() -> Result<PathBuf, String> {
+    xdg_dir("XDG_CONFIG_HOME", ".config")
+}
+
+// $XDG_DATA_HOME, or ~/.local/share
+pub fn data_home() -> Result<PathBuf, String> {
+    xdg_dir("XDG_DATA_HOME", ".local/share")
+}
+
+// $XDG_CONFIG_DIRS, or /etc/xdg
+fn config_dirs() -> Vec<PathBuf> {
+    let dirs: Vec<PathBuf> = std::env::var("XDG_CONFIG_DIRS")
+        .unwrap_or_default()
+        .split(':')
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .collect();
+    if dirs.is_empty() {
+        vec![PathBuf::from("/etc/xdg")]
+    } else {
+        dirs
+    }
+}
+
+fn xdg_dir(variable: &str, fallback: &str) -> Result<PathBuf, String> {
+    if let Some(dir) = std::env::var_os(variable).map(PathBuf::from) {
+        if dir.is_absolute() {
+            return Ok(dir);
+        }
+    }
+    let home = dirs::home_dir().ok_or("Could not find home directory")?;
+    Ok(home.join(fallback))
+}
+
+// Expand a path as written in a component descriptor: a leading `~`,
+// `$XDG_CONFIG_HOME`, `$XDG_DATA_HOME`, `$XDG_CONFIG_DIRS` (one path per
+// directory) or any other `$VARIABLE`, which yields nothing while unset.
+// Absolute paths stay as they are; relative ones are under the home directory.
+pub fn expand(path: &str) -> Result<Vec<PathBuf>, String> {
+    let home = dirs::home_dir().ok_or("Could not find home directory")?;
+
+    if path.starts_with('/') {
+        return Ok(vec![PathBuf::from(path)]);
+    }
+    if let Some(rest) = path.strip_prefix('~') {
+        return Ok(vec![home.join(rest.trim_start_matches('/'))]);
+    }
+
+    let Some(reference) = path.strip_prefix('$') else {
+        return Ok(vec![home.join(path)]);
+    };
+    let (variable, rest) = reference.split_once('/').unwrap_or((reference, ""));
+    let bases = match variable {
+        "XDG_CONFIG_HOME" => vec![config_home()?],
+        "XDG_DATA_HOME" => vec![data_home()?],
+        "XDG_CONFIG_DIRS" => config_dirs(),
+        "HOME" => vec![home],
+        _ => std::env::var_os(variable)
+            .map(PathBuf::from)
+            .filter(|dir| dir.is_absolute())
+            .into_iter()
+            .collect(),
+    };
+
+    Ok(bases
+        .into_iter()
+        .map(|base| if rest.is_empty() { base } else { base.join(rest) })
+        .collect())
+}
+
+// Resolve a tool's candidate config paths, given in its own precedence order
+pub fn resolve(patterns: &[String]) -> Result<Resolution, String> {
+    let mut candidates: Vec<Candidate> = Vec::new();
+    for pattern in patterns {
+        for path in expand(pattern)? {
+            // With XDG unset, `$XDG_CONFIG_HOME/x` and `~/.config/x` are one file
+            if candidates.iter().any(|candidate| candidate.path == path) {
+                continue;
+            }
+            candidates.push(Candidate {
+                exists: path.is_file(),
+                user: is_user_path(&path),
+                path,
+            });
+        }
+    }
+
+    let active = candidates.iter().find(|candidate| candidate.exists);
+    let target = match active {
+        Some(candidate) if candidate.user => Some(candidate),
+        _ => candidates.iter().find(|candidate| candidate.user),
+    }
+    .ok_or("No candidate config path under the home directory")?
+    .path
+    .clone();
+
+    Ok(Resolution {
+        active: active.map(|candidate| candidate.path.clone()),
+        target,
+        candidates,
+    })
+}
+
+// Under the home directory or $XDG_CONFIG_HOME, so the user's own to change
+pub fn is_user_path(path: &Path) -> bool {
+    dirs::home_dir().is_some_and(|home| path.starts_with(home))
+        || config_home().is_ok_and(|config_home| path.starts_with(config_home))
+}
+
+// `path` relative to $XDG_CONFIG_HOME, when it is inside it
+pub fn relative_to_config_home(path: &Path) -> Option<PathBuf> {
+    let config_home = config_home().ok()?;
+    path.strip_prefix(config_home).ok().map(Path::to_path_buf)
+}
@@ -0,0 +1,206 @@
+// NixDeck 2133 - Rice Config Backup History
+//
+// Every write keeps the files it replaces in ~/.nixdeck/backups/<component>/,
+// one directory per generation, named by when it was taken. A generation
+// holds a copy of each file changed together, numbered in the order of its
+// generation.json, which records where each one lived. Only the newest
+// BACKUP_LIMIT are kept.
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const BACKUP_LIMIT: usize = 20;
+const METADATA_FILE: &str = "generation.json";
+const EXTENSION: &str = "bak";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Backup {
+    // Generation id, e.g. 20261017-142501-123; sorts by age
+    pub id: String,
+    pub created: String,
+    // Bytes kept across every file
+    pub size: u64,
+    pub files: Vec<BackupFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupFile {
+    // Where the file lived; a restore writes it back here
+    pub path: PathBuf,
+    // The main config, rather than a file it includes
+    pub main: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Generation {
+    created: String,
+    files: Vec<BackupFile>,
+}
+
+// Keep a copy of every file about to be replaced, as one generation. Files
+// that don't exist yet have nothing to keep; with none, no generation is made.
+pub fn save(component: &str, files: &[BackupFile]) -> Result<Option<Backup>, String> {
+    let kept: Vec<BackupFile> = files.iter().filter(|file| file.path.is_file()).cloned().collect();
+    if kept.is_empty() {
+        return Ok(None);
+    }
+
+    let dir = get_backups_dir(component);
+    fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create backup directory: {}", e))?;
+
+    // Two writes within a millisecond still get their own generation
+    let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S-%3f").to_string();
+    let mut id = stamp.clone();
+    let mut n = 1;
+    while dir.join(&id).exists() {
+        id = format!("{}-{}", stamp, n);
+        n += 1;
+    }
+
+    // Filled under a hidden name and renamed, so a half-written generation
+    // never shows up in the history
+    let staging = dir.join(format!(".{}.tmp", id));
+    let written = write_generation(&staging, component, kept);
+    if let Err(e) = written {
+        let _ = fs::remove_dir_all(&staging);
+        return Err(e);
+    }
+
+    let generation = dir.join(&id);
+    fs::rename(&staging, &generation)
+        .map_err(|e| format!("Failed to save {} backup: {}", component, e))?;
+
+    prune(&dir)?;
+    describe(&generation).map(Some)
+}
+
+fn write_generation(dir: &Path, component: &str, files: Vec<BackupFile>) -> Result<(), String> {
+    fs::create_dir_all(dir)
+        .map_err(|e| format!("Failed to create backup directory: {}", e))?;
+
+    for (i, file) in files.iter().enumerate() {
+        fs::copy(&file.path, copy_file(dir, i))
+            .map_err(|e| format!("Failed to back up {} file {}: {}", component, file.path.display(), e))?;
+    }
+
+    let generation = Generation {
+        created: chrono::Local::now().to_rfc3339(),
+        files,
+    };
+    let json = serde_json::to_string_pretty(&generation)
+        .map_err(|e| format!("Failed to serialize backup: {}", e))?;
+    fs::write(dir.join(METADATA_FILE), json)
+        .map_err(|e| format!("Failed to write backup metadata: {}", e))
+}
+
+// Newest first
+pub fn list(component: &str) -> Result<Vec<Backup>, String> {
+    let mut backups: Vec<Backup> = generations(&get_backups_dir(component))?
+        .iter()
+        .filter_map(|path| describe(path).ok())
+        .collect();
+    backups.reverse();
+    Ok(backups)
+}
+
+// Every file in a generation, with the content it held
+pub fn read(component: &str, id: &str) -> Result<Vec<(BackupFile, String)>, String> {
+    let dir = get_backup_path(component, id)?;
+    let generation = read_metadata(&dir)?;
+
+    generation
+        .files
+        .into_iter()
+        .enumerate()
+        .map(|(i, file)| {
+            let content = fs::read_to_string(copy_file(&dir, i))
+                .map_err(|e| format!("Failed to read backup {} of {}: {}", id, file.path.display(), e))?;
+            Ok((file, content))
+        })
+        .collect()
+}
+
+fn prune(dir: &Path) -> Result<(), String> {
+    let all = generations(dir)?;
+    let excess = all.len().saturating_sub(BACKUP_LIMIT);
+    for path in &all[..excess] {
+        fs::remove_dir_all(path)
+            .map_err(|e| format!("Failed to remove old backup {}: {}", path.display(), e))?;
+    }
+    Ok(())
+}
+
+// Generation directories, oldest first
+fn generations(dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read backup directory: {}", e)),
+    };
+
+    let mut paths: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.join(METADATA_FILE).is_file() && !generation_id(path).starts_with('.'))
+        .collect();
+    paths.sort_by_key(|path| age_key(path));
+    Ok(paths)
+}
+
+// `<stamp>` then `<stamp>-1`, `<stamp>-2`, ... for writes in the same millisecond
+fn age_key(path: &Path) -> (String, u32) {
+    let id = generation_id(path);
+    match id.match_indices('-').nth(2) {
+        Some((dash, _)) => (id[..dash].to_string(), id[dash + 1..].parse().unwrap_or(0)),
+        None => (id.to_string(), 0),
+    }
+}
+
+fn generation_id(path: &Path) -> &str {
+    path.file_name().and_then(|name| name.to_str()).unwrap_or_default()
+}
+
+fn describe(dir: &Path) -> Result<Backup, String> {
+    let generation = read_metadata(dir)?;
+    let size = (0..generation.files.len())
+        .filter_map(|i| fs::metadata(copy_file(dir, i)).ok())
+        .map(|metadata| metadata.len())
+        .sum();
+
+    Ok(Backup {
+        id: generation_id(dir).to_string(),
+        created: generation.created,
+        size,
+        files: generation.files,
+    })
+}
+
+fn read_metadata(dir: &Path) -> Result<Generation, String> {
+    let content = fs::read_to_string(dir.join(METADATA_FILE))
+        .map_err(|e| format!("Failed to read backup {}: {}", dir.display(), e))?;
+    serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse backup {}: {}", dir.display(), e))
+}
+
+fn copy_file(dir: &Path, index: usize) -> PathBuf {
+    dir.join(format!("{}.{}", index, EXTENSION))
+}
+
+fn get_backup_path(component: &str, id: &str) -> Result<PathBuf, String> {
+    // Ids come from list(); anything else can't name a backup
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_digit() || c == '-') {
+        return Err(format!("Invalid backup id '{}'", id));
+    }
+
+    let path = get_backups_dir(component).join(id);
+    if !path.join(METADATA_FILE).is_file() {
+        return Err(format!("No {} backup '{}'", component, id));
+    }
+    Ok(path)
+}
+
+fn get_backups_dir(component: &str) -> PathBuf {
+    let home = dirs::home_dir().expect("Could not find home directory");
+    home.join(".nixdeck").join("backups").join(component)
+}
@@ -0,0 +1,68 @@
+// NixDeck 2133 - INI Config Values (polybar, dunst)
+use std::ops::Range;
+
+use super::{lines, trimmed, SyntaxError};
+
+// `section.key`, e.g. `bar/main.background`; a bare key is one above the
+// first section header. A repeated key's last assignment wins.
+pub fn locate(text: &str, path: &[&str]) -> Result<Option<Range<usize>>, String> {
+    let (key, section) = path.split_last().ok_or("Empty key path")?;
+    let section = section.join(".");
+
+    let mut current = String::new();
+    let mut found = None;
+
+    for (offset, line) in lines(text) {
+        let content = line.trim_start();
+        if content.is_empty() || content.starts_with(';') || content.starts_with('#') {
+            continue;
+        }
+
+        if content.starts_with('[') {
+            if let Some(close) = content.find(']') {
+                current = content[1..close].trim().to_string();
+            }
+            continue;
+        }
+        if current != section {
+            continue;
+        }
+
+        if let Some(equals) = line.find('=') {
+            if line[..equals].trim() == *key {
+                found = Some(trimmed(text, offset + equals + 1, offset + line.len()));
+            }
+        }
+    }
+
+    Ok(found)
+}
+
+// Every line is blank, a comment, a `[section]` header or a `key = value`
+pub fn check(text: &str) -> Vec<SyntaxError> {
+    let mut errors = Vec::new();
+
+    for (index, (_, line)) in lines(text).into_iter().enumerate() {
+        let content = line.trim();
+        if content.is_empty() || content.starts_with(';') || content.starts_with('#') {
+            continue;
+        }
+
+        let message = if content.starts_with('[') {
+            match content.find(']') {
+                Some(close) if !content[1..close].trim().is_empty() => continue,
+                Some(_) => "Empty section name",
+                None => "Section header is missing ']'",
+            }
+        } else {
+            match content.find('=') {
+                Some(equals) if !content[..equals].trim().is_empty() => continue,
+                Some(_) => "Missing key before '='",
+                None => "Expected 'key = value'",
+            }
+        };
+        errors.push(SyntaxError { line: index + 1, message: message.to_string() });
+    }
+
+    errors
+}
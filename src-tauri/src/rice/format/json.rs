@@ -0,0 +1,183 @@
+// NixDeck 2133 - JSON/JSONC Config Values (waybar)
+use std::ops::Range;
+
+use super::{error_at, matching_close, skip_trivia, string_end, SyntaxError};
+
+// Path segments name object members; numeric segments index arrays, so a
+// multi-bar waybar config is addressed as `0.height`
+pub fn locate(text: &str, path: &[&str]) -> Result<Option<Range<usize>>, String> {
+    let bytes = text.as_bytes();
+    let mut pos = skip_trivia(bytes, 0, false);
+
+    for segment in path {
+        let found = match bytes.get(pos) {
+            Some(b'{') => find_member(text, pos, segment)?,
+            Some(b'[') => match segment.parse::<usize>() {
+                Ok(index) => find_element(bytes, pos, index)?,
+                Err(_) => return Err(format!("'{}' is an array; index it by number", segment)),
+            },
+            _ => return Ok(None),
+        };
+        pos = match found {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+    }
+
+    Ok(Some(pos..value_end(bytes, pos)?))
+}
+
+// Start of the value of member `name` in the object opening at `open`
+fn find_member(text: &str, open: usize, name: &str) -> Result<Option<usize>, String> {
+    let bytes = text.as_bytes();
+    let mut pos = open + 1;
+
+    loop {
+        pos = skip_trivia(bytes, pos, false);
+        match bytes.get(pos) {
+            Some(b'"') => {}
+            Some(b'}') | None => return Ok(None),
+            _ => return Err("Malformed object".to_string()),
+        }
+
+        let key_end = string_end(bytes, pos)?;
+        let key: String = serde_json::from_str(&text[pos..key_end])
+            .map_err(|e| format!("Malformed key: {}", e))?;
+
+        pos = skip_trivia(bytes, key_end, false);
+        if bytes.get(pos) != Some(&b':') {
+            return Err(format!("Expected ':' after \"{}\"", key));
+        }
+        pos = skip_trivia(bytes, pos + 1, false);
+
+        if key == name {
+            return Ok(Some(pos));
+        }
+
+        pos = skip_trivia(bytes, value_end(bytes, pos)?, false);
+        if bytes.get(pos) == Some(&b',') {
+            pos += 1;
+        }
+    }
+}
+
+fn find_element(bytes: &[u8], open: usize, index: usize) -> Result<Option<usize>, String> {
+    let mut pos = open + 1;
+
+    for n in 0.. {
+        pos = skip_trivia(bytes, pos, false);
+        if matches!(bytes.get(pos), Some(b']') | None) {
+            return Ok(None);
+        }
+        if n == index {
+            return Ok(Some(pos));
+        }
+
+        pos = skip_trivia(bytes, value_end(bytes, pos)?, false);
+        if bytes.get(pos) == Some(&b',') {
+            pos += 1;
+        }
+    }
+    Ok(None)
+}
+
+fn value_end(bytes: &[u8], pos: usize) -> Result<usize, String> {
+    match bytes.get(pos) {
+        Some(b'"') => string_end(bytes, pos),
+        Some(b'{') | Some(b'[') => matching_close(bytes, pos, false),
+        Some(_) => Ok(bytes[pos..]
+            .iter()
+            .position(|&b| matches!(b, b',' | b'}' | b']' | b'/') || b.is_ascii_whitespace())
+            .map(|offset| pos + offset)
+            .unwrap_or(bytes.len())),
+        None => Err("Unexpected end of file".to_string()),
+    }
+}
+
+// Parse the whole document and report the first error, the way waybar would
+// refuse to start on it
+pub fn check(text: &str) -> Vec<SyntaxError> {
+    let bytes = text.as_bytes();
+    let pos = skip_trivia(bytes, 0, false);
+    if pos == bytes.len() {
+        return error_at(text, pos, "Empty config");
+    }
+
+    match check_value(text, pos) {
+        Ok(end) => {
+            let end = skip_trivia(bytes, end, false);
+            if end < bytes.len() {
+                return error_at(text, end, "Unexpected content after the top-level value");
+            }
+            Vec::new()
+        }
+        Err((pos, message)) => error_at(text, pos, message),
+    }
+}
+
+// End of the value starting at `pos`, or where and why it is malformed
+fn check_value(text: &str, pos: usize) -> Result<usize, (usize, String)> {
+    let bytes = text.as_bytes();
+    match bytes.get(pos) {
+        Some(b'{') => check_container(text, pos, b'}'),
+        Some(b'[') => check_container(text, pos, b']'),
+        Some(b'"') => check_string(text, pos),
+        Some(_) => {
+            let end = bytes[pos..]
+                .iter()
+                .position(|&b| !(b.is_ascii_alphanumeric() || matches!(b, b'-' | b'+' | b'.')))
+                .map(|offset| pos + offset)
+                .unwrap_or(bytes.len());
+            if end == pos {
+                let found = text[pos..].chars().next().unwrap_or(' ');
+                return Err((pos, format!("Unexpected '{}'", found)));
+            }
+            match serde_json::from_str::<serde_json::Value>(&text[pos..end]) {
+                Ok(_) => Ok(end),
+                Err(_) => Err((pos, format!("Invalid value '{}'", &text[pos..end]))),
+            }
+        }
+        None => Err((pos, "Unexpected end of file".to_string())),
+    }
+}
+
+// Objects and arrays; trailing commas are fine, as waybar allows them
+fn check_container(text: &str, open: usize, close: u8) -> Result<usize, (usize, String)> {
+    let bytes = text.as_bytes();
+    let unclosed = || (open, format!("'{}' is never closed", bytes[open] as char));
+    let mut pos = skip_trivia(bytes, open + 1, false);
+
+    loop {
+        match bytes.get(pos) {
+            Some(&b) if b == close => return Ok(pos + 1),
+            None => return Err(unclosed()),
+            _ => {}
+        }
+
+        if close == b'}' {
+            if bytes.get(pos) != Some(&b'"') {
+                return Err((pos, "Expected a quoted key or '}'".to_string()));
+            }
+            pos = skip_trivia(bytes, check_string(text, pos)?, false);
+            if bytes.get(pos) != Some(&b':') {
+                return Err((pos, "Expected ':' after key".to_string()));
+            }
+            pos = skip_trivia(bytes, pos + 1, false);
+        }
+
+        pos = skip_trivia(bytes, check_value(text, pos)?, false);
+        match bytes.get(pos) {
+            Some(b',') => pos = skip_trivia(bytes, pos + 1, false),
+            Some(&b) if b == close => {}
+            None => return Err(unclosed()),
+            _ => return Err((pos, format!("Expected ',' or '{}'", close as char))),
+        }
+    }
+}
+
+fn check_string(text: &str, pos: usize) -> Result<usize, (usize, String)> {
+    let end = string_end(text.as_bytes(), pos).map_err(|e| (pos, e))?;
+    serde_json::from_str::<String>(&text[pos..end])
+        .map(|_| end)
+        .map_err(|_| (pos, "Invalid string (bad escape or raw line break)".to_string()))
+}
@@ -0,0 +1,51 @@
+// NixDeck 2133 - kitty.conf Values
+use std::ops::Range;
+
+use super::{lines, trimmed, SyntaxError};
+
+// `key value` lines; kitty applies the last one, so that is what gets edited
+pub fn locate(text: &str, path: &[&str]) -> Result<Option<Range<usize>>, String> {
+    let [key] = path else {
+        return Err("kitty options are not nested; use the option name alone".to_string());
+    };
+
+    let mut found = None;
+    for (offset, line) in lines(text) {
+        let content = line.trim_start();
+        if content.is_empty() || content.starts_with('#') {
+            continue;
+        }
+
+        let indent = line.len() - content.len();
+        let name_end = content.find(char::is_whitespace).unwrap_or(content.len());
+        if &content[..name_end] == *key {
+            found = Some(trimmed(text, offset + indent + name_end, offset + line.len()));
+        }
+    }
+
+    Ok(found)
+}
+
+// Option names are plain words; kitty skips any line it can't read, so a typo
+// would silently drop a setting
+pub fn check(text: &str) -> Vec<SyntaxError> {
+    let mut errors = Vec::new();
+
+    for (index, (_, line)) in lines(text).into_iter().enumerate() {
+        let content = line.trim_start();
+        // `\` continues the previous line
+        if content.is_empty() || content.starts_with('#') || content.starts_with('\\') {
+            continue;
+        }
+
+        let name = content.split_whitespace().next().unwrap_or("");
+        if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            errors.push(SyntaxError {
+                line: index + 1,
+                message: format!("'{}' is not a valid option name", name),
+            });
+        }
+    }
+
+    errors
+}
@@ -0,0 +1,198 @@
+// NixDeck 2133 - libconfig Values (picom.conf)
+use std::ops::Range;
+
+use super::{error_at, matching_close, skip_trivia, string_end, trimmed, SyntaxError};
+
+// Settings nest through groups, e.g. `shadow-radius` or
+// `wintypes.tooltip.opacity`
+pub fn locate(text: &str, path: &[&str]) -> Result<Option<Range<usize>>, String> {
+    let bytes = text.as_bytes();
+    let mut pos = 0;
+
+    for (depth, segment) in path.iter().enumerate() {
+        let start = match find_setting(text, pos, segment)? {
+            Some(start) => start,
+            None => return Ok(None),
+        };
+
+        if depth + 1 == path.len() {
+            return Ok(Some(start..value_end(text, start)?));
+        }
+        if bytes.get(start) != Some(&b'{') {
+            return Ok(None);
+        }
+        pos = start + 1;
+    }
+
+    Ok(None)
+}
+
+// Start of setting `name`'s value among the settings from `pos` to the end of
+// the enclosing group
+fn find_setting(text: &str, mut pos: usize, name: &str) -> Result<Option<usize>, String> {
+    let bytes = text.as_bytes();
+
+    loop {
+        pos = skip_trivia(bytes, pos, true);
+        if matches!(bytes.get(pos), Some(b'}') | None) {
+            return Ok(None);
+        }
+
+        let name_end = bytes[pos..]
+            .iter()
+            .position(|&b| !(b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'*')))
+            .map(|offset| pos + offset)
+            .unwrap_or(bytes.len());
+        if name_end == pos {
+            return Err(format!("Unexpected '{}' in config", bytes[pos] as char));
+        }
+        let setting = &text[pos..name_end];
+
+        pos = skip_trivia(bytes, name_end, true);
+        if !matches!(bytes.get(pos), Some(b'=') | Some(b':')) {
+            return Err(format!("Expected '=' after {}", setting));
+        }
+        pos = skip_trivia(bytes, pos + 1, true);
+
+        if setting == name {
+            return Ok(Some(pos));
+        }
+
+        pos = skip_trivia(bytes, value_end(text, pos)?, true);
+        if matches!(bytes.get(pos), Some(b';') | Some(b',')) {
+            pos += 1;
+        }
+    }
+}
+
+fn value_end(text: &str, pos: usize) -> Result<usize, String> {
+    let bytes = text.as_bytes();
+    match bytes.get(pos) {
+        Some(b'"') => string_end(bytes, pos),
+        Some(b'{') | Some(b'(') | Some(b'[') => matching_close(bytes, pos, true),
+        Some(_) => {
+            let end = bytes[pos..]
+                .iter()
+                .position(|&b| matches!(b, b';' | b',' | b'\n' | b'}' | b')' | b']' | b'#'))
+                .map(|offset| pos + offset)
+                .unwrap_or(bytes.len());
+            Ok(trimmed(text, pos, end).end)
+        }
+        None => Err("Unexpected end of file".to_string()),
+    }
+}
+
+// Parse every setting and report the first error, as picom would on startup
+pub fn check(text: &str) -> Vec<SyntaxError> {
+    match check_settings(text, 0, None) {
+        Ok(_) => Vec::new(),
+        Err((pos, message)) => error_at(text, pos, message),
+    }
+}
+
+// Settings from `pos` to the `}` closing the group opened at `open`, or to the
+// end of the file at the top level
+fn check_settings(text: &str, mut pos: usize, open: Option<usize>) -> Result<usize, (usize, String)> {
+    let bytes = text.as_bytes();
+
+    loop {
+        pos = skip_trivia(bytes, pos, true);
+        match (bytes.get(pos), open) {
+            (None, None) => return Ok(pos),
+            (None, Some(open)) => return Err((open, "'{' is never closed".to_string())),
+            (Some(b'}'), Some(_)) => return Ok(pos + 1),
+            (Some(b'}'), None) => return Err((pos, "Unmatched '}'".to_string())),
+            _ => {}
+        }
+
+        let name_end = bytes[pos..]
+            .iter()
+            .position(|&b| !(b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'*')))
+            .map(|offset| pos + offset)
+            .unwrap_or(bytes.len());
+        if name_end == pos || bytes[pos].is_ascii_digit() {
+            return Err((pos, "Expected a setting name".to_string()));
+        }
+        let name = &text[pos..name_end];
+
+        pos = skip_trivia(bytes, name_end, true);
+        if !matches!(bytes.get(pos), Some(b'=') | Some(b':')) {
+            return Err((pos, format!("Expected '=' after {}", name)));
+        }
+        pos = skip_trivia(bytes, pos + 1, true);
+        pos = skip_trivia(bytes, check_value(text, pos)?, true);
+        if matches!(bytes.get(pos), Some(b';') | Some(b',')) {
+            pos += 1;
+        }
+    }
+}
+
+fn check_value(text: &str, pos: usize) -> Result<usize, (usize, String)> {
+    let bytes = text.as_bytes();
+    match bytes.get(pos) {
+        Some(b'{') => check_settings(text, pos + 1, Some(pos)),
+        Some(b'(') => check_elements(text, pos, b')'),
+        Some(b'[') => check_elements(text, pos, b']'),
+        Some(b'"') => {
+            // Adjacent strings are joined into one
+            let mut end = string_end(bytes, pos).map_err(|e| (pos, e))?;
+            loop {
+                let next = skip_trivia(bytes, end, true);
+                if bytes.get(next) != Some(&b'"') {
+                    return Ok(end);
+                }
+                end = string_end(bytes, next).map_err(|e| (next, e))?;
+            }
+        }
+        Some(_) => {
+            let end = bytes[pos..]
+                .iter()
+                .position(|&b| !(b.is_ascii_alphanumeric() || matches!(b, b'-' | b'+' | b'.')))
+                .map(|offset| pos + offset)
+                .unwrap_or(bytes.len());
+            let token = &text[pos..end];
+            if token.is_empty() {
+                return Err((pos, "Expected a value".to_string()));
+            }
+            if !is_scalar(token) {
+                return Err((pos, format!("Invalid value '{}'", token)));
+            }
+            Ok(end)
+        }
+        None => Err((pos, "Expected a value".to_string())),
+    }
+}
+
+// Lists `( )` and arrays `[ ]`; a trailing comma is tolerated
+fn check_elements(text: &str, open: usize, close: u8) -> Result<usize, (usize, String)> {
+    let bytes = text.as_bytes();
+    let mut pos = skip_trivia(bytes, open + 1, true);
+
+    loop {
+        if bytes.get(pos) == Some(&close) {
+            return Ok(pos + 1);
+        }
+        pos = skip_trivia(bytes, check_value(text, pos)?, true);
+        match bytes.get(pos) {
+            Some(b',') => pos = skip_trivia(bytes, pos + 1, true),
+            Some(&b) if b == close => return Ok(pos + 1),
+            None => return Err((open, format!("'{}' is never closed", bytes[open] as char))),
+            _ => return Err((pos, format!("Expected ',' or '{}'", close as char))),
+        }
+    }
+}
+
+// Booleans, integers (hex and the `L` 64-bit suffix included) and floats
+fn is_scalar(token: &str) -> bool {
+    let lower = token.to_ascii_lowercase();
+    if lower == "true" || lower == "false" {
+        return true;
+    }
+
+    let number = lower.trim_end_matches('l');
+    if let Some(hex) = number.strip_prefix("0x") {
+        return !hex.is_empty() && hex.chars().all(|c| c.is_ascii_hexdigit());
+    }
+    number.chars().all(|c| c.is_ascii_digit() || matches!(c, '.' | '-' | '+' | 'e'))
+        && number.parse::<f64>().is_ok()
+}
@@ -0,0 +1,431 @@
+// NixDeck 2133 - Rice Config Formats
+//
+// Each format finds the exact span of one value in the file's text, so a
+// value can be read or replaced without reformatting the file or losing its
+// comments. Each can also check a whole file's syntax before it is written.
+mod ini;
+mod json;
+mod kitty;
+mod libconfig;
+mod rasi;
+mod toml;
+mod yaml;
+
+use serde_json::Value;
+use std::ops::Range;
+
+// A syntax problem at a 1-based line
+#[derive(Debug, Clone)]
+pub struct SyntaxError {
+    pub line: usize,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    // JSON with comments and trailing commas, as waybar reads it
+    Json,
+    Ini,
+    Kitty,
+    Yaml,
+    Toml,
+    Rasi,
+    Libconfig,
+}
+
+impl Format {
+    // As named in a component descriptor's `format`
+    pub fn from_name(name: &str) -> Option<Format> {
+        match name {
+            "json" => Some(Format::Json),
+            "ini" => Some(Format::Ini),
+            "kitty" => Some(Format::Kitty),
+            "yaml" => Some(Format::Yaml),
+            "toml" => Some(Format::Toml),
+            "rasi" => Some(Format::Rasi),
+            "libconfig" => Some(Format::Libconfig),
+            _ => None,
+        }
+    }
+
+    // For components that don't name a format. `.conf` could be anything, so
+    // it isn't guessed at.
+    pub fn from_extension(extension: &str) -> Option<Format> {
+        match extension {
+            "json" | "jsonc" => Some(Format::Json),
+            "ini" => Some(Format::Ini),
+            "yml" | "yaml" => Some(Format::Yaml),
+            "toml" => Some(Format::Toml),
+            "rasi" => Some(Format::Rasi),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Format::Json => "JSON",
+            Format::Ini => "INI",
+            Format::Kitty => "kitty",
+            Format::Yaml => "YAML",
+            Format::Toml => "TOML",
+            Format::Rasi => "rasi",
+            Format::Libconfig => "libconfig",
+        }
+    }
+
+    // Whether string values must always be quoted
+    fn quotes_strings(&self) -> bool {
+        matches!(self, Format::Json | Format::Toml | Format::Libconfig)
+    }
+
+    // Byte range of the raw value at `key_path`, quotes included
+    fn locate(&self, text: &str, key_path: &str) -> Result<Range<usize>, String> {
+        let path: Vec<&str> = key_path.split('.').map(str::trim).collect();
+        if path.iter().any(|segment| segment.is_empty()) {
+            return Err(format!("Invalid key path '{}'", key_path));
+        }
+
+        let found = match self {
+            Format::Json => json::locate(text, &path),
+            Format::Ini => ini::locate(text, &path),
+            Format::Kitty => kitty::locate(text, &path),
+            Format::Yaml => yaml::locate(text, &path),
+            Format::Toml => toml::locate(text, &path),
+            Format::Rasi => rasi::locate(text, &path),
+            Format::Libconfig => libconfig::locate(text, &path),
+        };
+        found?.ok_or_else(|| format!("Key '{}' not found", key_path))
+    }
+}
+
+// Syntax errors in a whole file; empty when it parses
+pub fn check(format: Format, text: &str) -> Vec<SyntaxError> {
+    match format {
+        Format::Json => json::check(text),
+        Format::Ini => ini::check(text),
+        Format::Kitty => kitty::check(text),
+        Format::Yaml => yaml::check(text),
+        Format::Toml => toml::check(text),
+        Format::Rasi => rasi::check(text),
+        Format::Libconfig => libconfig::check(text),
+    }
+}
+
+// Read the value at `key_path`
+pub fn get(format: Format, text: &str, key_path: &str) -> Result<Value, String> {
+    let span = format.locate(text, key_path)?;
+    Ok(decode(format, &text[span]))
+}
+
+// Replace the value at `key_path`, leaving the rest of the text untouched
+pub fn set(format: Format, text: &str, key_path: &str, value: &Value) -> Result<String, String> {
+    let span = format.locate(text, key_path)?;
+    let raw = encode(format, &text[span.clone()], value)?;
+
+    let mut updated = String::with_capacity(text.len() + raw.len());
+    updated.push_str(&text[..span.start]);
+    updated.push_str(&raw);
+    updated.push_str(&text[span.end..]);
+
+    // Make sure the edit reads back as written before it goes anywhere near disk
+    if get(format, &updated, key_path)? != *value {
+        return Err(format!("Setting '{}' would not round-trip in this {} file", key_path, format.name()));
+    }
+
+    Ok(updated)
+}
+
+fn decode(format: Format, raw: &str) -> Value {
+    if format == Format::Json {
+        return serde_json::from_str(raw).unwrap_or_else(|_| Value::String(raw.to_string()));
+    }
+
+    match quote_of(raw) {
+        Some('"') => {
+            serde_json::from_str(raw).unwrap_or_else(|_| Value::String(raw[1..raw.len() - 1].to_string()))
+        }
+        Some(_) => {
+            let inner = &raw[1..raw.len() - 1];
+            // YAML escapes a single quote by doubling it
+            let inner = if format == Format::Yaml { inner.replace("''", "'") } else { inner.to_string() };
+            Value::String(inner)
+        }
+        None => match raw {
+            "true" => Value::Bool(true),
+            "false" => Value::Bool(false),
+            _ => number(raw).unwrap_or_else(|| Value::String(raw.to_string())),
+        },
+    }
+}
+
+fn encode(format: Format, existing: &str, value: &Value) -> Result<String, String> {
+    if format == Format::Json {
+        return serde_json::to_string(value).map_err(|e| format!("Failed to encode value: {}", e));
+    }
+
+    match value {
+        Value::String(text) => {
+            // Keep the file's own quoting style where it has one
+            let quote = quote_of(existing)
+                .or_else(|| format.quotes_strings().then_some('"'))
+                .or_else(|| (format == Format::Yaml && yaml::needs_quotes(text)).then_some('"'));
+
+            match quote {
+                Some('\'') if !text.contains('\'') && !text.contains('\n') => Ok(format!("'{}'", text)),
+                Some(_) => serde_json::to_string(text).map_err(|e| format!("Failed to encode value: {}", e)),
+                None if text.contains('\n') => Err(format!("{} values can't span lines", format.name())),
+                None => Ok(text.clone()),
+            }
+        }
+        Value::Number(number) => Ok(number.to_string()),
+        Value::Bool(flag) => Ok(flag.to_string()),
+        _ => Err(format!("Only strings, numbers and booleans can be set in {} files", format.name())),
+    }
+}
+
+fn quote_of(raw: &str) -> Option<char> {
+    let first = raw.chars().next()?;
+    ((first == '"' || first == '\'') && raw.len() >= 2 && raw.ends_with(first)).then_some(first)
+}
+
+fn number(raw: &str) -> Option<Value> {
+    if let Ok(integer) = raw.parse::<i64>() {
+        return Some(Value::from(integer));
+    }
+    // Only plain decimals; "inf", "1e3px" and the like stay strings
+    if !raw.chars().all(|c| c.is_ascii_digit() || matches!(c, '.' | '-' | '+')) {
+        return None;
+    }
+    raw.parse::<f64>().ok().and_then(serde_json::Number::from_f64).map(Value::Number)
+}
+
+// Scanning helpers shared by the brace-delimited formats
+
+// Skip whitespace and comments: `//` and `/* */` always, `#` when `hash_comments`
+fn skip_trivia(bytes: &[u8], mut pos: usize, hash_comments: bool) -> usize {
+    while pos < bytes.len() {
+        match bytes[pos] {
+            b' ' | b'\t' | b'\r' | b'\n' => pos += 1,
+            b'#' if hash_comments => pos = line_end(bytes, pos),
+            b'/' if bytes.get(pos + 1) == Some(&b'/') => pos = line_end(bytes, pos),
+            b'/' if bytes.get(pos + 1) == Some(&b'*') => {
+                pos = find(bytes, pos + 2, b"*/").map(|end| end + 2).unwrap_or(bytes.len());
+            }
+            _ => break,
+        }
+    }
+    pos
+}
+
+// Index just past the closing quote of the string starting at `pos`
+fn string_end(bytes: &[u8], pos: usize) -> Result<usize, String> {
+    let quote = bytes[pos];
+    let mut i = pos + 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' if quote == b'"' => i += 2,
+            c if c == quote => return Ok(i + 1),
+            _ => i += 1,
+        }
+    }
+    Err("Unterminated string".to_string())
+}
+
+// Index just past the bracket closing the one at `pos`, skipping strings and comments
+fn matching_close(bytes: &[u8], pos: usize, hash_comments: bool) -> Result<usize, String> {
+    let mut depth = 0usize;
+    let mut i = pos;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'"' | b'\'' => {
+                i = string_end(bytes, i)?;
+                continue;
+            }
+            b'#' if hash_comments => {
+                i = line_end(bytes, i);
+                continue;
+            }
+            b'/' if matches!(bytes.get(i + 1), Some(b'/') | Some(b'*')) => {
+                i = skip_trivia(bytes, i, hash_comments);
+                continue;
+            }
+            b'{' | b'[' | b'(' => depth += 1,
+            b'}' | b']' | b')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(i + 1);
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    Err("Unbalanced brackets".to_string())
+}
+
+fn line_end(bytes: &[u8], pos: usize) -> usize {
+    bytes[pos..]
+        .iter()
+        .position(|&b| b == b'\n')
+        .map(|offset| pos + offset)
+        .unwrap_or(bytes.len())
+}
+
+fn find(bytes: &[u8], from: usize, needle: &[u8]) -> Option<usize> {
+    bytes
+        .get(from..)?
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .map(|offset| from + offset)
+}
+
+// Lines with the byte offset each starts at, line endings stripped
+fn lines(text: &str) -> Vec<(usize, &str)> {
+    let mut offset = 0;
+    text.split_inclusive('\n')
+        .map(|line| {
+            let start = offset;
+            offset += line.len();
+            (start, line.trim_end_matches(['\n', '\r']))
+        })
+        .collect()
+}
+
+// 1-based line of byte `pos`
+fn line_at(text: &str, pos: usize) -> usize {
+    text.as_bytes()[..pos.min(text.len())].iter().filter(|&&b| b == b'\n').count() + 1
+}
+
+// A single error at byte `pos`, for the formats that stop at the first one
+fn error_at(text: &str, pos: usize, message: impl Into<String>) -> Vec<SyntaxError> {
+    vec![SyntaxError { line: line_at(text, pos), message: message.into() }]
+}
+
+// Narrow `start..end` of `text` to its non-whitespace part
+fn trimmed(text: &str, start: usize, end: usize) -> Range<usize> {
+    let slice = &text[start..end];
+    let leading = slice.len() - slice.trim_start().len();
+    let trailing = slice.len() - slice.trim_end().len();
+    if leading == slice.len() {
+        return start..start;
+    }
+    start + leading..end - trailing
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    // Replace the value at `key`, which must currently read `old` in the file,
+    // and check `new` is written in its place with every other byte kept
+    fn edit(format: Format, text: &str, key: &str, old: &str, value: Value, new: &str) -> String {
+        let span = format.locate(text, key).unwrap();
+        assert_eq!(&text[span.clone()], old, "{} in {}", key, format.name());
+
+        let updated = set(format, text, key, &value).unwrap();
+        assert_eq!(&updated[..span.start], &text[..span.start]);
+        assert_eq!(&updated[span.start..span.start + new.len()], new);
+        assert_eq!(&updated[span.start + new.len()..], &text[span.end..]);
+        assert_eq!(get(format, &updated, key).unwrap(), value);
+        updated
+    }
+
+    #[test]
+    fn json_with_comments_and_trailing_commas() {
+        let text = r#"// waybar
+{
+    "layer": "top", // "height": 99,
+    "height": 30,
+    /* modules */ "modules-left": ["sway/workspaces"],
+    "bars": [{"name": "a"}, {"name": "b",},],
+}
+"#;
+        assert_eq!(get(Format::Json, text, "modules-left").unwrap(), json!(["sway/workspaces"]));
+
+        let text = edit(Format::Json, text, "height", "30", json!(34), "34");
+        let text = edit(Format::Json, &text, "layer", r#""top""#, json!("bottom"), r#""bottom""#);
+        let text = edit(Format::Json, &text, "bars.1.name", r#""b""#, json!("c"), r#""c""#);
+        assert!(check(Format::Json, &text).is_empty());
+        assert!(get(Format::Json, &text, "bars.2.name").is_err());
+    }
+
+    #[test]
+    fn ini_keeps_sections_comments_and_quotes() {
+        let text = "; polybar\n[colors]\nbackground = #222\nforeground = \"#eee\"\n\n[bar/main]\n; width = 50%\nwidth = 100%\nfont-0 = 'Hack:size=10'\n";
+
+        let text = edit(Format::Ini, text, "colors.background", "#222", json!("#333"), "#333");
+        let text = edit(Format::Ini, &text, "colors.foreground", "\"#eee\"", json!("#fff"), "\"#fff\"");
+        let text = edit(Format::Ini, &text, "bar/main.width", "100%", json!("90%"), "90%");
+        let text = edit(Format::Ini, &text, "bar/main.font-0", "'Hack:size=10'", json!("Iosevka:size=11"), "'Iosevka:size=11'");
+        assert!(text.contains("; width = 50%"));
+        assert!(check(Format::Ini, &text).is_empty());
+    }
+
+    #[test]
+    fn kitty_edits_the_last_assignment() {
+        let text = "# kitty\nfont_family      JetBrains Mono\nfont_size 11.0\n# font_size 14.0\nbackground #1e1e2e\nfont_size 12.0\n";
+        assert_eq!(get(Format::Kitty, text, "font_size").unwrap(), json!(12.0));
+
+        let text = edit(Format::Kitty, text, "font_size", "12.0", json!(13.5), "13.5");
+        let text = edit(Format::Kitty, &text, "font_family", "JetBrains Mono", json!("Fira Code"), "Fira Code");
+        let text = edit(Format::Kitty, &text, "background", "#1e1e2e", json!("#000000"), "#000000");
+        assert!(text.contains("font_size 11.0\n# font_size 14.0\n"));
+        assert!(check(Format::Kitty, &text).is_empty());
+    }
+
+    #[test]
+    fn yaml_keeps_quoting_and_trailing_comments() {
+        let text = "# alacritty\nfont:\n  normal:\n    family: \"Hack\" # main font\n  size: 11.0\ncolors:\n  primary:\n    background: '#1e1e2e'\n    foreground: 'it''s'\nwindow:\n  title: Alacritty  # title\n";
+        assert_eq!(get(Format::Yaml, text, "colors.primary.foreground").unwrap(), json!("it's"));
+
+        let text = edit(Format::Yaml, text, "font.normal.family", "\"Hack\"", json!("Fira Code"), "\"Fira Code\"");
+        let text = edit(Format::Yaml, &text, "font.size", "11.0", json!(12.5), "12.5");
+        let text = edit(Format::Yaml, &text, "colors.primary.background", "'#1e1e2e'", json!("#000000"), "'#000000'");
+        let text = edit(Format::Yaml, &text, "window.title", "Alacritty", json!("Terminal"), "Terminal");
+        // A plain value YAML would read as a boolean gets quoted
+        let text = edit(Format::Yaml, &text, "window.title", "Terminal", json!("yes"), "\"yes\"");
+        assert!(check(Format::Yaml, &text).is_empty());
+    }
+
+    #[test]
+    fn toml_across_tables_and_dotted_keys() {
+        let text = "# alacritty.toml\n[font]\nsize = 11.0 # points\nnormal.family = \"Hack\"\n\n[font.bold]\nfamily = 'Hack Bold'\n\n[colors.primary]\nbackground = \"#1e1e2e\"\n";
+
+        let text = edit(Format::Toml, text, "font.size", "11.0", json!(12.5), "12.5");
+        let text = edit(Format::Toml, &text, "font.normal.family", "\"Hack\"", json!("Fira Code"), "\"Fira Code\"");
+        let text = edit(Format::Toml, &text, "font.bold.family", "'Hack Bold'", json!("Fira Bold"), "'Fira Bold'");
+        let text = edit(Format::Toml, &text, "colors.primary.background", "\"#1e1e2e\"", json!("#000000"), "\"#000000\"");
+        assert!(check(Format::Toml, &text).is_empty());
+    }
+
+    #[test]
+    fn rasi_by_selector_and_property() {
+        let text = "/* rofi */\nconfiguration {\n    font: \"Hack 10\"; // font: \"x\";\n    show-icons: true;\n}\n* {\n    background-color: #1e1e2e;\n    location: 'center';\n}\n";
+
+        let text = edit(Format::Rasi, text, "configuration.font", "\"Hack 10\"", json!("Iosevka 12"), "\"Iosevka 12\"");
+        let text = edit(Format::Rasi, &text, "configuration.show-icons", "true", json!(false), "false");
+        let text = edit(Format::Rasi, &text, "*.background-color", "#1e1e2e", json!("#000000"), "#000000");
+        let text = edit(Format::Rasi, &text, "*.location", "'center'", json!("north"), "'north'");
+        assert!(check(Format::Rasi, &text).is_empty());
+    }
+
+    #[test]
+    fn libconfig_through_nested_groups() {
+        let text = "# picom\nshadow = true;\nshadow-radius = 12; // px\nbackend = \"glx\";\nwintypes:\n{\n  tooltip = { fade = true; opacity = 0.75; };\n  /* menus */ popup_menu = { opacity = 0.8; };\n};\n";
+
+        let text = edit(Format::Libconfig, text, "shadow", "true", json!(false), "false");
+        let text = edit(Format::Libconfig, &text, "shadow-radius", "12", json!(16), "16");
+        let text = edit(Format::Libconfig, &text, "backend", "\"glx\"", json!("xrender"), "\"xrender\"");
+        let text = edit(Format::Libconfig, &text, "wintypes.tooltip.opacity", "0.75", json!(0.9), "0.9");
+        let text = edit(Format::Libconfig, &text, "wintypes.popup_menu.opacity", "0.8", json!(0.85), "0.85");
+        assert!(check(Format::Libconfig, &text).is_empty());
+    }
+
+    #[test]
+    fn values_that_cannot_be_written_are_refused() {
+        assert!(set(Format::Kitty, "font_size 11\n", "font_size", &json!("a\nb")).is_err());
+        assert!(set(Format::Ini, "[a]\nb = 1\n", "a.b", &json!([1])).is_err());
+        assert!(set(Format::Ini, "[a]\nb = 1\n", "a.c", &json!(2)).is_err());
+    }
+}
@@ -0,0 +1,179 @@
+// NixDeck 2133 - Rofi Theme (rasi) Values
+use std::ops::Range;
+
+use super::{error_at, find, line_at, skip_trivia, string_end, trimmed, SyntaxError};
+
+// `selector.property`, e.g. `configuration.font` or `*.background-color`.
+// The selector is everything before the last dot and must match the block's
+// selector as written. Later declarations win, as in rofi.
+pub fn locate(text: &str, path: &[&str]) -> Result<Option<Range<usize>>, String> {
+    let (property, selector) = path.split_last().ok_or("Empty key path")?;
+    if selector.is_empty() {
+        return Err("rasi properties live in a block; use selector.property".to_string());
+    }
+    let selector = normalize(&selector.join("."));
+
+    let bytes = text.as_bytes();
+    let mut blocks: Vec<String> = Vec::new();
+    let mut statement = 0;
+    let mut found = None;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'"' | b'\'' => {
+                i = string_end(bytes, i)?;
+                continue;
+            }
+            b'/' if matches!(bytes.get(i + 1), Some(b'/') | Some(b'*')) => {
+                i = skip_trivia(bytes, i, false);
+                continue;
+            }
+            b'{' => {
+                blocks.push(block_selector(&text[statement..i]));
+                statement = i + 1;
+            }
+            b'}' => {
+                blocks.pop();
+                statement = i + 1;
+            }
+            b';' => {
+                let declaration = &text[statement..i];
+                if let Some(colon) = property_colon(declaration) {
+                    let name = strip_comments(&declaration[..colon]);
+                    if blocks.last() == Some(&selector) && name.trim() == *property {
+                        found = Some(trimmed(text, statement + colon + 1, i));
+                    }
+                }
+                statement = i + 1;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    Ok(found)
+}
+
+// A block's selector, minus any `@import`/`@theme` lines and comments before it
+fn block_selector(prefix: &str) -> String {
+    let prefix = strip_comments(prefix);
+    let selector: Vec<&str> = prefix
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('@'))
+        .collect();
+    normalize(&selector.join(" "))
+}
+
+// The `:` ending a declaration's property name. A comment before the
+// declaration may hold colons of its own, e.g. `// font: "x";`.
+fn property_colon(declaration: &str) -> Option<usize> {
+    let bytes = declaration.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'/' if matches!(bytes.get(i + 1), Some(b'/') | Some(b'*')) => i = skip_trivia(bytes, i, false),
+            b':' => return Some(i),
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+fn strip_comments(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut output = String::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'/' && matches!(bytes.get(i + 1), Some(b'/') | Some(b'*')) {
+            i = skip_trivia(bytes, i, false);
+            output.push(' ');
+        } else {
+            let next = text[i..].chars().next().map(char::len_utf8).unwrap_or(1);
+            output.push_str(&text[i..i + next]);
+            i += next;
+        }
+    }
+    output
+}
+
+fn normalize(selector: &str) -> String {
+    selector.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// Blocks must balance and every declaration inside one needs `name: value;`
+pub fn check(text: &str) -> Vec<SyntaxError> {
+    let bytes = text.as_bytes();
+    let mut errors = Vec::new();
+    let mut blocks: Vec<usize> = Vec::new();
+    let mut statement = 0;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'"' | b'\'' => match string_end(bytes, i) {
+                Ok(end) => {
+                    i = end;
+                    continue;
+                }
+                Err(e) => return [errors, error_at(text, i, e)].concat(),
+            },
+            b'/' if bytes.get(i + 1) == Some(&b'*') && find(bytes, i + 2, b"*/").is_none() => {
+                return [errors, error_at(text, i, "Unterminated comment")].concat();
+            }
+            b'/' if matches!(bytes.get(i + 1), Some(b'/') | Some(b'*')) => {
+                i = skip_trivia(bytes, i, false);
+                continue;
+            }
+            b'{' => {
+                blocks.push(i);
+                statement = i + 1;
+            }
+            b'}' => {
+                if blocks.pop().is_none() {
+                    errors.push(SyntaxError { line: line_at(text, i), message: "Unmatched '}'".to_string() });
+                } else if let Some(start) = leftover(text, statement, i) {
+                    errors.push(SyntaxError { line: line_at(text, start), message: "Missing ';' after declaration".to_string() });
+                }
+                statement = i + 1;
+            }
+            b';' => {
+                let declaration = strip_comments(&text[statement..i]);
+                let declaration = declaration.trim();
+                let message = if declaration.starts_with('@') {
+                    None
+                } else if blocks.is_empty() {
+                    Some("Declaration outside of a block")
+                } else if !declaration.contains(':') {
+                    Some("Expected 'property: value;'")
+                } else {
+                    None
+                };
+                if let Some(message) = message {
+                    let start = statement + (text[statement..i].len() - text[statement..i].trim_start().len());
+                    errors.push(SyntaxError { line: line_at(text, start), message: message.to_string() });
+                }
+                statement = i + 1;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    if let Some(&open) = blocks.first() {
+        errors.push(SyntaxError { line: line_at(text, open), message: "'{' is never closed".to_string() });
+    } else if let Some(start) = leftover(text, statement, bytes.len()) {
+        errors.push(SyntaxError { line: line_at(text, start), message: "Expected '{' after selector".to_string() });
+    }
+
+    errors
+}
+
+// Start of any text between `start` and `end` besides comments and `@` lines
+fn leftover(text: &str, start: usize, end: usize) -> Option<usize> {
+    if block_selector(&text[start..end]).is_empty() {
+        return None;
+    }
+    let slice = &text[start..end];
+    Some(start + (slice.len() - slice.trim_start().len()))
+}
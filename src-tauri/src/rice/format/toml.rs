@@ -0,0 +1,99 @@
+// NixDeck 2133 - TOML Config Values (alacritty.toml)
+use std::ops::Range;
+
+use super::{error_at, find, lines, matching_close, string_end, trimmed, SyntaxError};
+
+// Full dotted path, however the file splits it between `[table]` headers and
+// dotted keys: `font.normal.family` matches `[font.normal]` + `family` and
+// `[font]` + `normal.family` alike
+pub fn locate(text: &str, path: &[&str]) -> Result<Option<Range<usize>>, String> {
+    let bytes = text.as_bytes();
+    let mut table: Vec<String> = Vec::new();
+    let mut skip_until = 0;
+
+    for (offset, line) in lines(text) {
+        // Inside a multi-line value that started on an earlier line
+        if offset < skip_until {
+            continue;
+        }
+
+        let content = line.trim_start();
+        if content.is_empty() || content.starts_with('#') {
+            continue;
+        }
+
+        if content.starts_with('[') {
+            let header = content.trim_start_matches('[');
+            let close = header.find(']').ok_or("Malformed table header")?;
+            table = split_keys(&header[..close]);
+            continue;
+        }
+
+        let equals = match line.find('=') {
+            Some(equals) => equals,
+            None => continue,
+        };
+        let value_start = offset + equals + 1;
+        let value = value_span(text, bytes, value_start)?;
+        skip_until = value.end;
+
+        let mut full = table.clone();
+        full.extend(split_keys(&line[..equals]));
+        if full.iter().map(String::as_str).eq(path.iter().copied()) {
+            return Ok(Some(value));
+        }
+    }
+
+    Ok(None)
+}
+
+// `a."b.c".d` -> ["a", "b.c", "d"]
+fn split_keys(keys: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut quote = None;
+
+    for c in keys.chars() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if c == q => quote = None,
+            (None, '.') => parts.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    parts.push(current);
+
+    parts.into_iter().map(|part| part.trim().to_string()).collect()
+}
+
+fn value_span(text: &str, bytes: &[u8], from: usize) -> Result<Range<usize>, String> {
+    let start = from + (text[from..].len() - text[from..].trim_start_matches([' ', '\t']).len());
+
+    let end = match bytes.get(start) {
+        Some(b'"') if bytes[start..].starts_with(b"\"\"\"") => {
+            find(bytes, start + 3, b"\"\"\"").ok_or("Unterminated string")? + 3
+        }
+        Some(b'\'') if bytes[start..].starts_with(b"'''") => {
+            find(bytes, start + 3, b"'''").ok_or("Unterminated string")? + 3
+        }
+        Some(b'"') | Some(b'\'') => string_end(bytes, start)?,
+        Some(b'[') | Some(b'{') => matching_close(bytes, start, true)?,
+        _ => {
+            let line_end = text[start..].find('\n').map(|i| start + i).unwrap_or(text.len());
+            let comment = text[start..line_end].find('#').map(|i| start + i).unwrap_or(line_end);
+            return Ok(trimmed(text, start, comment));
+        }
+    };
+
+    Ok(start..end)
+}
+
+pub fn check(text: &str) -> Vec<SyntaxError> {
+    match ::toml::from_str::<::toml::Table>(text) {
+        Ok(_) => Vec::new(),
+        Err(e) => {
+            let pos = e.span().map(|span| span.start).unwrap_or(0);
+            error_at(text, pos, e.message().trim().replace('\n', ": "))
+        }
+    }
+}
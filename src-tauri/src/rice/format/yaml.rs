@@ -0,0 +1,234 @@
+// NixDeck 2133 - YAML Config Values (alacritty.yml)
+use std::ops::Range;
+
+use super::{lines, SyntaxError};
+
+// Nested block mappings, e.g. `font.normal.family`. Lists, anchors and
+// multi-line scalars are left alone.
+pub fn locate(text: &str, path: &[&str]) -> Result<Option<Range<usize>>, String> {
+    let lines = lines(text);
+    let mut from = 0;
+    let mut parent_indent: Option<usize> = None;
+
+    for (depth, segment) in path.iter().enumerate() {
+        let mut child_indent = None;
+        let mut found = None;
+
+        for (index, (_, line)) in lines.iter().enumerate().skip(from) {
+            let content = line.trim_start();
+            if content.is_empty() || content.starts_with('#') {
+                continue;
+            }
+
+            let indent = line.len() - content.len();
+            if parent_indent.is_some_and(|parent| indent <= parent) {
+                break;
+            }
+            if indent != *child_indent.get_or_insert(indent) {
+                continue;
+            }
+
+            if let Some((key, _)) = split_key(content) {
+                if key == *segment {
+                    found = Some((index, indent));
+                    break;
+                }
+            }
+        }
+
+        let (index, indent) = match found {
+            Some(found) => found,
+            None => return Ok(None),
+        };
+
+        if depth + 1 < path.len() {
+            from = index + 1;
+            parent_indent = Some(indent);
+            continue;
+        }
+
+        let (offset, line) = lines[index];
+        let content_start = line.len() - line.trim_start().len();
+        let (_, value_start) = split_key(&line[content_start..]).ok_or("Malformed mapping")?;
+        let value = scalar(&line[content_start + value_start..])?;
+        let start = offset + content_start + value_start + value.start;
+        return Ok(Some(start..offset + content_start + value_start + value.end));
+    }
+
+    Ok(None)
+}
+
+// Strings YAML would read as something else, or not at all, unless quoted
+pub fn needs_quotes(text: &str) -> bool {
+    text.is_empty()
+        || text.contains(": ")
+        || text.contains(" #")
+        || text.starts_with(|c: char| "#&*!|>'\"%@`{}[],-?:".contains(c) || c.is_whitespace())
+        || text.ends_with(char::is_whitespace)
+        || matches!(text, "true" | "false" | "yes" | "no" | "on" | "off" | "null" | "~")
+        || text.parse::<f64>().is_ok()
+}
+
+// The key of a `key: value` line and where its value part begins
+fn split_key(content: &str) -> Option<(String, usize)> {
+    if content.starts_with('-') {
+        return None;
+    }
+
+    let (key, rest_start) = match content.chars().next()? {
+        quote @ ('"' | '\'') => {
+            let close = content[1..].find(quote)? + 1;
+            (content[1..close].to_string(), close + 1)
+        }
+        _ => {
+            let colon = content
+                .match_indices(':')
+                .map(|(i, _)| i)
+                .find(|&i| content[i + 1..].is_empty() || content[i + 1..].starts_with(' '))?;
+            (content[..colon].trim().to_string(), colon)
+        }
+    };
+
+    let rest = &content[rest_start..];
+    let colon = rest.find(':')?;
+    if !rest[..colon].trim().is_empty() {
+        return None;
+    }
+    Some((key, rest_start + colon + 1))
+}
+
+// Span of the scalar in a value part, without any trailing comment
+fn scalar(value: &str) -> Result<Range<usize>, String> {
+    let start = value.len() - value.trim_start().len();
+    let rest = &value[start..];
+
+    let end = match rest.chars().next() {
+        None | Some('#') => return Err("Key holds a nested block, not a single value".to_string()),
+        Some('|') | Some('>') => return Err("Multi-line values can't be edited here".to_string()),
+        Some('"') => {
+            let mut escaped = false;
+            let close = rest[1..]
+                .char_indices()
+                .find(|&(_, c)| {
+                    let done = c == '"' && !escaped;
+                    escaped = c == '\\' && !escaped;
+                    done
+                })
+                .ok_or("Unterminated string")?;
+            close.0 + 2
+        }
+        Some('\'') => {
+            // '' inside single quotes is an escaped quote
+            let mut i = 1;
+            loop {
+                let close = rest[i..].find('\'').ok_or("Unterminated string")? + i;
+                if rest[close + 1..].starts_with('\'') {
+                    i = close + 2;
+                } else {
+                    break close + 1;
+                }
+            }
+        }
+        Some(_) => rest.find(" #").unwrap_or(rest.len()),
+    };
+
+    Ok(start..start + rest[..end].trim_end().len())
+}
+
+// Line-level checks for the block style alacritty configs are written in:
+// indentation, `key: value` shape and string quoting. Flow collections and
+// multi-line scalars are skipped over rather than parsed.
+pub fn check(text: &str) -> Vec<SyntaxError> {
+    let mut errors = Vec::new();
+    // Indent of the key whose `|`/`>` block is being skipped
+    let mut block_scalar: Option<usize> = None;
+    // Indent of the last key that held a plain value, which can't have children
+    let mut scalar_key: Option<usize> = None;
+    let mut flow_depth = 0i32;
+
+    for (index, (_, line)) in lines(text).into_iter().enumerate() {
+        let mut error = |message: &str| errors.push(SyntaxError { line: index + 1, message: message.to_string() });
+        let content = line.trim_start();
+        let indent = line.len() - content.len();
+
+        if content.is_empty() {
+            continue;
+        }
+        if let Some(parent) = block_scalar {
+            if indent > parent {
+                continue;
+            }
+            block_scalar = None;
+        }
+        if content.starts_with('#') {
+            continue;
+        }
+        if line[..indent].contains('\t') {
+            error("Tabs can't be used for indentation");
+            continue;
+        }
+        if flow_depth > 0 {
+            flow_depth += bracket_balance(content);
+            continue;
+        }
+        if content == "---" || content == "..." {
+            scalar_key = None;
+            continue;
+        }
+
+        if scalar_key.is_some_and(|parent| indent > parent) {
+            error("Unexpected indentation under a key that already has a value");
+            continue;
+        }
+        scalar_key = None;
+
+        // A list item may hold a scalar or the first key of an inline mapping
+        let list_item = content.starts_with("- ") || content == "-";
+        let content = if list_item { content.trim_start_matches(['-', ' ']) } else { content };
+
+        let value = match split_key(content) {
+            Some((_, value_start)) => content[value_start..].trim(),
+            None if list_item || content.starts_with(['[', '{']) => content,
+            None => {
+                error("Expected 'key: value' or a list item");
+                continue;
+            }
+        };
+
+        match value.chars().next() {
+            None | Some('#') => {}
+            Some('|') | Some('>') => block_scalar = Some(indent),
+            Some('[') | Some('{') => flow_depth = bracket_balance(value).max(0),
+            // Anchors and tags may still be followed by a nested block
+            Some('&') | Some('!') => {}
+            Some(_) => {
+                if let Err(e) = scalar(value) {
+                    error(&e);
+                }
+                if !list_item && split_key(content).is_some() {
+                    scalar_key = Some(indent);
+                }
+            }
+        }
+    }
+
+    errors
+}
+
+// Net opening brackets on a line, ignoring quoted text and comments
+fn bracket_balance(text: &str) -> i32 {
+    let mut depth = 0;
+    let mut quote = None;
+    for c in text.chars() {
+        match (quote, c) {
+            (Some(q), _) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '#') => break,
+            (None, '[' | '{') => depth += 1,
+            (None, ']' | '}') => depth -= 1,
+            _ => {}
+        }
+    }
+    depth
+}
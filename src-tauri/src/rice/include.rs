@@ -0,0 +1,297 @@
+// NixDeck 2133 - Rice Config Include Graph
+//
+// Rices split their config across files: kitty `include`, polybar
+// `include-file`, rofi `@theme`, eww `(include ...)`, waybar's "include" list
+// and stylesheet @imports. Starting from the main config, then each of the
+// component's `files`, every file those directives reach is collected along
+// with its content.
+use regex::Regex;
+use serde::Serialize;
+use std::collections::{HashSet, VecDeque};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use super::registry;
+use crate::paths;
+
+// More than any real rice has; stops a runaway glob or directory include
+const MAX_FILES: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Syntax {
+    Kitty,
+    Polybar,
+    Rofi,
+    Eww,
+    Waybar,
+    Css,
+}
+
+impl Syntax {
+    pub fn from_name(name: &str) -> Option<Syntax> {
+        match name {
+            "kitty" => Some(Syntax::Kitty),
+            "polybar" => Some(Syntax::Polybar),
+            "rofi" => Some(Syntax::Rofi),
+            "eww" => Some(Syntax::Eww),
+            "waybar" => Some(Syntax::Waybar),
+            "css" => Some(Syntax::Css),
+            _ => None,
+        }
+    }
+
+    // Stylesheets are recognised by extension whatever the component says
+    fn of_file(path: &Path, fallback: Option<Syntax>) -> Option<Syntax> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("css") | Some("scss") => Some(Syntax::Css),
+            _ => fallback,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    // The file the tool loads
+    Main,
+    // Reached through another file's include directive
+    Include,
+    // One of the component's `files`
+    Extra,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigFile {
+    pub path: PathBuf,
+    pub role: Role,
+    // The file that included it first; the main config and `files` have none
+    pub parent: Option<PathBuf>,
+    // System themes and defaults are read, never written
+    pub writable: bool,
+    // None when it doesn't exist (yet) or can't be read
+    pub content: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigTree {
+    pub component: String,
+    // Every file comes after the one that included it, main config first
+    pub files: Vec<ConfigFile>,
+}
+
+enum Directive {
+    File(String),
+    Glob(String),
+    Directory(String),
+    // rofi `@theme "name"`, looked up in the theme directories too
+    Theme(String),
+    // `@import "name"`, where scss may leave out the extension and underscore
+    Stylesheet(String),
+}
+
+// The include graph rooted at `main`, the config the tool reads
+pub fn tree(component: &registry::Component, main: &Path) -> ConfigTree {
+    let main_syntax = component.includes.as_deref().and_then(Syntax::from_name);
+
+    let mut roots = vec![(main.to_path_buf(), Role::Main, main_syntax)];
+    for path in component.extra_files() {
+        // Extras like rofi's other .rasi files share the main config's syntax
+        let syntax = if path.extension() == main.extension() { main_syntax } else { None };
+        roots.push((path, Role::Extra, syntax));
+    }
+
+    let mut seen: HashSet<PathBuf> = HashSet::new();
+    let mut files: Vec<ConfigFile> = Vec::new();
+
+    for (root, role, syntax) in roots {
+        let mut queue = VecDeque::from([(root, role, None, syntax)]);
+
+        while let Some((path, role, parent, syntax)) = queue.pop_front() {
+            if files.len() >= MAX_FILES {
+                break;
+            }
+            // Links into a dotfiles repo and include cycles lead back to a seen file
+            if !seen.insert(fs::canonicalize(&path).unwrap_or_else(|_| path.clone())) {
+                continue;
+            }
+
+            let content = fs::read_to_string(&path).ok();
+            let syntax = Syntax::of_file(&path, syntax);
+            if let (Some(text), Some(syntax)) = (&content, syntax) {
+                for include in includes(syntax, text, &path, main) {
+                    // An included file is written in its includer's syntax
+                    queue.push_back((include, Role::Include, Some(path.clone()), Some(syntax)));
+                }
+            }
+
+            files.push(ConfigFile {
+                writable: paths::is_user_path(&path),
+                path,
+                role,
+                parent,
+                content,
+            });
+        }
+    }
+
+    ConfigTree {
+        component: component.name.clone(),
+        files,
+    }
+}
+
+// The files `file` includes, in the order it names them
+fn includes(syntax: Syntax, text: &str, file: &Path, main: &Path) -> Vec<PathBuf> {
+    let file_dir = file.parent().unwrap_or(Path::new("/"));
+    // kitty, eww and waybar resolve relative includes against the config
+    // directory; the rest against the including file
+    let base = match syntax {
+        Syntax::Kitty | Syntax::Eww | Syntax::Waybar => main.parent().unwrap_or(file_dir),
+        Syntax::Polybar | Syntax::Rofi | Syntax::Css => file_dir,
+    };
+
+    directives(syntax, text)
+        .into_iter()
+        .flat_map(|directive| -> Vec<PathBuf> {
+            match directive {
+                Directive::File(name) => locate(base, &name).into_iter().take(1).collect(),
+                Directive::Glob(pattern) => locate(base, &pattern).into_iter().flat_map(registry::matching_files).collect(),
+                Directive::Directory(name) => locate(base, &name).iter().flat_map(|dir| directory_files(dir)).collect(),
+                Directive::Theme(name) => theme_file(base, &name).into_iter().collect(),
+                Directive::Stylesheet(name) => stylesheet_file(base, &name).into_iter().collect(),
+            }
+        })
+        .collect()
+}
+
+fn directives(syntax: Syntax, text: &str) -> Vec<Directive> {
+    match syntax {
+        Syntax::Kitty => code_lines(text, &["#"])
+            .filter_map(|line| {
+                let (keyword, value) = line.split_once(char::is_whitespace)?;
+                let value = value.trim().to_string();
+                match keyword {
+                    "include" => Some(Directive::File(value)),
+                    "globinclude" => Some(Directive::Glob(value)),
+                    _ => None,
+                }
+            })
+            .collect(),
+        Syntax::Polybar => code_lines(text, &[";", "#"])
+            .filter_map(|line| {
+                let (key, value) = line.split_once('=')?;
+                let value = value.trim().trim_matches('"').to_string();
+                match key.trim() {
+                    "include-file" => Some(Directive::File(value)),
+                    "include-directory" => Some(Directive::Directory(value)),
+                    _ => None,
+                }
+            })
+            .collect(),
+        Syntax::Rofi => captures(text, &["//"], r#"@(?:theme|import)\s+"([^"]+)""#)
+            .into_iter()
+            .map(Directive::Theme)
+            .collect(),
+        Syntax::Eww => captures(text, &[";"], r#"\(include\s+"([^"]+)"\s*\)"#)
+            .into_iter()
+            .map(Directive::File)
+            .collect(),
+        Syntax::Waybar => captures(text, &["//"], r#""include"\s*:\s*(\[[^\]]*\]|"[^"]*")"#)
+            .iter()
+            .flat_map(|list| captures(list, &[], r#""([^"]+)""#))
+            .map(Directive::File)
+            .collect(),
+        Syntax::Css => captures(text, &["//"], r#"@(?:import|use|forward)\s+(?:url\(\s*)?["']([^"']+)["']"#)
+            .into_iter()
+            .map(Directive::Stylesheet)
+            .collect(),
+    }
+}
+
+// Trimmed lines, minus blank ones and whole-line comments
+fn code_lines<'a>(text: &'a str, comments: &'a [&str]) -> impl Iterator<Item = &'a str> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !comments.iter().any(|prefix| line.starts_with(prefix)))
+}
+
+// The first group of each match of `pattern` outside whole-line comments
+fn captures(text: &str, comments: &[&str], pattern: &str) -> Vec<String> {
+    let regex = match Regex::new(pattern) {
+        Ok(regex) => regex,
+        Err(_) => return Vec::new(),
+    };
+    let code: Vec<&str> = code_lines(text, comments).collect();
+    let code = code.join("\n");
+    regex
+        .captures_iter(&code)
+        .filter_map(|captures| Some(captures.get(1)?.as_str().to_string()))
+        .collect()
+}
+
+// `~` and `$VARIABLE` paths expand; relative ones are under `base`
+fn locate(base: &Path, name: &str) -> Vec<PathBuf> {
+    if name.starts_with('~') || name.starts_with('$') {
+        return paths::expand(name).unwrap_or_default();
+    }
+    // `./widgets/bar.yuck` is the same file as `widgets/bar.yuck`
+    let path = base.join(name);
+    vec![path.components().filter(|part| *part != Component::CurDir).collect()]
+}
+
+fn directory_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .map(|entries| entries.flatten().map(|entry| entry.path()).filter(|path| path.is_file()).collect())
+        .unwrap_or_default();
+    files.sort();
+    files
+}
+
+// rofi tries the name as a path, then a bare name in its theme directories,
+// adding `.rasi` when there is no extension
+fn theme_file(base: &Path, name: &str) -> Option<PathBuf> {
+    if name == "/dev/null" {
+        return None;
+    }
+    let name = if Path::new(name).extension().is_some() {
+        name.to_string()
+    } else {
+        format!("{}.rasi", name)
+    };
+
+    let mut candidates = locate(base, &name);
+    if !name.contains('/') {
+        let theme_dirs = [
+            paths::config_home().map(|dir| dir.join("rofi/themes")),
+            paths::data_home().map(|dir| dir.join("rofi/themes")),
+            Ok(PathBuf::from("/usr/share/rofi/themes")),
+        ];
+        candidates.extend(theme_dirs.into_iter().flatten().map(|dir| dir.join(&name)));
+    }
+
+    candidates
+        .iter()
+        .find(|path| path.is_file())
+        .or_else(|| candidates.first())
+        .cloned()
+}
+
+// Only stylesheets that exist; `@use "sass:math"` and remote URLs name none
+fn stylesheet_file(base: &Path, name: &str) -> Option<PathBuf> {
+    if name.contains(':') {
+        return None;
+    }
+    let path = locate(base, name).into_iter().next()?;
+    if path.extension().is_some() {
+        return path.is_file().then_some(path);
+    }
+
+    let stem = path.file_name()?.to_str()?.to_string();
+    [
+        path.with_file_name(format!("{}.scss", stem)),
+        path.with_file_name(format!("_{}.scss", stem)),
+        path.with_file_name(format!("{}.css", stem)),
+    ]
+    .into_iter()
+    .find(|candidate| candidate.is_file())
+}
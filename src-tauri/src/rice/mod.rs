@@ -1,47 +1,339 @@
 // NixDeck 2133 - Ricing Control Module
-use std::path::PathBuf;
+mod backup;
+mod format;
+mod include;
+mod registry;
+mod reload;
+mod validate;
 
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::atomic::Staged;
+
+pub use crate::paths::Resolution;
+pub use backup::{Backup, BackupFile};
+pub use include::ConfigTree;
+pub use registry::{Component, Registry};
+pub use reload::ReloadReport;
+pub use validate::Validation;
+
+// The config the tool actually reads, which may be a system-wide default
 pub async fn get_config(component: String) -> Result<String, String> {
-    let config_path = get_component_config_path(&component)?;
+    let active = registry::find(&component)?
+        .resolve()?
+        .active
+        .ok_or_else(|| format!("No {} config found", component))?;
     
-    std::fs::read_to_string(config_path)
+    fs::read_to_string(active)
         .map_err(|e| format!("Failed to read {} config: {}", component, e))
 }
 
-pub async fn apply_config(component: String, config: String) -> Result<(), String> {
+// Where a component's config is looked for, which file is active and which
+// one NixDeck writes
+pub async fn resolve_config(component: String) -> Result<Resolution, String> {
+    registry::find(&component)?.resolve()
+}
+
+// Check a config without writing it
+pub async fn validate_config(component: String, config: String) -> Result<Validation, String> {
+    let descriptor = registry::find(&component)?;
+    Ok(validate::validate(&descriptor, &config).await)
+}
+
+// The main config and every file it includes, with their contents
+pub async fn get_config_tree(component: String) -> Result<ConfigTree, String> {
+    let descriptor = registry::find(&component)?;
+    let resolution = descriptor.resolve()?;
+    Ok(include::tree(&descriptor, &resolution.active.unwrap_or(resolution.target)))
+}
+
+// Invalid configs are refused unless `force` is set
+pub async fn apply_config(component: String, config: String, force: bool) -> Result<(), String> {
     let config_path = get_component_config_path(&component)?;
+    let change = FileChange {
+        path: config_path,
+        content: config,
+    };
+    apply_files(component, vec![change], force).await
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FileChange {
+    pub path: PathBuf,
+    pub content: String,
+}
+
+struct PendingWrite {
+    path: PathBuf,
+    content: String,
+    // The main config, rather than a file it includes
+    main: bool,
+}
+
+// Write several files of a component's config as one change: every file is
+// validated before any is written, and a failed write undoes the rest. Only
+// the main config and files in its include tree can be written.
+pub async fn apply_files(component: String, changes: Vec<FileChange>, force: bool) -> Result<(), String> {
+    let descriptor = registry::find(&component)?;
+    let resolution = descriptor.resolve()?;
+    let active = resolution.active.clone().unwrap_or_else(|| resolution.target.clone());
+    let tree = include::tree(&descriptor, &active);
+    
+    let mut writes: Vec<PendingWrite> = Vec::new();
+    let mut failures = Vec::new();
+    for change in changes {
+        // Changes to a system-wide default go to the user's own copy
+        let main = change.path == active || change.path == resolution.target;
+        let path = if main { resolution.target.clone() } else { change.path };
+        
+        if !main {
+            match tree.files.iter().find(|file| file.path == path) {
+                None => return Err(format!("{} is not part of the {} config", path.display(), component)),
+                Some(file) if !file.writable => {
+                    return Err(format!("{} is outside the home directory and is never written", path.display()))
+                }
+                Some(_) => {}
+            }
+        }
+        if writes.iter().any(|write| write.path == path) {
+            return Err(format!("{} is in the change twice", path.display()));
+        }
+        
+        let validation = if main {
+            validate::validate(&descriptor, &change.content).await
+        } else {
+            validate::validate_include(&descriptor, &path, &change.content)
+        };
+        if !validation.valid {
+            failures.push(format!("{}:\n{}", path.display(), validation.summary()));
+        }
+        
+        writes.push(PendingWrite {
+            path,
+            content: change.content,
+            main,
+        });
+    }
     
-    // Create snapshot before applying
-    let backup_path = format!("{}.nixdeck-backup", config_path.display());
-    std::fs::copy(&config_path, &backup_path)
-        .map_err(|e| format!("Failed to create backup: {}", e))?;
+    if writes.is_empty() {
+        return Err("No files to write".to_string());
+    }
+    if !failures.is_empty() && !force {
+        return Err(format!(
+            "{} config failed validation and was not written:\n{}",
+            component,
+            failures.join("\n")
+        ));
+    }
     
-    std::fs::write(config_path, config)
-        .map_err(|e| format!("Failed to write {} config: {}", component, e))
+    write_files(&component, &writes)
 }
 
-pub async fn preview_config(component: String, config: String) -> Result<String, String> {
-    // Return a diff-like preview
-    let current = get_config(component.clone()).await?;
-    Ok(format!("=== CURRENT ===\n{}\n\n=== NEW ===\n{}", current, config))
+// Backup generations of a component's config, newest first
+pub async fn list_backups(component: String) -> Result<Vec<Backup>, String> {
+    get_component_config_path(&component)?;
+    backup::list(&component)
 }
 
-fn get_component_config_path(component: &str) -> Result<PathBuf, String> {
-    let home = dirs::home_dir().ok_or("Could not find home directory")?;
-    let config_base = home.join(".config");
-    
-    let path = match component {
-        "waybar" => config_base.join("waybar/config"),
-        "polybar" => config_base.join("polybar/config.ini"),
-        "eww" => config_base.join("eww/eww.yuck"),
-        "conky" => config_base.join("conky/conky.conf"),
-        "kitty" => config_base.join("kitty/kitty.conf"),
-        "alacritty" => config_base.join("alacritty/alacritty.yml"),
-        "picom" => config_base.join("picom/picom.conf"),
-        "dunst" => config_base.join("dunst/dunstrc"),
-        "rofi" => config_base.join("rofi/config.rasi"),
-        _ => return Err(format!("Unknown component: {}", component)),
+// What restoring a backup would change, one diff per file it holds
+pub async fn diff_backup(
+    component: String,
+    id: String,
+    word_highlight: bool,
+) -> Result<Vec<crate::diff::FileDiff>, String> {
+    get_component_config_path(&component)?;
+    let options = crate::diff::DiffOptions {
+        word_highlight,
+        ..crate::diff::DiffOptions::default()
     };
+
+    Ok(backup::read(&component, &id)?
+        .into_iter()
+        .map(|(file, saved)| {
+            let current = fs::read_to_string(&file.path).unwrap_or_default();
+            crate::diff::compare(
+                &current,
+                &saved,
+                &format!("{} (current)", file.path.display()),
+                &format!("{} (backup {})", file.path.display(), id),
+                &options,
+            )
+        })
+        .collect())
+}
+
+// Put a backup back, every file to the path it was taken from. It was a live
+// config once, so it isn't re-validated; the files it replaces get a backup
+// of their own, so a restore can be undone.
+pub async fn restore_backup(component: String, id: String) -> Result<(), String> {
+    get_component_config_path(&component)?;
+    let mut writes = Vec::new();
+    for (file, content) in backup::read(&component, &id)? {
+        // The history lives outside the config; don't trust it to name a path
+        if !crate::paths::is_user_path(&file.path) {
+            return Err(format!("Backup {} names {}, outside the home directory", id, file.path.display()));
+        }
+        writes.push(PendingWrite {
+            path: file.path,
+            content,
+            main: file.main,
+        });
+    }
+    write_files(&component, &writes)
+}
+
+// Stage every file in a temp file next to it, synced, keep backups of what
+// is there, then rename them all into place. Each rename is atomic, so a file
+// is always either old or new; a failed rename puts back the files already
+// replaced. The original's permissions carry over.
+fn write_files(component: &str, writes: &[PendingWrite]) -> Result<(), String> {
+    let mut staged: Vec<Staged> = Vec::new();
+    for write in writes {
+        match stage(&write.path, write.content.as_bytes()) {
+            Ok(file) => staged.push(file),
+            Err(e) => {
+                discard(&staged);
+                return Err(format!("Failed to write {} config {}: {}", component, write.path.display(), e));
+            }
+        }
+    }
+    
+    // One generation covers the whole change, so it can be undone as one
+    let replaced: Vec<BackupFile> = writes
+        .iter()
+        .map(|write| BackupFile {
+            path: write.path.clone(),
+            main: write.main,
+        })
+        .collect();
+    if let Err(e) = backup::save(component, &replaced) {
+        discard(&staged);
+        return Err(e);
+    }
+    
+    // What each file held before, to undo a change that fails halfway
+    let originals: Vec<Option<Vec<u8>>> = staged.iter().map(|file| fs::read(&file.path).ok()).collect();
+    
+    for (i, file) in staged.iter().enumerate() {
+        if let Err(e) = file.commit() {
+            let mut unrestored = Vec::new();
+            for (done, original) in staged[..i].iter().zip(&originals) {
+                let restored = match original {
+                    Some(content) => stage(&done.path, content).and_then(|undo| undo.commit()),
+                    None => fs::remove_file(&done.path).map_err(|e| e.to_string()),
+                };
+                if restored.is_err() {
+                    unrestored.push(done.path.display().to_string());
+                }
+            }
+            discard(&staged[i + 1..]);
+            
+            let outcome = if unrestored.is_empty() {
+                "no file was changed".to_string()
+            } else {
+                format!("could not put back {}", unrestored.join(", "))
+            };
+            return Err(format!("Failed to write {} config: {}; {}", component, e, outcome));
+        }
+    }
     
-    Ok(path)
+    Ok(())
+}
+
+// Configs are often symlinks into a dotfiles repo; replace the file the link
+// points at, not the link. The original's permissions carry over.
+fn stage(path: &Path, content: &[u8]) -> Result<Staged, String> {
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    crate::atomic::stage(&path, content, None)
+}
+
+fn discard(staged: &[Staged]) {
+    for file in staged {
+        file.discard();
+    }
+}
+
+pub async fn preview_config(
+    component: String,
+    config: String,
+    word_highlight: bool,
+) -> Result<crate::diff::FileDiff, String> {
+    // A component with no config yet diffs against an empty file
+    let current = match registry::find(&component)?.resolve()?.active {
+        Some(_) => get_config(component.clone()).await?,
+        None => String::new(),
+    };
+
+    let options = crate::diff::DiffOptions {
+        word_highlight,
+        ..crate::diff::DiffOptions::default()
+    };
+    Ok(crate::diff::compare(
+        &current,
+        &config,
+        &format!("{} (current)", component),
+        &format!("{} (new)", component),
+        &options,
+    ))
+}
+
+// Make a running component pick up its config
+pub async fn reload_component(component: String) -> Result<ReloadReport, String> {
+    reload::reload(&registry::find(&component)?).await
+}
+
+// Read one value, e.g. `bar/main.background` from polybar
+pub async fn get_value(component: String, key_path: String) -> Result<serde_json::Value, String> {
+    let format = registry::find(&component)?.format()?;
+    let config = get_config(component.clone()).await?;
+
+    format::get(format, &config, &key_path)
+        .map_err(|e| format!("{} config: {}", component, e))
+}
+
+// Change one value in place, keeping the rest of the file (comments included)
+// exactly as it was
+pub async fn set_value(component: String, key_path: String, value: serde_json::Value) -> Result<(), String> {
+    let format = registry::find(&component)?.format()?;
+    let config = get_config(component.clone()).await?;
+
+    let updated = format::set(format, &config, &key_path, &value)
+        .map_err(|e| format!("{} config: {}", component, e))?;
+    apply_config(component, updated, false).await
+}
+
+// Every registered component, built-in or user-defined
+pub fn components() -> Vec<Component> {
+    registry::all()
+}
+
+// The components, plus any user component files that couldn't be used
+pub async fn list_components() -> Result<Registry, String> {
+    Ok(registry::load())
+}
+
+// Components with an active config file on this machine
+pub fn installed_components() -> Vec<String> {
+    registry::all()
+        .into_iter()
+        .filter(|component| component.resolve().is_ok_and(|resolution| resolution.active.is_some()))
+        .map(|component| component.name)
+        .collect()
+}
+
+// Config entries under $XDG_CONFIG_HOME that snapshots copy, by their name there
+pub fn snapshot_dirs() -> Vec<(String, PathBuf)> {
+    let mut found: Vec<(String, PathBuf)> = registry::all()
+        .iter()
+        .filter_map(|component| component.snapshot_dir())
+        .collect();
+    found.sort();
+    found.dedup();
+    found
+}
+
+pub fn get_component_config_path(component: &str) -> Result<PathBuf, String> {
+    registry::find(component)?.config_path()
 }
@@ -0,0 +1,471 @@
+// NixDeck 2133 - Rice Component Registry
+//
+// Everything NixDeck knows about a ricing app: where its config lives, how to
+// parse and check it, how to reload it and whether snapshots include it. The
+// built-ins below can be overridden, and new apps added, with one TOML file
+// per component in ~/.nixdeck/components/, written like a [[component]] entry
+// without the header. A file's name stands in for a missing `name`. Paths go
+// through crate::paths, so `~`, `$XDG_CONFIG_HOME` and friends work in them.
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::SystemTime;
+
+use super::format::Format;
+use super::include::Syntax;
+use crate::paths::{self, Resolution};
+
+// Config candidates are in each tool's own lookup order
+const BUILTINS: &str = r#"
+[[component]]
+name = "waybar"
+config = [
+    "$XDG_CONFIG_HOME/waybar/config", "$XDG_CONFIG_HOME/waybar/config.jsonc",
+    "~/.config/waybar/config", "~/.config/waybar/config.jsonc",
+    "~/waybar/config", "~/waybar/config.jsonc",
+    "/etc/xdg/waybar/config", "/etc/xdg/waybar/config.jsonc",
+]
+files = ["$XDG_CONFIG_HOME/waybar/*.css"]
+format = "json"
+includes = "waybar"
+reload = { signal = "USR2" }
+
+[[component]]
+name = "polybar"
+config = [
+    "$XDG_CONFIG_HOME/polybar/config", "$XDG_CONFIG_HOME/polybar/config.ini",
+    "~/.config/polybar/config", "~/.config/polybar/config.ini",
+    "$XDG_CONFIG_DIRS/polybar/config.ini", "/etc/polybar/config.ini",
+]
+files = ["$XDG_CONFIG_HOME/polybar/*.sh"]
+format = "ini"
+includes = "polybar"
+reload = { signal = "USR1" }
+
+[[component]]
+name = "eww"
+config = ["$XDG_CONFIG_HOME/eww/eww.yuck"]
+files = ["$XDG_CONFIG_HOME/eww/*.scss"]
+includes = "eww"
+reload = { command = ["eww", "reload"] }
+
+[[component]]
+name = "conky"
+config = ["$XDG_CONFIG_HOME/conky/conky.conf", "~/.conkyrc", "/etc/conky/conky.conf"]
+reload = { signal = "USR1" }
+
+[[component]]
+name = "kitty"
+config = [
+    "$KITTY_CONFIG_DIRECTORY/kitty.conf",
+    "$XDG_CONFIG_HOME/kitty/kitty.conf", "~/.config/kitty/kitty.conf",
+    "$XDG_CONFIG_DIRS/kitty/kitty.conf",
+]
+format = "kitty"
+includes = "kitty"
+check = [
+    "kitty", "+runpy",
+    "import sys; from kitty.config import load_config; bad = []; load_config(sys.argv[1], accumulate_bad_lines=bad); [print(f'line {b.number}: {b.exception}') for b in bad]",
+    "{file}",
+]
+reload = { signal = "USR1" }
+
+# alacritty 0.13 moved to TOML; a YAML config is only read when no TOML one exists
+[[component]]
+name = "alacritty"
+config = [
+    "$XDG_CONFIG_HOME/alacritty/alacritty.toml", "$XDG_CONFIG_DIRS/alacritty/alacritty.toml",
+    "$XDG_CONFIG_HOME/alacritty.toml", "$XDG_CONFIG_DIRS/alacritty.toml",
+    "~/.config/alacritty/alacritty.toml", "~/.alacritty.toml",
+    "/etc/alacritty/alacritty.toml",
+    "$XDG_CONFIG_HOME/alacritty/alacritty.yml", "$XDG_CONFIG_DIRS/alacritty/alacritty.yml",
+    "$XDG_CONFIG_HOME/alacritty.yml", "$XDG_CONFIG_DIRS/alacritty.yml",
+    "~/.config/alacritty/alacritty.yml", "~/.alacritty.yml",
+]
+reload = { manual = "alacritty reloads its config itself while live_config_reload is on" }
+
+[[component]]
+name = "picom"
+config = [
+    "$XDG_CONFIG_HOME/picom.conf", "$XDG_CONFIG_HOME/picom/picom.conf", "~/.picom.conf",
+    "$XDG_CONFIG_DIRS/picom.conf", "$XDG_CONFIG_DIRS/picom/picom.conf",
+]
+format = "libconfig"
+check = ["picom", "--config", "{file}", "--diagnostics"]
+reload = "respawn"
+
+[[component]]
+name = "dunst"
+config = ["$XDG_CONFIG_HOME/dunst/dunstrc", "$XDG_CONFIG_DIRS/dunst/dunstrc", "/etc/dunst/dunstrc"]
+files = ["$XDG_CONFIG_HOME/dunst/dunstrc.d/*.conf"]
+format = "ini"
+reload = { command = ["dunstctl", "reload"] }
+
+[[component]]
+name = "rofi"
+config = ["$XDG_CONFIG_HOME/rofi/config.rasi", "$XDG_CONFIG_DIRS/rofi/config.rasi", "/etc/rofi.rasi"]
+files = ["$XDG_CONFIG_HOME/rofi/*.rasi"]
+format = "rasi"
+includes = "rofi"
+check = ["rofi", "-config", "{file}", "-dump-config"]
+reload = { manual = "rofi reads its config each time it is launched" }
+
+[[component]]
+name = "gtk-3.0"
+config = ["$XDG_CONFIG_HOME/gtk-3.0/settings.ini", "$XDG_CONFIG_DIRS/gtk-3.0/settings.ini", "/etc/gtk-3.0/settings.ini"]
+files = ["$XDG_CONFIG_HOME/gtk-3.0/gtk.css"]
+format = "ini"
+reload = { manual = "GTK applications pick up theme changes when they are restarted" }
+
+[[component]]
+name = "gtk-4.0"
+config = ["$XDG_CONFIG_HOME/gtk-4.0/settings.ini", "$XDG_CONFIG_DIRS/gtk-4.0/settings.ini", "/etc/gtk-4.0/settings.ini"]
+files = ["$XDG_CONFIG_HOME/gtk-4.0/gtk.css"]
+format = "ini"
+reload = { manual = "GTK applications pick up theme changes when they are restarted" }
+"#;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Component {
+    #[serde(default)]
+    pub name: String,
+    // Where the main config file may be, in the tool's own lookup order; the
+    // first that exists is the one it reads
+    pub config: Vec<String>,
+    // Other files that belong to the rice; `*` and `?` work in the file name
+    #[serde(default)]
+    pub files: Vec<String>,
+    // Parser for value edits and syntax checks; by file extension when unset
+    #[serde(default)]
+    pub format: Option<String>,
+    // Include directive syntax of the config, e.g. "kitty" for `include x.conf`;
+    // stylesheet @imports are followed either way
+    #[serde(default)]
+    pub includes: Option<String>,
+    // Check-mode command run on a temporary copy of a new config, `{file}` being its path.
+    // Pass it as an argument of its own rather than inside a script, where a
+    // quote in the path would end the string.
+    #[serde(default)]
+    pub check: Vec<String>,
+    #[serde(default)]
+    pub reload: Reload,
+    // Process name for reloading, when it isn't `name`
+    #[serde(default)]
+    pub process: Option<String>,
+    // Whether containers and safety snapshots include its config directory
+    #[serde(default = "default_snapshot")]
+    pub snapshot: bool,
+    // "builtin", or the file the descriptor was read from
+    #[serde(default, skip_deserializing)]
+    pub source: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Reload {
+    // Send this signal, e.g. "USR2", to every running instance
+    Signal(String),
+    // Run the component's own reload command
+    Command(Vec<String>),
+    // Stop the running instances and start one again with the same arguments
+    Respawn,
+    // Nothing to do from outside; the note says why
+    Manual(String),
+}
+
+impl Default for Reload {
+    fn default() -> Self {
+        Reload::Manual("Restart it to pick up the new config".to_string())
+    }
+}
+
+fn default_snapshot() -> bool {
+    true
+}
+
+// Every usable component, and the user files that had to be skipped
+#[derive(Debug, Clone, Serialize)]
+pub struct Registry {
+    pub components: Vec<Component>,
+    pub problems: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct Builtins {
+    component: Vec<Component>,
+}
+
+impl Component {
+    // Which candidate the tool reads now, and which one a new config goes to
+    pub fn resolve(&self) -> Result<Resolution, String> {
+        paths::resolve(&self.config).map_err(|e| format!("{}: {}", self.name, e))
+    }
+
+    // The config file NixDeck writes. It is the active one unless that is a
+    // system-wide default, which a new user config then shadows.
+    pub fn config_path(&self) -> Result<PathBuf, String> {
+        Ok(self.resolve()?.target)
+    }
+
+    // Files from `files` that exist, minus the main config
+    pub fn extra_files(&self) -> Vec<PathBuf> {
+        let config = self.config_path().ok();
+        let mut files: Vec<PathBuf> = self
+            .files
+            .iter()
+            .flat_map(|pattern| expand_glob(pattern))
+            .filter(|path| Some(path) != config.as_ref())
+            .collect();
+        files.sort();
+        files.dedup();
+        files
+    }
+
+    pub fn format(&self) -> Result<Format, String> {
+        let resolution = self.resolve()?;
+        let path = resolution.active.unwrap_or(resolution.target);
+        let format = match &self.format {
+            Some(name) => Format::from_name(name),
+            None => path.extension().and_then(|ext| ext.to_str()).and_then(Format::from_extension),
+        };
+        format.ok_or_else(|| format!("Editing single values is not supported for {} configs", self.name))
+    }
+
+    pub fn process_name(&self) -> &str {
+        self.process.as_deref().unwrap_or(&self.name)
+    }
+
+    // The entry under $XDG_CONFIG_HOME that snapshots copy, by its name there:
+    // the config's directory, or the file itself for one like picom.conf.
+    // Configs kept elsewhere, like ~/.conkyrc, aren't snapshotted.
+    pub fn snapshot_dir(&self) -> Option<(String, PathBuf)> {
+        if !self.snapshot {
+            return None;
+        }
+        let path = self.config_path().ok()?;
+        let relative = paths::relative_to_config_home(&path)?;
+        let name = relative.components().next()?.as_os_str().to_str()?.to_string();
+        Some((name.clone(), paths::config_home().ok()?.join(name)))
+    }
+
+    fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        if self.name.is_empty()
+            || self.name.starts_with('.')
+            || !self.name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        {
+            problems.push(format!("invalid name '{}'", self.name));
+        }
+        if self.config.is_empty() {
+            problems.push("no config path".to_string());
+        }
+        if let Some(format) = &self.format {
+            if Format::from_name(format).is_none() {
+                problems.push(format!("unknown format '{}'", format));
+            }
+        }
+        if let Some(includes) = &self.includes {
+            if Syntax::from_name(includes).is_none() {
+                problems.push(format!("unknown include syntax '{}'", includes));
+            }
+        }
+        if !self.check.is_empty() && !self.check.iter().any(|arg| arg.contains("{file}")) {
+            problems.push("check command never mentions {file}".to_string());
+        }
+        match &self.reload {
+            Reload::Signal(signal) if signal.is_empty() || !signal.chars().all(|c| c.is_ascii_alphanumeric()) => {
+                problems.push(format!("invalid reload signal '{}'", signal));
+            }
+            Reload::Command(argv) if argv.is_empty() => problems.push("empty reload command".to_string()),
+            _ => {}
+        }
+
+        problems
+    }
+}
+
+pub fn all() -> Vec<Component> {
+    load().components
+}
+
+pub fn find(name: &str) -> Result<Component, String> {
+    all()
+        .into_iter()
+        .find(|component| component.name == name)
+        .ok_or_else(|| format!("Unknown component: {}", name))
+}
+
+// Built-ins, then user components; a user file with a built-in's name replaces it.
+// Nearly every rice command looks a component up, so the parsed registry is
+// kept until the user component directory or a file in it changes.
+pub fn load() -> Registry {
+    static CACHE: OnceLock<Mutex<Option<Cached>>> = OnceLock::new();
+    let stamps = user_stamps();
+
+    let mut cache = CACHE
+        .get_or_init(|| Mutex::new(None))
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some(cached) = cache.as_ref().filter(|cached| cached.stamps == stamps) {
+        return cached.registry.clone();
+    }
+
+    let registry = parse();
+    *cache = Some(Cached { stamps, registry: registry.clone() });
+    registry
+}
+
+struct Cached {
+    stamps: Vec<Stamp>,
+    registry: Registry,
+}
+
+// A path and its mtime, None when it doesn't exist
+type Stamp = (PathBuf, Option<SystemTime>);
+
+// The directory's mtime catches files added, removed or renamed; each file's
+// catches one edited in place
+fn user_stamps() -> Vec<Stamp> {
+    let stamp = |path: PathBuf| {
+        let modified = fs::metadata(&path).and_then(|metadata| metadata.modified()).ok();
+        (path, modified)
+    };
+
+    let mut stamps = vec![stamp(get_components_dir())];
+    stamps.extend(user_files().into_iter().map(stamp));
+    stamps
+}
+
+fn parse() -> Registry {
+    let mut components = toml::from_str::<Builtins>(BUILTINS)
+        .expect("built-in component registry is valid TOML")
+        .component;
+    for component in &mut components {
+        component.source = "builtin".to_string();
+    }
+
+    let mut problems = Vec::new();
+    let mut user: Vec<Component> = Vec::new();
+    for path in user_files() {
+        match read_user_component(&path) {
+            Ok(component) => user.push(component),
+            Err(e) => problems.push(format!("{}: {}", path.display(), e)),
+        }
+    }
+    user.sort_by(|a, b| a.name.cmp(&b.name));
+
+    for component in user {
+        match components.iter_mut().find(|existing| existing.name == component.name) {
+            Some(existing) => *existing = component,
+            None => components.push(component),
+        }
+    }
+
+    Registry { components, problems }
+}
+
+fn read_user_component(path: &Path) -> Result<Component, String> {
+    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let mut component: Component =
+        toml::from_str(&content).map_err(|e| e.message().trim().replace('\n', ": "))?;
+
+    if component.name.is_empty() {
+        component.name = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or_default().to_string();
+    }
+    component.source = path.display().to_string();
+
+    let problems = component.problems();
+    if !problems.is_empty() {
+        return Err(problems.join("; "));
+    }
+    Ok(component)
+}
+
+fn user_files() -> Vec<PathBuf> {
+    let entries = match fs::read_dir(get_components_dir()) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut files: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().and_then(|ext| ext.to_str()) == Some("toml"))
+        .collect();
+    files.sort();
+    files
+}
+
+// Existing files matching `pattern`; wildcards only in the last segment
+fn expand_glob(pattern: &str) -> Vec<PathBuf> {
+    paths::expand(pattern)
+        .unwrap_or_default()
+        .into_iter()
+        .flat_map(matching_files)
+        .collect()
+}
+
+// Existing files at `path`, whose file name may hold wildcards
+pub(super) fn matching_files(path: PathBuf) -> Vec<PathBuf> {
+    let name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+
+    if !name.contains(['*', '?']) {
+        return if path.is_file() { vec![path] } else { Vec::new() };
+    }
+
+    let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+    let mut matches: Vec<PathBuf> = fs::read_dir(&dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|file| file.is_file())
+                .filter(|file| {
+                    file.file_name()
+                        .and_then(|file_name| file_name.to_str())
+                        .is_some_and(|file_name| wildcard_match(name, file_name))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    matches.sort();
+    matches
+}
+
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Where the last `*` was, and how much text it has swallowed so far
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((star_p, star_t)) => {
+                    p = star_p + 1;
+                    t = star_t + 1;
+                    star = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+fn get_components_dir() -> PathBuf {
+    let home = dirs::home_dir().expect("Could not find home directory");
+    home.join(".nixdeck").join("components")
+}
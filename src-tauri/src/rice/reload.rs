@@ -0,0 +1,206 @@
+// NixDeck 2133 - Rice Component Reload
+//
+// Each component has one way of picking up a new config, its `reload` in the
+// registry. Running instances are found by name through /proc, and after the
+// reload the same lookup decides whether the component came back.
+use serde::Serialize;
+use std::fs;
+use std::process::Stdio;
+use std::time::Duration;
+
+use super::registry::{Component, Reload};
+
+// How long a component gets to settle before its health is judged
+const SETTLE: Duration = Duration::from_millis(1500);
+// How long old instances get to exit before a respawn gives up on them
+const EXIT_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReloadReport {
+    pub component: String,
+    pub strategy: String,
+    // Whether the component is running on the new config, as far as can be told
+    pub healthy: bool,
+    // Instances running once the reload settled
+    pub pids: Vec<u32>,
+    pub message: String,
+}
+
+pub async fn reload(component: &Component) -> Result<ReloadReport, String> {
+    let process = component.process_name();
+    let before = find_processes(process);
+
+    let (healthy, message) = match &component.reload {
+        Reload::Manual(note) => (true, note.to_string()),
+        _ if before.is_empty() && !matches!(component.reload, Reload::Respawn) => {
+            (false, format!("{} isn't running", process))
+        }
+        Reload::Signal(signal) => {
+            send_signal(signal, &before).await?;
+            tokio::time::sleep(SETTLE).await;
+
+            let survivors = before.iter().filter(|&&pid| is_alive(pid)).count();
+            if survivors == before.len() {
+                (true, format!("Sent SIG{} to {} instance(s)", signal, before.len()))
+            } else {
+                (false, format!("{} of {} instance(s) exited after SIG{}", before.len() - survivors, before.len(), signal))
+            }
+        }
+        Reload::Command(argv) => {
+            let output = tokio::process::Command::new(&argv[0])
+                .args(&argv[1..])
+                .stdin(Stdio::null())
+                .output()
+                .await
+                .map_err(|e| format!("Failed to run {}: {}", argv.join(" "), e))?;
+            tokio::time::sleep(SETTLE).await;
+
+            let running = !find_processes(process).is_empty();
+            let output_text = format!(
+                "{}{}",
+                String::from_utf8_lossy(&output.stdout),
+                String::from_utf8_lossy(&output.stderr)
+            );
+            match (output.status.success(), running) {
+                (true, true) => (true, format!("Ran {}", argv.join(" "))),
+                (true, false) => (false, format!("{} exited after {}", process, argv.join(" "))),
+                (false, _) => (false, format!("{} failed ({}): {}", argv.join(" "), output.status, output_text.trim())),
+            }
+        }
+        Reload::Respawn => respawn(process, &before).await?,
+    };
+
+    let strategy = match component.reload {
+        Reload::Signal(_) => "signal",
+        Reload::Command(_) => "command",
+        Reload::Respawn => "respawn",
+        Reload::Manual(_) => "manual",
+    };
+    Ok(ReloadReport {
+        component: component.name.clone(),
+        strategy: strategy.to_string(),
+        healthy,
+        pids: find_processes(process),
+        message,
+    })
+}
+
+// Stop the running instances and start the first one's command line again,
+// detached so it outlives NixDeck. A component that wasn't running is started
+// bare.
+async fn respawn(process: &str, before: &[u32]) -> Result<(bool, String), String> {
+    let argv = before
+        .first()
+        .and_then(|&pid| command_line(pid))
+        .unwrap_or_else(|| vec![process.to_string()]);
+
+    if !before.is_empty() {
+        send_signal("TERM", before).await?;
+
+        let deadline = tokio::time::Instant::now() + EXIT_TIMEOUT;
+        while before.iter().any(|&pid| is_alive(pid)) {
+            if tokio::time::Instant::now() >= deadline {
+                return Ok((false, format!("{} did not exit after SIGTERM", process)));
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    }
+
+    let mut child = tokio::process::Command::new(&argv[0])
+        .args(&argv[1..])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .process_group(0)
+        .spawn()
+        .map_err(|e| format!("Failed to start {}: {}", argv.join(" "), e))?;
+    let started = child.id();
+    // Reap it whenever it exits; forking tools like `picom -b` exit right away
+    tokio::spawn(async move {
+        let _ = child.wait().await;
+    });
+
+    tokio::time::sleep(SETTLE).await;
+    let after = find_processes(process);
+    if after.is_empty() {
+        return Ok((false, format!("{} exited right after starting; check its config", argv.join(" "))));
+    }
+
+    let detail = match started {
+        Some(pid) if after.contains(&pid) => format!("pid {}", pid),
+        _ => format!("pid {}", after[0]),
+    };
+    Ok((true, format!("Restarted {} ({})", argv.join(" "), detail)))
+}
+
+async fn send_signal(signal: &str, pids: &[u32]) -> Result<(), String> {
+    let output = tokio::process::Command::new("kill")
+        .arg(format!("-{}", signal))
+        .args(pids.iter().map(u32::to_string))
+        .output()
+        .await
+        .map_err(|e| format!("Failed to send SIG{}: {}", signal, e))?;
+
+    if !output.status.success() {
+        return Err(format!("Failed to send SIG{}: {}", signal, String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(())
+}
+
+// This user's processes named `name`, by their comm or the first word of
+// their command line
+fn find_processes(name: &str) -> Vec<u32> {
+    let entries = match fs::read_dir("/proc") {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+    let own_uid = uid("self");
+    let own_pid = std::process::id();
+
+    let mut pids: Vec<u32> = entries
+        .flatten()
+        .filter_map(|entry| entry.file_name().to_str()?.parse::<u32>().ok())
+        .filter(|&pid| pid != own_pid && is_alive(pid))
+        .filter(|&pid| own_uid.is_some() && uid(&pid.to_string()) == own_uid)
+        .filter(|&pid| {
+            // comm is cut at 15 bytes, so the command line settles long names
+            let comm = fs::read_to_string(format!("/proc/{}/comm", pid)).unwrap_or_default();
+            comm.trim_end() == name
+                || command_line(pid)
+                    .and_then(|argv| argv.into_iter().next())
+                    .is_some_and(|program| program.rsplit('/').next() == Some(name))
+        })
+        .collect();
+    pids.sort_unstable();
+    pids
+}
+
+fn command_line(pid: u32) -> Option<Vec<String>> {
+    let raw = fs::read(format!("/proc/{}/cmdline", pid)).ok()?;
+    let argv: Vec<String> = raw
+        .split(|&b| b == 0)
+        .filter(|arg| !arg.is_empty())
+        .map(|arg| String::from_utf8_lossy(arg).into_owned())
+        .collect();
+    (!argv.is_empty()).then_some(argv)
+}
+
+// Running, and not a zombie waiting to be reaped
+fn is_alive(pid: u32) -> bool {
+    let stat = match fs::read_to_string(format!("/proc/{}/stat", pid)) {
+        Ok(stat) => stat,
+        Err(_) => return false,
+    };
+    // The state follows the parenthesised name, which may itself hold spaces
+    let state = stat.rsplit_once(')').and_then(|(_, rest)| rest.trim_start().chars().next());
+    !matches!(state, Some('Z') | Some('X') | None)
+}
+
+fn uid(pid: &str) -> Option<u32> {
+    let status = fs::read_to_string(format!("/proc/{}/status", pid)).ok()?;
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("Uid:"))
+        .and_then(|ids| ids.split_whitespace().next())
+        .and_then(|id| id.parse().ok())
+}
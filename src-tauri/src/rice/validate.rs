@@ -0,0 +1,187 @@
+// NixDeck 2133 - Pre-apply Config Validation
+//
+// A config is checked before it is written: first by its format's parser, then,
+// where the component's binary has a check mode, by the tool itself running
+// against a temporary copy.
+use regex::Regex;
+use serde::Serialize;
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+
+use super::format::{self, Format};
+use super::registry::Component;
+
+const CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Diagnostic {
+    // 1-based, when the parser or tool said where
+    pub line: Option<usize>,
+    pub severity: Severity,
+    // "syntax", or the binary that reported it
+    pub source: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Validation {
+    pub valid: bool,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Validation {
+    // The errors, one per line, for refusing a write
+    pub fn summary(&self) -> String {
+        self.diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == Severity::Error)
+            .map(|diagnostic| match diagnostic.line {
+                Some(line) => format!("line {} ({}): {}", line, diagnostic.source, diagnostic.message),
+                None => format!("({}): {}", diagnostic.source, diagnostic.message),
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+pub async fn validate(component: &Component, config: &str) -> Validation {
+    // eww and conky have no parser here; the tool check is all they get
+    let mut diagnostics = syntax_check(component.format().ok(), config);
+
+    // The tool would only repeat a syntax error, less precisely
+    if diagnostics.is_empty() {
+        if let Ok(config_path) = component.config_path() {
+            diagnostics.extend(tool_check(component, &config_path, config).await);
+        }
+    }
+
+    Validation {
+        valid: diagnostics.iter().all(|diagnostic| diagnostic.severity != Severity::Error),
+        diagnostics,
+    }
+}
+
+// Check a file the main config includes. Tools can't check one on its own, so
+// only the parser runs: the component's when the file shares the main config's
+// extension, else the one its extension names.
+pub fn validate_include(component: &Component, path: &Path, config: &str) -> Validation {
+    let main = component.config_path().ok();
+    let format = if main.as_ref().and_then(|main| main.extension()) == path.extension() {
+        component.format().ok()
+    } else {
+        path.extension().and_then(|ext| ext.to_str()).and_then(Format::from_extension)
+    };
+
+    let diagnostics = syntax_check(format, config);
+    Validation {
+        valid: diagnostics.is_empty(),
+        diagnostics,
+    }
+}
+
+fn syntax_check(format: Option<Format>, config: &str) -> Vec<Diagnostic> {
+    let Some(format) = format else {
+        return Vec::new();
+    };
+    format::check(format, config)
+        .into_iter()
+        .map(|error| Diagnostic {
+            line: Some(error.line),
+            severity: Severity::Error,
+            source: "syntax".to_string(),
+            message: error.message,
+        })
+        .collect()
+}
+
+// Run the component's own check command (its `check` in the registry) on a
+// temporary copy, so the live file is never touched and a running instance
+// never sees the draft. Components whose binary has no check mode, like
+// dunst, polybar and waybar, have none.
+async fn tool_check(component: &Component, config_path: &Path, config: &str) -> Vec<Diagnostic> {
+    let Some((tool, _)) = component.check.split_first() else {
+        return Vec::new();
+    };
+    let tool = tool.as_str();
+
+    let file_name = config_path.file_name().unwrap_or_else(|| std::ffi::OsStr::new(&component.name));
+    let warning = |message: String| {
+        vec![Diagnostic { line: None, severity: Severity::Warning, source: tool.to_string(), message }]
+    };
+
+    let dir = match crate::atomic::private_dir(&format!("check-{}", component.name)) {
+        Ok(dir) => dir,
+        Err(e) => return warning(format!("Could not write a temporary copy to check: {}", e)),
+    };
+    let file = dir.join(file_name);
+    let file_arg = file.display().to_string();
+    let args: Vec<String> = component.check[1..].iter().map(|arg| arg.replace("{file}", &file_arg)).collect();
+
+    if let Err(e) = std::fs::write(&file, config) {
+        let _ = std::fs::remove_dir_all(&dir);
+        return warning(format!("Could not write a temporary copy to check: {}", e));
+    }
+
+    let run = tokio::process::Command::new(tool)
+        .args(&args)
+        .stdin(Stdio::null())
+        .kill_on_drop(true)
+        .output();
+    let result = tokio::time::timeout(CHECK_TIMEOUT, run).await;
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let output = match result {
+        Ok(Ok(output)) => output,
+        // Not installed: nothing to check with
+        Ok(Err(e)) if e.kind() == std::io::ErrorKind::NotFound => return Vec::new(),
+        Ok(Err(e)) => return warning(format!("Failed to run check: {}", e)),
+        Err(_) => return warning(format!("Check timed out after {}s", CHECK_TIMEOUT.as_secs())),
+    };
+
+    let text = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let located = located_lines(&text, &file, config_path, tool);
+
+    // A failure that doesn't point into the file is more likely the tool
+    // (no display, too old) than the config, so it is reported but not fatal
+    if located.is_empty() && !output.status.success() {
+        let first = text.lines().map(str::trim).find(|line| !line.is_empty()).unwrap_or("no output");
+        return warning(format!("Check was inconclusive ({}): {}", output.status, first));
+    }
+
+    located
+}
+
+// Output lines that name a line of the config, e.g. `line 12: ...` or
+// `/tmp/.../picom.conf:12: ...`
+fn located_lines(text: &str, file: &Path, config_path: &Path, tool: &str) -> Vec<Diagnostic> {
+    let file = file.display().to_string();
+    let pattern = format!(r"(?i)(?:\bline\s*:?\s*|{}:)(\d+)", regex::escape(&file));
+    let line_number = match Regex::new(&pattern) {
+        Ok(regex) => regex,
+        Err(_) => return Vec::new(),
+    };
+
+    text.lines()
+        .filter_map(|line| {
+            let number = line_number.captures(line)?.get(1)?.as_str().parse().ok()?;
+            Some(Diagnostic {
+                line: Some(number),
+                severity: Severity::Error,
+                source: tool.to_string(),
+                message: line.trim().replace(&file, &config_path.display().to_string()),
+            })
+        })
+        .collect()
+}
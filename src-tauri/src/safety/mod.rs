@@ -21,31 +21,18 @@ pub async fn create_snapshot(name: String) -> Result<(), String> {
     fs::create_dir_all(&snapshot_dir)
         .map_err(|e| format!("Failed to create snapshot directory: {}", e))?;
     
-    // Snapshot critical configs
-    let home = dirs::home_dir().ok_or("Could not find home directory")?;
-    let config_dir = home.join(".config");
-    
-    let critical_components = vec![
-        "waybar",
-        "polybar",
-        "eww",
-        "kitty",
-        "alacritty",
-        "picom",
-    ];
-    
+    // Snapshot every registered component's config directory
     let mut snapshotted_files = Vec::new();
     
-    for component in critical_components {
-        let src = config_dir.join(component);
+    for (component, src) in crate::rice::snapshot_dirs() {
         if src.exists() {
-            let dst = snapshot_dir.join(component);
+            let dst = snapshot_dir.join(&component);
             if let Some(parent) = dst.parent() {
                 fs::create_dir_all(parent)
                     .map_err(|e| format!("Failed to create directory: {}", e))?;
             }
             copy_recursive(&src, &dst)?;
-            snapshotted_files.push(component.to_string());
+            snapshotted_files.push(component);
         }
     }
     
@@ -107,8 +94,7 @@ pub async fn restore_snapshot(name: String) -> Result<(), String> {
         .map_err(|e| format!("Failed to parse snapshot metadata: {}", e))?;
     
     // Restore files
-    let home = dirs::home_dir().ok_or("Could not find home directory")?;
-    let config_dir = home.join(".config");
+    let config_dir = crate::paths::config_home()?;
     
     for component in metadata.files {
         let src = snapshot_dir.join(&component);
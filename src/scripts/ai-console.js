@@ -2,6 +2,7 @@
 // Handles AI chat interface and OpenRouter integration
 
 import { invoke } from '@tauri-apps/api/tauri';
+import { listen } from '@tauri-apps/api/event';
 import { logOperation } from './main.js';
 
 // ============================================================================
@@ -12,6 +13,9 @@ const AIConsoleState = {
     messages: [],
     activeLoadout: 'DEFAULT',
     isProcessing: false,
+    activeRequestId: null,
+    activeConversation: null,
+    pendingApproval: null,
     operations: []
 };
 
@@ -29,7 +33,7 @@ function initializeAIConsole() {
     const changeLoadoutBtn = document.getElementById('change-loadout-btn');
     
     if (aiInput) {
-        aiInput.addEventListener('keypress', handleKeyPress);
+        aiInput.addEventListener('keydown', handleKeyPress);
     }
     
     if (sendBtn) {
@@ -40,6 +44,17 @@ function initializeAIConsole() {
         changeLoadoutBtn.addEventListener('click', openLoadoutSelector);
     }
     
+    // Mutating tool calls wait on the user before the backend runs them
+    listen('ai://tool-approval', handleToolApproval);
+    listen('ai://tool-approval-cancelled', handleToolApprovalCancelled);
+    
+    // Operations persist in the backend and report progress as they run
+    listen('operation://update', handleOperationUpdate);
+    refreshOperations();
+    
+    // Secrets replaced with placeholders before a request left the machine
+    listen('ai://redactions', handleRedactions);
+    
     console.log('[AI Console] Initialized');
 }
 
@@ -48,6 +63,11 @@ function initializeAIConsole() {
 // ============================================================================
 
 function handleKeyPress(event) {
+    if (event.key === 'Escape') {
+        cancelMessage();
+        return;
+    }
+    
     if (event.key === 'Enter' && !event.shiftKey) {
         event.preventDefault();
         sendMessage();
@@ -69,33 +89,116 @@ async function sendMessage() {
     addMessageToChat('USER', message, 'user-message');
     
     // Set processing state
+    const requestId = `req-${Date.now()}`;
     AIConsoleState.isProcessing = true;
+    AIConsoleState.activeRequestId = requestId;
     updateSendButton(true);
     
+    // Stream the reply into a single message as chunks arrive
+    const messageIndex = AIConsoleState.messages.length;
+    const contentElement = addMessageToChat('AI', '', 'ai-message');
+    const unlisten = await listen('ai://chunk', (event) => {
+        if (event.payload.request_id === requestId && contentElement) {
+            contentElement.textContent += event.payload.delta;
+            contentElement.closest('#chat-messages')?.scrollTo(0, Number.MAX_SAFE_INTEGER);
+        }
+    });
+    
     try {
         // Send to backend
         const response = await invoke('send_ai_message', {
             message,
-            loadout: AIConsoleState.activeLoadout
+            loadout: AIConsoleState.activeLoadout,
+            requestId,
+            conversation: AIConsoleState.activeConversation
         });
         
-        // Add AI response to chat
-        addMessageToChat('AI', response, 'ai-message');
+        if (contentElement) {
+            contentElement.textContent = response.content;
+        }
+        AIConsoleState.messages[messageIndex].content = response.content;
         
-        // Check if response contains operation commands
-        parseOperations(response);
+        // The model may have queued operations through its tools
+        await refreshOperations();
         
-        logOperation('AI message sent and received', 'success');
+        logOperation(`AI message sent and received (${response.model})`, 'success');
     } catch (error) {
         console.error('[AI Console] Error:', error);
         addMessageToChat('SYSTEM', `Error: ${error}`, 'system-message');
         logOperation(`AI error: ${error}`, 'error');
     } finally {
+        unlisten();
         AIConsoleState.isProcessing = false;
+        AIConsoleState.activeRequestId = null;
         updateSendButton(false);
     }
 }
 
+function handleRedactions(event) {
+    const { redactions } = event.payload;
+    const summary = redactions
+        .map(r => `${r.placeholder} (${r.role})`)
+        .join('\n');
+    
+    addMessageToChat('SYSTEM', `Redacted ${redactions.length} secret(s) before sending:\n${summary}`, 'system-message');
+    logOperation(`Redacted ${redactions.length} secret(s) from AI request`, 'warning');
+}
+
+// A popup rather than confirm(), so the prompt can be taken down when the
+// request it belongs to is cancelled or times out
+function handleToolApproval(event) {
+    const { approval_id: approvalId, tool, arguments: args } = event.payload;
+    AIConsoleState.pendingApproval = { approvalId, tool };
+    
+    const answer = (approved) => async () => {
+        AIConsoleState.pendingApproval = null;
+        window.NixDeck.closePopup();
+        
+        addMessageToChat('SYSTEM', `${approved ? 'Approved' : 'Declined'} tool call: ${tool}`, 'system-message');
+        logOperation(`Tool call ${tool} ${approved ? 'approved' : 'declined'}`, approved ? 'info' : 'warning');
+        
+        try {
+            await invoke('respond_ai_tool_call', { approvalId, approved });
+        } catch (error) {
+            console.error('[AI Console] Tool approval failed:', error);
+        }
+    };
+    
+    window.NixDeck.showPopup(
+        'Tool Approval',
+        `
+            <div class="info-item">AI requests to run <strong>${escapeHtml(tool)}</strong>:</div>
+            <pre style="background: var(--color-bg-tertiary); padding: 8px; margin-top: 8px; max-height: 300px; overflow-y: auto;">${escapeHtml(JSON.stringify(args, null, 2))}</pre>
+            <div class="info-item" style="margin-top: 16px;">Allow this action?</div>
+        `,
+        [
+            { label: '[DECLINE]', action: answer(false) },
+            { label: '[ALLOW]', action: answer(true), primary: true }
+        ]
+    );
+}
+
+function handleToolApprovalCancelled(event) {
+    const { approval_id: approvalId } = event.payload;
+    const pending = AIConsoleState.pendingApproval;
+    if (!pending || pending.approvalId !== approvalId) return;
+    
+    AIConsoleState.pendingApproval = null;
+    window.NixDeck.closePopup();
+    addMessageToChat('SYSTEM', `Tool call ${pending.tool} was cancelled before it was answered`, 'system-message');
+    logOperation(`Tool call ${pending.tool} cancelled`, 'warning');
+}
+
+async function cancelMessage() {
+    if (!AIConsoleState.activeRequestId) return;
+    
+    try {
+        await invoke('cancel_ai_message', { requestId: AIConsoleState.activeRequestId });
+    } catch (error) {
+        console.error('[AI Console] Cancel failed:', error);
+    }
+}
+
 function addMessageToChat(sender, content, className) {
     const messagesContainer = document.getElementById('chat-messages');
     if (!messagesContainer) return;
@@ -120,6 +223,8 @@ function addMessageToChat(sender, content, className) {
         content,
         className
     });
+    
+    return messageDiv.querySelector('.message-content');
 }
 
 function updateSendButton(isProcessing) {
@@ -138,96 +243,155 @@ function updateSendButton(isProcessing) {
 }
 
 // ============================================================================
-// OPERATIONS PARSING
+// OPERATIONS
 // ============================================================================
 
-function parseOperations(response) {
-    // Look for operation markers in AI response
-    // Format: [OPERATION:type:name]
-    const operationRegex = /\[OPERATION:(\w+):(.+?)\]/g;
-    let match;
-    
-    while ((match = operationRegex.exec(response)) !== null) {
-        const [, type, name] = match;
-        createOperation(type, name, response);
+// Operations live in the backend; the queue panel mirrors them
+async function refreshOperations() {
+    try {
+        const operations = await invoke('list_operations');
+        AIConsoleState.operations = operations;
+        
+        const operationsList = document.getElementById('operations-list');
+        if (operationsList) {
+            operationsList.innerHTML = '';
+            operations.forEach(renderOperationCard);
+        }
+        updateOperationCount();
+    } catch (error) {
+        console.error('[AI Console] Failed to list operations:', error);
     }
 }
 
-function createOperation(type, name, context) {
-    const operation = {
-        id: Date.now(),
-        type,
-        name,
-        context,
-        status: 'pending'
-    };
+function handleOperationUpdate(event) {
+    const operation = event.payload;
+    const index = AIConsoleState.operations.findIndex(op => op.id === operation.id);
     
-    AIConsoleState.operations.push(operation);
-    addOperationCard(operation);
+    if (index >= 0) {
+        AIConsoleState.operations[index] = operation;
+    } else {
+        AIConsoleState.operations.push(operation);
+    }
+    renderOperationCard(operation);
+    updateOperationCount();
     
-    logOperation(`New operation: ${type} - ${name}`, 'info');
+    if (operation.status === 'awaiting_confirmation') {
+        const step = operation.steps[operation.current_step];
+        logOperation(`Operation "${operation.title}" waiting for confirmation: ${step.description}`, 'warning');
+    } else if (operation.status === 'completed') {
+        logOperation(`Operation completed: ${operation.title}`, 'success');
+    } else if (operation.status === 'failed') {
+        logOperation(`Operation failed: ${operation.title}`, 'error');
+    }
 }
 
-function addOperationCard(operation) {
+function renderOperationCard(operation) {
     const operationsList = document.getElementById('operations-list');
     if (!operationsList) return;
     
-    const card = document.createElement('div');
-    card.className = 'operation-card';
-    card.setAttribute('data-operation-id', operation.id);
+    let card = operationsList.querySelector(`[data-operation-id="${operation.id}"]`);
+    if (!card) {
+        card = document.createElement('div');
+        card.className = 'operation-card';
+        card.setAttribute('data-operation-id', operation.id);
+        operationsList.appendChild(card);
+    }
+    
+    const total = operation.steps.length;
+    const done = Math.min(operation.current_step, total);
+    const actions = operationActions(operation.status)
+        .map(([action, label]) => `<button class="action-btn" data-action="${action}" data-id="${operation.id}">${label}</button>`)
+        .join('');
     
     card.innerHTML = `
-        <div class="operation-title">${operation.name}</div>
-        <div class="operation-status">Status: ${operation.status}</div>
-        <div class="operation-actions" style="margin-top: 8px; display: flex; gap: 4px;">
-            <button class="action-btn" data-action="preview" data-id="${operation.id}">[PREVIEW]</button>
-            <button class="action-btn" data-action="apply" data-id="${operation.id}">[APPLY]</button>
-            <button class="action-btn" data-action="cancel" data-id="${operation.id}">[CANCEL]</button>
-        </div>
+        <div class="operation-title">${escapeHtml(operation.title)}</div>
+        <div class="operation-status">Status: ${operation.status.replace('_', ' ')} (${done}/${total})</div>
+        <div class="operation-actions" style="margin-top: 8px; display: flex; gap: 4px;">${actions}</div>
     `;
     
-    // Add event listeners
     card.querySelectorAll('.action-btn').forEach(btn => {
         btn.addEventListener('click', handleOperationAction);
     });
     
-    operationsList.appendChild(card);
-    updateOperationCount();
+    const colors = {
+        completed: 'var(--color-success)',
+        failed: 'var(--color-error)',
+        aborted: 'var(--color-error)',
+        running: 'var(--color-warning)',
+        awaiting_confirmation: 'var(--color-warning)'
+    };
+    card.style.borderLeftColor = colors[operation.status] || '';
+}
+
+function operationActions(status) {
+    switch (status) {
+        case 'queued':
+            return [['preview', '[PREVIEW]'], ['start', '[START]'], ['abort', '[ABORT]']];
+        case 'running':
+            return [['preview', '[PREVIEW]'], ['abort', '[ABORT]']];
+        case 'awaiting_confirmation':
+            return [['preview', '[PREVIEW]'], ['confirm', '[CONFIRM]'], ['decline', '[DECLINE]']];
+        case 'failed':
+            return [['preview', '[PREVIEW]'], ['start', '[RETRY]'], ['abort', '[ABORT]']];
+        default:
+            return [['preview', '[PREVIEW]'], ['delete', '[REMOVE]']];
+    }
 }
 
 async function handleOperationAction(event) {
     const action = event.target.getAttribute('data-action');
-    const operationId = parseInt(event.target.getAttribute('data-id'));
+    const id = event.target.getAttribute('data-id');
     
-    const operation = AIConsoleState.operations.find(op => op.id === operationId);
+    const operation = AIConsoleState.operations.find(op => op.id === id);
     if (!operation) return;
     
-    switch (action) {
-        case 'preview':
-            previewOperation(operation);
-            break;
-        case 'apply':
-            await applyOperation(operation);
-            break;
-        case 'cancel':
-            cancelOperation(operation);
-            break;
+    try {
+        switch (action) {
+            case 'preview':
+                previewOperation(operation);
+                break;
+            case 'start':
+                await invoke('start_operation', { id });
+                break;
+            case 'confirm':
+                await invoke('confirm_operation_step', { id, approved: true });
+                break;
+            case 'decline':
+                await invoke('confirm_operation_step', { id, approved: false });
+                break;
+            case 'abort':
+                await invoke('abort_operation', { id });
+                break;
+            case 'delete':
+                await invoke('delete_operation', { id });
+                await refreshOperations();
+                break;
+        }
+    } catch (error) {
+        console.error('[AI Console] Operation action failed:', error);
+        logOperation(`Operation ${action} failed: ${error}`, 'error');
     }
 }
 
 function previewOperation(operation) {
+    const steps = operation.steps.map((step, index) => {
+        const action = step.action.kind === 'shell'
+            ? `$ ${step.action.command}`
+            : `${step.action.name} ${JSON.stringify(step.action.args || {})}`;
+        const risky = step.requires_confirmation ? ' [CONFIRM]' : '';
+        const logs = step.logs.map(log => `    ${log.message}`).join('\n');
+        return `${index + 1}. [${step.status}]${risky} ${step.description}\n   ${action}${logs ? '\n' + logs : ''}`;
+    }).join('\n\n');
+    
     window.NixDeck.showPopup(
-        `Operation Preview: ${operation.name}`,
+        `Operation: ${operation.title}`,
         `
             <div class="info-item">
-                <strong>Type:</strong> ${operation.type}
-            </div>
-            <div class="info-item">
-                <strong>Name:</strong> ${operation.name}
+                <strong>Status:</strong> ${operation.status}
             </div>
             <div class="info-item" style="margin-top: 16px;">
-                <strong>Context:</strong>
-                <pre style="background: var(--color-bg-tertiary); padding: 8px; margin-top: 8px; max-height: 300px; overflow-y: auto;">${escapeHtml(operation.context)}</pre>
+                <strong>Steps:</strong>
+                <pre style="background: var(--color-bg-tertiary); padding: 8px; margin-top: 8px; max-height: 300px; overflow-y: auto;">${escapeHtml(steps)}</pre>
             </div>
         `,
         [
@@ -236,71 +400,12 @@ function previewOperation(operation) {
     );
 }
 
-async function applyOperation(operation) {
-    try {
-        operation.status = 'applying';
-        updateOperationCard(operation);
-        
-        // TODO: Implement actual operation application based on type
-        // For now, just simulate
-        await new Promise(resolve => setTimeout(resolve, 1000));
-        
-        operation.status = 'completed';
-        updateOperationCard(operation);
-        
-        logOperation(`Applied operation: ${operation.name}`, 'success');
-        
-        // Remove after a delay
-        setTimeout(() => {
-            removeOperationCard(operation);
-        }, 3000);
-    } catch (error) {
-        operation.status = 'failed';
-        updateOperationCard(operation);
-        logOperation(`Operation failed: ${error}`, 'error');
-    }
-}
-
-function cancelOperation(operation) {
-    operation.status = 'cancelled';
-    removeOperationCard(operation);
-    logOperation(`Cancelled operation: ${operation.name}`, 'info');
-}
-
-function updateOperationCard(operation) {
-    const card = document.querySelector(`[data-operation-id="${operation.id}"]`);
-    if (!card) return;
-    
-    const statusElement = card.querySelector('.operation-status');
-    if (statusElement) {
-        statusElement.textContent = `Status: ${operation.status}`;
-    }
-    
-    // Update styling based on status
-    if (operation.status === 'completed') {
-        card.style.borderLeftColor = 'var(--color-success)';
-    } else if (operation.status === 'failed') {
-        card.style.borderLeftColor = 'var(--color-error)';
-    } else if (operation.status === 'applying') {
-        card.style.borderLeftColor = 'var(--color-warning)';
-    }
-}
-
-function removeOperationCard(operation) {
-    const card = document.querySelector(`[data-operation-id="${operation.id}"]`);
-    if (card) {
-        card.remove();
-    }
-    
-    // Remove from state
-    AIConsoleState.operations = AIConsoleState.operations.filter(op => op.id !== operation.id);
-    updateOperationCount();
-}
-
 function updateOperationCount() {
     const countElement = document.querySelector('.queue-count');
     if (countElement) {
-        countElement.textContent = AIConsoleState.operations.length;
+        const active = AIConsoleState.operations
+            .filter(op => !['completed', 'aborted'].includes(op.status));
+        countElement.textContent = active.length;
     }
 }
 
@@ -342,6 +447,7 @@ function escapeHtml(text) {
 export {
     AIConsoleState,
     addMessageToChat,
+    cancelMessage,
     setActiveLoadout,
-    createOperation
+    refreshOperations
 };
@@ -2,6 +2,7 @@
 // Core application initialization and state management
 
 import { invoke } from '@tauri-apps/api/tauri';
+import { renderDiff } from './rice-studio.js';
 
 // ============================================================================
 // STATE MANAGEMENT
@@ -418,6 +419,7 @@ function createContainerCard(containerName) {
         <div class="container-info">Desktop container snapshot</div>
         <div class="container-actions">
             <button class="container-action-btn" data-action="load" data-container="${containerName}">[LOAD]</button>
+            <button class="container-action-btn" data-action="compare" data-container="${containerName}">[COMPARE]</button>
             <button class="container-action-btn" data-action="export" data-container="${containerName}">[EXPORT]</button>
             <button class="container-action-btn" data-action="delete" data-container="${containerName}">[DELETE]</button>
         </div>
@@ -441,6 +443,23 @@ async function handleContainerAction(event) {
                 await invoke('load_container', { name: container });
                 logOperation(`Loaded container: ${container}`, 'success');
                 break;
+            case 'compare': {
+                // Compare against another container, or the live config if left empty
+                const other = prompt(`Compare "${container}" with which container? (empty = current config)`);
+                if (other === null) break;
+                const diffs = await invoke('compare_container', {
+                    name: container,
+                    other: other.trim() || null
+                });
+                showPopup(
+                    `COMPARE: ${container}`,
+                    diffs.length > 0
+                        ? diffs.map(diff => renderDiff(diff)).join('')
+                        : '<div class="info-item">No differences</div>',
+                    [{ label: '[CLOSE]', action: closePopup, primary: true }]
+                );
+                break;
+            }
             case 'export':
                 // TODO: Implement file dialog for export path
                 logOperation(`Export not yet implemented`, 'warning');
@@ -74,19 +74,31 @@ async function openLoadoutManager() {
                                 <label style="color: var(--color-text-dim); font-size: 11px;">LOADOUT NAME:</label>
                                 <input type="text" id="new-loadout-name" placeholder="my-loadout" style="width: 100%; padding: 8px; margin-top: 4px; background: var(--color-bg-tertiary); border: var(--border); color: var(--color-text-primary); font-family: var(--font-mono);">
                             </div>
+                            <div>
+                                <label style="color: var(--color-text-dim); font-size: 11px;">PROVIDER:</label>
+                                <select id="new-loadout-provider" style="width: 100%; padding: 8px; margin-top: 4px; background: var(--color-bg-tertiary); border: var(--border); color: var(--color-text-primary); font-family: var(--font-mono);">
+                                    <option value="openrouter">OpenRouter</option>
+                                    <option value="openai">OpenAI-compatible</option>
+                                    <option value="ollama">Ollama</option>
+                                </select>
+                            </div>
+                            <div>
+                                <label style="color: var(--color-text-dim); font-size: 11px;">ENDPOINT (OPTIONAL):</label>
+                                <input type="text" id="new-loadout-endpoint" placeholder="http://localhost:11434" style="width: 100%; padding: 8px; margin-top: 4px; background: var(--color-bg-tertiary); border: var(--border); color: var(--color-text-primary); font-family: var(--font-mono);">
+                            </div>
                             <div>
                                 <label style="color: var(--color-text-dim); font-size: 11px;">MODEL:</label>
                                 <select id="new-loadout-model" style="width: 100%; padding: 8px; margin-top: 4px; background: var(--color-bg-tertiary); border: var(--border); color: var(--color-text-primary); font-family: var(--font-mono);">
-                                    <option value="anthropic/claude-3.5-sonnet">Claude 3.5 Sonnet</option>
-                                    <option value="anthropic/claude-3-opus">Claude 3 Opus</option>
-                                    <option value="openai/gpt-4">GPT-4</option>
-                                    <option value="openai/gpt-3.5-turbo">GPT-3.5 Turbo</option>
-                                    <option value="google/gemini-pro">Gemini Pro</option>
+                                    <option value="">Loading models...</option>
                                 </select>
+                                <span id="new-loadout-model-status" style="color: var(--color-text-dim); font-size: 10px;"></span>
                             </div>
                             <div>
                                 <label style="color: var(--color-text-dim); font-size: 11px;">SYSTEM PROMPT:</label>
-                                <textarea id="new-loadout-prompt" rows="4" placeholder="You are a helpful assistant..." style="width: 100%; padding: 8px; margin-top: 4px; background: var(--color-bg-tertiary); border: var(--border); color: var(--color-text-primary); font-family: var(--font-mono); resize: vertical;"></textarea>
+                                <textarea id="new-loadout-prompt" rows="4" placeholder="You are a helpful assistant... ({{hostname}}, {{distro}} and {{> template}} are filled in)" style="width: 100%; padding: 8px; margin-top: 4px; background: var(--color-bg-tertiary); border: var(--border); color: var(--color-text-primary); font-family: var(--font-mono); resize: vertical;"></textarea>
+                                <select id="new-loadout-template" style="width: 100%; padding: 8px; margin-top: 4px; background: var(--color-bg-tertiary); border: var(--border); color: var(--color-text-primary); font-family: var(--font-mono);">
+                                    <option value="">Use a prompt template...</option>
+                                </select>
                             </div>
                             <div>
                                 <label style="color: var(--color-text-dim); font-size: 11px;">TEMPERATURE:</label>
@@ -97,19 +109,58 @@ async function openLoadoutManager() {
                                 <label style="color: var(--color-text-dim); font-size: 11px;">ENABLED TOOLS:</label>
                                 <div style="display: flex; flex-wrap: wrap; gap: 8px; margin-top: 8px;">
                                     <label style="display: flex; align-items: center; gap: 4px; color: var(--color-text-secondary); font-size: 11px;">
-                                        <input type="checkbox" value="rice" checked> Rice Control
+                                        <input type="checkbox" name="loadout-tool" value="rice" checked> Rice Control
+                                    </label>
+                                    <label style="display: flex; align-items: center; gap: 4px; color: var(--color-text-secondary); font-size: 11px;">
+                                        <input type="checkbox" name="loadout-tool" value="daemon" checked> Daemon Management
+                                    </label>
+                                    <label style="display: flex; align-items: center; gap: 4px; color: var(--color-text-secondary); font-size: 11px;">
+                                        <input type="checkbox" name="loadout-tool" value="cron" checked> Cron Jobs
+                                    </label>
+                                    <label style="display: flex; align-items: center; gap: 4px; color: var(--color-text-secondary); font-size: 11px;">
+                                        <input type="checkbox" name="loadout-tool" value="container" checked> Containers
+                                    </label>
+                                    <label style="display: flex; align-items: center; gap: 4px; color: var(--color-text-secondary); font-size: 11px;">
+                                        <input type="checkbox" name="loadout-tool" value="file" checked> File Operations
+                                    </label>
+                                    <label style="display: flex; align-items: center; gap: 4px; color: var(--color-text-secondary); font-size: 11px;">
+                                        <input type="checkbox" name="loadout-tool" value="operations"> Queue Operations
+                                    </label>
+                                </div>
+                            </div>
+                            <div>
+                                <label style="color: var(--color-text-dim); font-size: 11px;">SYSTEM CONTEXT (SENT WITH EVERY REQUEST):</label>
+                                <div style="display: flex; flex-wrap: wrap; gap: 8px; margin-top: 8px;">
+                                    <label style="display: flex; align-items: center; gap: 4px; color: var(--color-text-secondary); font-size: 11px;">
+                                        <input type="checkbox" name="loadout-context" value="system"> System Info
+                                    </label>
+                                    <label style="display: flex; align-items: center; gap: 4px; color: var(--color-text-secondary); font-size: 11px;">
+                                        <input type="checkbox" name="loadout-context" value="compositor"> Compositor / WM
+                                    </label>
+                                    <label style="display: flex; align-items: center; gap: 4px; color: var(--color-text-secondary); font-size: 11px;">
+                                        <input type="checkbox" name="loadout-context" value="rice"> Rice Components
+                                    </label>
+                                    <label style="display: flex; align-items: center; gap: 4px; color: var(--color-text-secondary); font-size: 11px;">
+                                        <input type="checkbox" name="loadout-context" value="services"> User Services
                                     </label>
                                     <label style="display: flex; align-items: center; gap: 4px; color: var(--color-text-secondary); font-size: 11px;">
-                                        <input type="checkbox" value="daemon" checked> Daemon Management
+                                        <input type="checkbox" name="loadout-context" value="cron"> Crontab
                                     </label>
+                                </div>
+                                <label style="color: var(--color-text-dim); font-size: 11px;">MAX CHARS PER SECTION:</label>
+                                <input type="number" id="new-loadout-context-cap" min="100" max="20000" value="2000" style="width: 100%; padding: 8px; margin-top: 4px; background: var(--color-bg-tertiary); border: var(--border); color: var(--color-text-primary); font-family: var(--font-mono);">
+                            </div>
+                            <div>
+                                <label style="color: var(--color-text-dim); font-size: 11px;">RELEVANT FILES (SEARCHED PER MESSAGE):</label>
+                                <div style="display: flex; flex-wrap: wrap; gap: 8px; margin-top: 8px;">
                                     <label style="display: flex; align-items: center; gap: 4px; color: var(--color-text-secondary); font-size: 11px;">
-                                        <input type="checkbox" value="cron" checked> Cron Jobs
+                                        <input type="checkbox" name="loadout-retrieval" value="rice"> Rice Configs
                                     </label>
                                     <label style="display: flex; align-items: center; gap: 4px; color: var(--color-text-secondary); font-size: 11px;">
-                                        <input type="checkbox" value="container" checked> Containers
+                                        <input type="checkbox" name="loadout-retrieval" value="containers"> Container Snapshots
                                     </label>
                                     <label style="display: flex; align-items: center; gap: 4px; color: var(--color-text-secondary); font-size: 11px;">
-                                        <input type="checkbox" value="file" checked> File Operations
+                                        <input type="checkbox" name="loadout-retrieval" value="units"> Unit Files
                                     </label>
                                 </div>
                             </div>
@@ -119,6 +170,7 @@ async function openLoadoutManager() {
             `,
             [
                 { label: '[CLOSE]', action: closePopup },
+                { label: '[IMPORT BUNDLE]', action: importLoadoutBundle },
                 { label: '[CREATE LOADOUT]', action: createNewLoadout, primary: true }
             ]
         );
@@ -132,6 +184,28 @@ async function openLoadoutManager() {
             });
         }
         
+        // Picking a template references it from the system prompt
+        const templateSelect = document.getElementById('new-loadout-template');
+        const templates = await invoke('list_prompt_templates').catch(() => []);
+        templates.forEach(template => templateSelect.appendChild(new Option(template, template)));
+        templateSelect.addEventListener('change', () => {
+            if (templateSelect.value) {
+                document.getElementById('new-loadout-prompt').value = `{{> ${templateSelect.value}}}`;
+            }
+        });
+        
+        // Fill the model list for the selected provider
+        const providerSelect = document.getElementById('new-loadout-provider');
+        const endpointInput = document.getElementById('new-loadout-endpoint');
+        const refreshModels = () => populateModelSelect(
+            'new-loadout-model',
+            providerSelect.value,
+            endpointInput.value.trim() || null
+        );
+        providerSelect.addEventListener('change', refreshModels);
+        endpointInput.addEventListener('change', refreshModels);
+        refreshModels();
+        
         // Add event listeners to existing loadout items
         attachLoadoutItemListeners();
         
@@ -141,6 +215,46 @@ async function openLoadoutManager() {
     }
 }
 
+// Replace a model <select>'s options with the provider's cached catalog,
+// keeping `selected` available even if the provider no longer lists it
+async function populateModelSelect(selectId, provider, endpoint, selected = null) {
+    const select = document.getElementById(selectId);
+    const status = document.getElementById(`${selectId}-status`);
+    if (!select) return;
+    
+    try {
+        const catalog = await invoke('list_ai_models', { provider, endpoint });
+        const ids = catalog.models.map(model => model.id);
+        
+        select.innerHTML = '';
+        if (selected && !ids.includes(selected)) {
+            select.appendChild(new Option(`${selected} (not listed)`, selected));
+        }
+        catalog.models.forEach(model => {
+            const label = model.supports_tools ? `${model.name || model.id} [tools]` : (model.name || model.id);
+            select.appendChild(new Option(label, model.id));
+        });
+        if (selected) {
+            select.value = selected;
+        }
+        
+        if (status) {
+            status.textContent = catalog.stale ? 'Offline - showing cached model list' : '';
+        }
+    } catch (error) {
+        console.error('[Loadout Manager] Failed to list models:', error);
+        if (status) {
+            status.textContent = `Failed to list models: ${error}`;
+        }
+    }
+}
+
+function logLoadoutWarnings(name, warnings) {
+    (warnings || []).forEach(warning => {
+        logOperation(`Loadout ${name}: ${warning}`, 'warning');
+    });
+}
+
 function createLoadoutItemHTML(name) {
     return `
         <div class="loadout-item" data-loadout="${name}" style="display: flex; justify-content: space-between; align-items: center; padding: 12px; margin-bottom: 8px; background: var(--color-bg-tertiary); border: var(--border); border-left: 3px solid var(--color-accent-dim); cursor: pointer; transition: all 0.2s ease;">
@@ -150,6 +264,7 @@ function createLoadoutItemHTML(name) {
             </div>
             <div style="display: flex; gap: 8px;">
                 <button class="action-btn loadout-edit-btn" data-loadout="${name}" style="font-size: 10px;">[EDIT]</button>
+                <button class="action-btn loadout-export-btn" data-loadout="${name}" style="font-size: 10px;">[EXPORT]</button>
                 <button class="action-btn loadout-delete-btn" data-loadout="${name}" style="font-size: 10px;">[DELETE]</button>
             </div>
         </div>
@@ -177,6 +292,15 @@ function attachLoadoutItemListeners() {
         });
     });
     
+    // Export buttons
+    document.querySelectorAll('.loadout-export-btn').forEach(btn => {
+        btn.addEventListener('click', (e) => {
+            e.stopPropagation();
+            const loadoutName = btn.getAttribute('data-loadout');
+            exportLoadoutBundle(loadoutName);
+        });
+    });
+    
     // Delete buttons
     document.querySelectorAll('.loadout-delete-btn').forEach(btn => {
         btn.addEventListener('click', (e) => {
@@ -193,6 +317,8 @@ function attachLoadoutItemListeners() {
 
 async function createNewLoadout() {
     const name = document.getElementById('new-loadout-name').value.trim();
+    const provider = document.getElementById('new-loadout-provider').value;
+    const endpoint = document.getElementById('new-loadout-endpoint').value.trim();
     const model = document.getElementById('new-loadout-model').value;
     const systemPrompt = document.getElementById('new-loadout-prompt').value.trim();
     const temperature = parseFloat(document.getElementById('new-loadout-temp').value);
@@ -202,26 +328,49 @@ async function createNewLoadout() {
         return;
     }
     
+    if (!model) {
+        alert('Please select a model');
+        return;
+    }
+    
     // Get enabled tools
-    const tools = Array.from(document.querySelectorAll('input[type="checkbox"]:checked'))
+    const tools = Array.from(document.querySelectorAll('input[name="loadout-tool"]:checked'))
+        .map(cb => cb.value);
+    
+    // System context is opt-in; no sections means none is sent
+    const contextSections = Array.from(document.querySelectorAll('input[name="loadout-context"]:checked'))
+        .map(cb => cb.value);
+    const contextMaxChars = parseInt(document.getElementById('new-loadout-context-cap').value, 10) || 2000;
+    
+    // Retrieval is opt-in too
+    const retrievalSources = Array.from(document.querySelectorAll('input[name="loadout-retrieval"]:checked'))
         .map(cb => cb.value);
     
     const loadoutConfig = {
         name,
+        provider,
+        endpoint: endpoint || null,
         model,
         system_prompt: systemPrompt || 'You are a helpful AI assistant for Linux system configuration.',
         temperature,
         max_tokens: 4096,
-        tools_enabled: tools
+        tools_enabled: tools,
+        context: contextSections.length > 0
+            ? { sections: contextSections, max_chars: contextMaxChars }
+            : null,
+        retrieval: retrievalSources.length > 0
+            ? { sources: retrievalSources }
+            : null
     };
     
     try {
-        await invoke('save_ai_loadout', {
+        const warnings = await invoke('save_ai_loadout', {
             name,
-            config: JSON.stringify(loadoutConfig, null, 2)
+            config: loadoutConfig
         });
         
         logOperation(`Created loadout: ${name}`, 'success');
+        logLoadoutWarnings(name, warnings);
         
         // Refresh loadout manager
         closePopup();
@@ -249,6 +398,7 @@ async function activateLoadout(name) {
         });
         
         logOperation(`Activated loadout: ${name}`, 'success');
+        logLoadoutWarnings(name, await invoke('validate_ai_loadout', { name }));
     } catch (error) {
         console.error('[Loadout Manager] Failed to activate loadout:', error);
         logOperation(`Failed to activate loadout: ${error}`, 'error');
@@ -257,8 +407,7 @@ async function activateLoadout(name) {
 
 async function editLoadout(name) {
     try {
-        const configStr = await invoke('load_ai_loadout', { name });
-        const config = JSON.parse(configStr);
+        const config = await invoke('load_ai_loadout', { name });
         
         window.NixDeck.showPopup(
             `EDIT LOADOUT: ${name}`,
@@ -267,12 +416,13 @@ async function editLoadout(name) {
                     <div>
                         <label style="color: var(--color-text-dim); font-size: 11px;">MODEL:</label>
                         <select id="edit-loadout-model" style="width: 100%; padding: 8px; margin-top: 4px; background: var(--color-bg-tertiary); border: var(--border); color: var(--color-text-primary); font-family: var(--font-mono);">
-                            <option value="anthropic/claude-3.5-sonnet" ${config.model === 'anthropic/claude-3.5-sonnet' ? 'selected' : ''}>Claude 3.5 Sonnet</option>
-                            <option value="anthropic/claude-3-opus" ${config.model === 'anthropic/claude-3-opus' ? 'selected' : ''}>Claude 3 Opus</option>
-                            <option value="openai/gpt-4" ${config.model === 'openai/gpt-4' ? 'selected' : ''}>GPT-4</option>
-                            <option value="openai/gpt-3.5-turbo" ${config.model === 'openai/gpt-3.5-turbo' ? 'selected' : ''}>GPT-3.5 Turbo</option>
-                            <option value="google/gemini-pro" ${config.model === 'google/gemini-pro' ? 'selected' : ''}>Gemini Pro</option>
+                            <option value="${config.model}">${config.model}</option>
                         </select>
+                        <span id="edit-loadout-model-status" style="color: var(--color-text-dim); font-size: 10px;"></span>
+                    </div>
+                    <div>
+                        <label style="color: var(--color-text-dim); font-size: 11px;">FALLBACK MODELS (COMMA-SEPARATED, IN ORDER):</label>
+                        <input type="text" id="edit-loadout-fallbacks" value="${(config.fallback_models || []).join(', ')}" placeholder="openai/gpt-4o-mini, meta-llama/llama-3.1-70b-instruct" style="width: 100%; padding: 8px; margin-top: 4px; background: var(--color-bg-tertiary); border: var(--border); color: var(--color-text-primary); font-family: var(--font-mono);">
                     </div>
                     <div>
                         <label style="color: var(--color-text-dim); font-size: 11px;">SYSTEM PROMPT:</label>
@@ -287,10 +437,13 @@ async function editLoadout(name) {
             `,
             [
                 { label: '[CANCEL]', action: () => { closePopup(); openLoadoutManager(); } },
+                { label: '[PREVIEW PROMPT]', action: () => previewSystemPrompt(name) },
                 { label: '[SAVE]', action: () => saveLoadoutEdit(name), primary: true }
             ]
         );
         
+        populateModelSelect('edit-loadout-model', config.provider, config.endpoint || null, config.model);
+        
         // Temperature slider listener
         const tempSlider = document.getElementById('edit-loadout-temp');
         const tempValue = document.getElementById('edit-temp-value');
@@ -305,27 +458,46 @@ async function editLoadout(name) {
     }
 }
 
+// Show the saved loadout's system prompt as it would be sent
+async function previewSystemPrompt(name) {
+    try {
+        const rendered = await invoke('preview_ai_system_prompt', { loadout: name });
+        const missing = rendered.missing.length > 0
+            ? `\n\nUnfilled placeholders: ${rendered.missing.join(', ')}`
+            : '';
+        alert(`${rendered.text}${missing}`);
+    } catch (error) {
+        console.error('[Loadout Manager] Failed to preview prompt:', error);
+        alert(`Failed to preview prompt: ${error}`);
+    }
+}
+
 async function saveLoadoutEdit(name) {
     const model = document.getElementById('edit-loadout-model').value;
+    const fallbackModels = document.getElementById('edit-loadout-fallbacks').value
+        .split(',')
+        .map(m => m.trim())
+        .filter(m => m);
     const systemPrompt = document.getElementById('edit-loadout-prompt').value.trim();
     const temperature = parseFloat(document.getElementById('edit-loadout-temp').value);
     
     try {
         // Load existing config to preserve other fields
-        const existingConfigStr = await invoke('load_ai_loadout', { name });
-        const existingConfig = JSON.parse(existingConfigStr);
+        const existingConfig = await invoke('load_ai_loadout', { name });
         
         // Update fields
         existingConfig.model = model;
+        existingConfig.fallback_models = fallbackModels;
         existingConfig.system_prompt = systemPrompt;
         existingConfig.temperature = temperature;
         
-        await invoke('save_ai_loadout', {
+        const warnings = await invoke('save_ai_loadout', {
             name,
-            config: JSON.stringify(existingConfig, null, 2)
+            config: existingConfig
         });
         
         logOperation(`Updated loadout: ${name}`, 'success');
+        logLoadoutWarnings(name, warnings);
         
         closePopup();
         setTimeout(() => openLoadoutManager(), 100);
@@ -341,16 +513,70 @@ async function deleteLoadout(name) {
     }
     
     try {
-        // TODO: Implement delete_ai_loadout backend command
-        // For now, just log
-        logOperation(`Delete loadout not yet implemented: ${name}`, 'warning');
-        alert('Delete functionality coming soon');
+        await invoke('delete_ai_loadout', { name });
+        logOperation(`Deleted loadout: ${name}`, 'success');
+        
+        // Refresh loadout manager
+        closePopup();
+        setTimeout(() => openLoadoutManager(), 100);
     } catch (error) {
         console.error('[Loadout Manager] Failed to delete loadout:', error);
         alert(`Failed to delete loadout: ${error}`);
     }
 }
 
+async function exportLoadoutBundle(name) {
+    const path = prompt('Export bundle to:', `${name}.nd2133-bundle.tar.gz`);
+    if (!path) return;
+    
+    try {
+        await invoke('export_ai_loadout_bundle', { loadouts: [name], path });
+        logOperation(`Exported loadout bundle: ${path}`, 'success');
+    } catch (error) {
+        console.error('[Loadout Manager] Failed to export bundle:', error);
+        alert(`Failed to export bundle: ${error}`);
+    }
+}
+
+// Bundle items that already exist are overwritten, renamed or skipped,
+// as the user chooses for each
+async function importLoadoutBundle() {
+    const path = prompt('Import bundle from:');
+    if (!path) return;
+    
+    try {
+        const items = await invoke('inspect_ai_loadout_bundle', { path });
+        const resolutions = {};
+        
+        for (const item of items.filter(item => item.exists)) {
+            const key = `${item.kind}:${item.name}`;
+            const answer = prompt(
+                `${item.kind} "${item.name}" already exists.\n\n` +
+                'Type a new name to import it as, "overwrite" to replace it, or leave empty to skip:'
+            );
+            if (answer === null) return;
+            
+            const choice = answer.trim();
+            if (!choice) {
+                resolutions[key] = { action: 'skip' };
+            } else if (choice === 'overwrite') {
+                resolutions[key] = { action: 'overwrite' };
+            } else {
+                resolutions[key] = { action: 'rename', name: choice };
+            }
+        }
+        
+        const installed = await invoke('import_ai_loadout_bundle', { path, resolutions });
+        installed.forEach(item => logOperation(`Imported ${item.kind}: ${item.name}`, 'success'));
+        
+        closePopup();
+        setTimeout(() => openLoadoutManager(), 100);
+    } catch (error) {
+        console.error('[Loadout Manager] Failed to import bundle:', error);
+        alert(`Failed to import bundle: ${error}`);
+    }
+}
+
 // ============================================================================
 // GENERIC POPUP UTILITIES
 // ============================================================================
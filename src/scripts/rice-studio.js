@@ -3,6 +3,7 @@
 
 import { invoke } from '@tauri-apps/api/tauri';
 import { logOperation } from './main.js';
+import { AIConsoleState } from './ai-console.js';
 
 // ============================================================================
 // STATE
@@ -12,7 +13,13 @@ const RiceStudioState = {
     currentComponent: 'waybar',
     currentConfig: '',
     originalConfig: '',
-    hasUnsavedChanges: false
+    hasUnsavedChanges: false,
+    // The main config's path, and the included file in the editor (null
+    // while it holds the main config)
+    mainPath: '',
+    currentFile: null,
+    // Edited included files by path, applied together with the main config
+    files: {}
 };
 
 // ============================================================================
@@ -24,19 +31,18 @@ document.addEventListener('DOMContentLoaded', () => {
 });
 
 function initializeRiceStudio() {
-    const componentButtons = document.querySelectorAll('.component-btn');
     const loadConfigBtn = document.getElementById('load-config-btn');
     const previewConfigBtn = document.getElementById('preview-config-btn');
     const applyConfigBtn = document.getElementById('apply-config-btn');
+    const setValueBtn = document.getElementById('set-value-btn');
+    const filesBtn = document.getElementById('files-btn');
+    const backupsBtn = document.getElementById('backups-btn');
+    const aiAssistBtn = document.getElementById('ai-assist-btn');
     const configEditor = document.getElementById('config-editor');
     
-    // Component selector
-    componentButtons.forEach(btn => {
-        btn.addEventListener('click', () => {
-            const component = btn.getAttribute('data-component');
-            selectComponent(component);
-        });
-    });
+    // Component selector; the built-in buttons stand until the registry loads
+    bindComponentButtons();
+    loadComponentList();
     
     // Action buttons
     if (loadConfigBtn) {
@@ -51,11 +57,31 @@ function initializeRiceStudio() {
         applyConfigBtn.addEventListener('click', applyConfig);
     }
     
+    if (setValueBtn) {
+        setValueBtn.addEventListener('click', setSingleValue);
+    }
+    
+    if (filesBtn) {
+        filesBtn.addEventListener('click', showFiles);
+    }
+    
+    if (backupsBtn) {
+        backupsBtn.addEventListener('click', showBackups);
+    }
+    
+    if (aiAssistBtn) {
+        aiAssistBtn.addEventListener('click', requestAIProposal);
+    }
+    
     // Editor change tracking
     if (configEditor) {
         configEditor.addEventListener('input', () => {
             RiceStudioState.hasUnsavedChanges = true;
-            RiceStudioState.currentConfig = configEditor.value;
+            if (RiceStudioState.currentFile) {
+                RiceStudioState.files[RiceStudioState.currentFile] = configEditor.value;
+            } else {
+                RiceStudioState.currentConfig = configEditor.value;
+            }
         });
     }
     
@@ -66,6 +92,37 @@ function initializeRiceStudio() {
 // COMPONENT SELECTION
 // ============================================================================
 
+function bindComponentButtons() {
+    document.querySelectorAll('.component-btn').forEach(btn => {
+        btn.addEventListener('click', () => {
+            const component = btn.getAttribute('data-component');
+            selectComponent(component);
+        });
+    });
+}
+
+// Rebuild the selector from the component registry, user components included
+async function loadComponentList() {
+    const selector = document.querySelector('.component-selector');
+    if (!selector) return;
+    
+    try {
+        const registry = await invoke('list_rice_components');
+        
+        selector.innerHTML = registry.components.map(component => {
+            const active = component.name === RiceStudioState.currentComponent ? ' active' : '';
+            return `<button class="component-btn${active}" data-component="${component.name}">[${component.name.toUpperCase()}]</button>`;
+        }).join('');
+        bindComponentButtons();
+        
+        registry.problems.forEach(problem => {
+            logOperation(`Skipped component file ${problem}`, 'warning');
+        });
+    } catch (error) {
+        console.error('[Rice Studio] Failed to load components:', error);
+    }
+}
+
 function selectComponent(component) {
     if (RiceStudioState.hasUnsavedChanges) {
         if (!confirm('You have unsaved changes. Switch component anyway?')) {
@@ -76,6 +133,8 @@ function selectComponent(component) {
     // Update state
     RiceStudioState.currentComponent = component;
     RiceStudioState.hasUnsavedChanges = false;
+    RiceStudioState.currentFile = null;
+    RiceStudioState.files = {};
     
     // Update UI
     document.querySelectorAll('.component-btn').forEach(btn => {
@@ -111,16 +170,26 @@ async function loadCurrentConfig() {
     try {
         editor.placeholder = 'Loading configuration...';
         
+        const resolution = await invoke('resolve_rice_config', { component });
         const config = await invoke('get_rice_config', { component });
         
         RiceStudioState.originalConfig = config;
         RiceStudioState.currentConfig = config;
         RiceStudioState.hasUnsavedChanges = false;
+        RiceStudioState.mainPath = resolution.active || resolution.target;
+        RiceStudioState.currentFile = null;
+        RiceStudioState.files = {};
         
         editor.value = config;
         editor.placeholder = '';
+        editor.readOnly = false;
+        document.getElementById('current-component').textContent = component;
         
-        logOperation(`Loaded ${component} configuration`, 'success');
+        logOperation(`Loaded ${component} configuration from ${resolution.active}`, 'success');
+        // A system-wide default is read here but saved as the user's own copy
+        if (resolution.active !== resolution.target) {
+            logOperation(`Changes will be saved to ${resolution.target}`, 'info');
+        }
     } catch (error) {
         console.error('[Rice Studio] Failed to load config:', error);
         editor.placeholder = `Error loading configuration: ${error}`;
@@ -128,6 +197,45 @@ async function loadCurrentConfig() {
     }
 }
 
+// Change one value (a colour, a font) in place; the backend keeps the rest of
+// the file, comments included, as it was
+async function setSingleValue() {
+    const component = RiceStudioState.currentComponent;
+    
+    if (RiceStudioState.hasUnsavedChanges &&
+        !confirm('Setting a value reloads the file and discards unsaved edits. Continue?')) {
+        return;
+    }
+    
+    const keyPath = prompt(`Key path in ${component} config (e.g. bar/main.background, font.normal.family):`);
+    if (!keyPath) return;
+    
+    try {
+        const current = await invoke('get_rice_value', { component, keyPath });
+        const input = prompt(`${keyPath} is currently ${JSON.stringify(current)}. New value:`, String(current));
+        if (input === null) return;
+        
+        // Numbers and booleans keep their type; anything else is a string
+        let value = input;
+        try {
+            const parsed = JSON.parse(input);
+            if (typeof parsed === 'number' || typeof parsed === 'boolean') {
+                value = parsed;
+            }
+        } catch (_) {}
+        
+        await invoke('set_rice_value', { component, keyPath, value });
+        logOperation(`Set ${component} ${keyPath} = ${JSON.stringify(value)}`, 'success');
+        
+        RiceStudioState.hasUnsavedChanges = false;
+        await loadCurrentConfig();
+    } catch (error) {
+        console.error('[Rice Studio] Set value error:', error);
+        logOperation(`Failed to set ${keyPath}: ${error}`, 'error');
+        alert(`Failed to set ${keyPath}: ${error}`);
+    }
+}
+
 // ============================================================================
 // CONFIGURATION PREVIEW
 // ============================================================================
@@ -155,10 +263,7 @@ async function previewConfig() {
                 </div>
                 <div class="info-item" style="margin-top: 16px;">
                     <strong>Changes:</strong>
-                    <pre style="background: var(--color-bg-tertiary); padding: 8px; margin-top: 8px; max-height: 400px; overflow-y: auto; font-size: 11px;">${escapeHtml(diff)}</pre>
-                </div>
-                <div class="info-item" style="margin-top: 16px; color: var(--color-warning); font-size: 11px;">
-                    Warning: This is a simplified preview. Actual changes may vary.
+                    ${renderDiff(diff)}
                 </div>
             `,
             [
@@ -174,14 +279,202 @@ async function previewConfig() {
     }
 }
 
+// ============================================================================
+// INCLUDED FILES
+// ============================================================================
+
+// The main config and everything it includes, indented under its includer
+async function showFiles() {
+    const component = RiceStudioState.currentComponent;
+    
+    try {
+        const tree = await invoke('get_rice_config_tree', { component });
+        
+        const depth = (file) => {
+            let level = 0;
+            let parent = file.parent;
+            while (parent) {
+                level++;
+                parent = tree.files.find(other => other.path === parent)?.parent;
+            }
+            return level;
+        };
+        
+        const rows = tree.files.map((file, index) => {
+            const name = file.path.split('/').pop();
+            const notes = [
+                file.role,
+                file.content === null ? 'missing' : '',
+                file.writable ? '' : 'read-only',
+                file.path in RiceStudioState.files ? 'edited' : ''
+            ].filter(Boolean).join(' &middot; ');
+            
+            return `
+                <div class="info-item" style="display: flex; justify-content: space-between; align-items: center; padding-left: ${depth(file) * 16}px;">
+                    <div>
+                        <strong>${escapeHtml(name)}</strong>
+                        <span style="color: var(--color-text-dim); font-size: 10px;">${notes}</span>
+                        <div style="color: var(--color-text-dim); font-size: 10px;">${escapeHtml(file.path)}</div>
+                    </div>
+                    <button class="action-btn file-open-btn" data-file="${index}" style="font-size: 10px;">[OPEN]</button>
+                </div>
+            `;
+        }).join('');
+        
+        window.NixDeck.showPopup(
+            `Files: ${component}`,
+            rows,
+            [
+                { label: '[CLOSE]', action: window.NixDeck.closePopup }
+            ]
+        );
+        
+        document.querySelectorAll('.file-open-btn').forEach(btn => {
+            btn.addEventListener('click', () => openFile(tree.files[Number(btn.getAttribute('data-file'))]));
+        });
+    } catch (error) {
+        console.error('[Rice Studio] File tree error:', error);
+        logOperation(`Failed to list ${component} files: ${error}`, 'error');
+        alert(`Failed to list files: ${error}`);
+    }
+}
+
+// Put one file of the tree in the editor; edits to each are kept until applied
+function openFile(file) {
+    const component = RiceStudioState.currentComponent;
+    const editor = document.getElementById('config-editor');
+    if (!editor) return;
+    
+    window.NixDeck.closePopup();
+    
+    if (file.role === 'main') {
+        if (!RiceStudioState.mainPath) {
+            RiceStudioState.mainPath = file.path;
+            RiceStudioState.originalConfig = file.content ?? '';
+            RiceStudioState.currentConfig = file.content ?? '';
+        }
+        RiceStudioState.currentFile = null;
+        editor.value = RiceStudioState.currentConfig;
+        editor.readOnly = false;
+        document.getElementById('current-component').textContent = component;
+    } else {
+        RiceStudioState.currentFile = file.path;
+        editor.value = RiceStudioState.files[file.path] ?? file.content ?? '';
+        editor.readOnly = !file.writable;
+        document.getElementById('current-component').textContent = `${component}: ${file.path.split('/').pop()}`;
+    }
+    
+    logOperation(`Editing ${file.path}${file.writable ? '' : ' (read-only)'}`, 'info');
+}
+
+// ============================================================================
+// BACKUP HISTORY
+// ============================================================================
+
+async function showBackups() {
+    const component = RiceStudioState.currentComponent;
+    
+    try {
+        const backups = await invoke('list_rice_backups', { component });
+        
+        const rows = backups.length === 0
+            ? '<div class="info-item" style="color: var(--color-text-dim);">No backups yet. One is kept each time the config is written.</div>'
+            : backups.map(backup => `
+                <div class="info-item" style="display: flex; justify-content: space-between; align-items: center;">
+                    <div>
+                        <strong>${new Date(backup.created).toLocaleString()}</strong>
+                        <span style="color: var(--color-text-dim); font-size: 10px;">${backup.id} &middot; ${backup.files.length} file${backup.files.length === 1 ? '' : 's'} &middot; ${backup.size} bytes</span>
+                        <div style="color: var(--color-text-dim); font-size: 10px;">${backup.files.map(file => escapeHtml(file.path)).join('<br>')}</div>
+                    </div>
+                    <div style="display: flex; gap: 8px;">
+                        <button class="action-btn backup-diff-btn" data-backup="${backup.id}" style="font-size: 10px;">[DIFF]</button>
+                        <button class="action-btn backup-restore-btn" data-backup="${backup.id}" style="font-size: 10px;">[RESTORE]</button>
+                    </div>
+                </div>
+            `).join('');
+        
+        window.NixDeck.showPopup(
+            `Backups: ${component}`,
+            rows,
+            [
+                { label: '[CLOSE]', action: window.NixDeck.closePopup }
+            ]
+        );
+        
+        document.querySelectorAll('.backup-diff-btn').forEach(btn => {
+            btn.addEventListener('click', () => showBackupDiff(component, btn.getAttribute('data-backup')));
+        });
+        document.querySelectorAll('.backup-restore-btn').forEach(btn => {
+            btn.addEventListener('click', () => restoreBackup(component, btn.getAttribute('data-backup')));
+        });
+    } catch (error) {
+        console.error('[Rice Studio] Backup list error:', error);
+        logOperation(`Failed to list ${component} backups: ${error}`, 'error');
+        alert(`Failed to list backups: ${error}`);
+    }
+}
+
+async function showBackupDiff(component, id) {
+    try {
+        const diffs = await invoke('diff_rice_backup', { component, id });
+        
+        window.NixDeck.showPopup(
+            `Backup ${id}`,
+            `
+                <div class="info-item">
+                    <strong>Restoring would change:</strong>
+                    ${diffs.map(renderDiff).join('')}
+                </div>
+            `,
+            [
+                { label: '[BACK]', action: showBackups },
+                { label: '[RESTORE]', action: () => restoreBackup(component, id), primary: true }
+            ]
+        );
+    } catch (error) {
+        console.error('[Rice Studio] Backup diff error:', error);
+        alert(`Failed to diff backup: ${error}`);
+    }
+}
+
+async function restoreBackup(component, id) {
+    if (!confirm(`Restore ${component} config from backup ${id}?\n\nEvery file in it is put back where it was taken from. The current files are backed up first.`)) {
+        return;
+    }
+    
+    try {
+        await invoke('restore_rice_backup', { component, id });
+        logOperation(`Restored ${component} config from backup ${id}`, 'success');
+        window.NixDeck.closePopup();
+        
+        if (RiceStudioState.currentComponent === component) {
+            RiceStudioState.hasUnsavedChanges = false;
+            await loadCurrentConfig();
+        }
+    } catch (error) {
+        console.error('[Rice Studio] Restore error:', error);
+        logOperation(`Failed to restore ${component} backup: ${error}`, 'error');
+        alert(`Failed to restore backup: ${error}`);
+    }
+}
+
 // ============================================================================
 // CONFIGURATION APPLICATION
 // ============================================================================
 
+function formatDiagnostic(diagnostic) {
+    const where = diagnostic.line ? `line ${diagnostic.line} ` : '';
+    return `${where}(${diagnostic.source}): ${diagnostic.message}`;
+}
+
 async function applyConfig() {
     const component = RiceStudioState.currentComponent;
     const config = RiceStudioState.currentConfig;
     
+    if (Object.keys(RiceStudioState.files).length > 0) {
+        return applyFiles();
+    }
+    
     if (!config) {
         alert('No configuration to apply. Load a config first.');
         return;
@@ -196,7 +489,7 @@ async function applyConfig() {
     const confirmed = confirm(
         `Apply configuration to ${component}?\n\n` +
         'This will:\n' +
-        '1. Create a backup of the current config\n' +
+        '1. Validate it and keep a backup of the current config\n' +
         '2. Write the new configuration\n' +
         '3. You may need to reload the component\n\n' +
         'Continue?'
@@ -205,6 +498,22 @@ async function applyConfig() {
     if (!confirmed) return;
     
     try {
+        // Validate before anything is written; errors need an explicit override
+        const validation = await invoke('validate_rice_config', { component, config });
+        validation.diagnostics.forEach(diagnostic => {
+            logOperation(`${component}: ${formatDiagnostic(diagnostic)}`, diagnostic.severity === 'error' ? 'error' : 'warning');
+        });
+        
+        let force = false;
+        if (!validation.valid) {
+            const errors = validation.diagnostics
+                .filter(diagnostic => diagnostic.severity === 'error')
+                .map(formatDiagnostic)
+                .join('\n');
+            force = confirm(`The new ${component} config has errors:\n\n${errors}\n\nApply it anyway?`);
+            if (!force) return;
+        }
+        
         // Create snapshot first
         const snapshotName = `${component}-${Date.now()}`;
         await invoke('create_snapshot', { name: snapshotName });
@@ -213,7 +522,8 @@ async function applyConfig() {
         // Apply configuration
         await invoke('apply_rice_config', { 
             component, 
-            config 
+            config,
+            force
         });
         
         RiceStudioState.originalConfig = config;
@@ -221,89 +531,250 @@ async function applyConfig() {
         
         logOperation(`Applied ${component} configuration`, 'success');
         
-        // Success dialog
+        showApplied(component, snapshotName);
+    } catch (error) {
+        console.error('[Rice Studio] Apply error:', error);
+        logOperation(`Failed to apply ${component} config: ${error}`, 'error');
+        alert(`Failed to apply configuration: ${error}`);
+    }
+}
+
+// Edited included files, with the main config when it changed too, go in as
+// one change: all are validated before any is written, and all are written
+// or none
+async function applyFiles() {
+    const component = RiceStudioState.currentComponent;
+    
+    const files = Object.entries(RiceStudioState.files).map(([path, content]) => ({ path, content }));
+    if (RiceStudioState.mainPath && RiceStudioState.currentConfig !== RiceStudioState.originalConfig) {
+        files.unshift({ path: RiceStudioState.mainPath, content: RiceStudioState.currentConfig });
+    }
+    
+    const confirmed = confirm(
+        `Apply ${files.length} file(s) to ${component}?\n\n` +
+        files.map(file => `  ${file.path}`).join('\n') + '\n\n' +
+        'They are validated and backed up first, then written together.\n\n' +
+        'Continue?'
+    );
+    
+    if (!confirmed) return;
+    
+    try {
+        const snapshotName = `${component}-${Date.now()}`;
+        await invoke('create_snapshot', { name: snapshotName });
+        logOperation(`Created snapshot: ${snapshotName}`, 'success');
+        
+        // Validation errors need an explicit override
+        try {
+            await invoke('apply_rice_files', { component, files });
+        } catch (error) {
+            if (!String(error).includes('failed validation') ||
+                !confirm(`${error}\n\nApply anyway?`)) {
+                throw error;
+            }
+            await invoke('apply_rice_files', { component, files, force: true });
+        }
+        
+        RiceStudioState.originalConfig = RiceStudioState.currentConfig;
+        RiceStudioState.files = {};
+        RiceStudioState.hasUnsavedChanges = false;
+        
+        logOperation(`Applied ${files.length} ${component} file(s)`, 'success');
+        showApplied(component, snapshotName);
+    } catch (error) {
+        console.error('[Rice Studio] Apply error:', error);
+        logOperation(`Failed to apply ${component} files: ${error}`, 'error');
+        alert(`Failed to apply files: ${error}`);
+    }
+}
+
+function showApplied(component, snapshotName) {
+    window.NixDeck.showPopup(
+        'Configuration Applied',
+        `
+            <div class="info-item">
+                <strong>Component:</strong> ${component}
+            </div>
+            <div class="info-item" style="margin-top: 16px;">
+                <strong>Status:</strong> <span style="color: var(--color-success);">Successfully applied</span>
+            </div>
+            <div class="info-item" style="margin-top: 16px;">
+                <strong>Backup:</strong> ${snapshotName}
+            </div>
+            <div class="info-item" style="margin-top: 16px; color: var(--color-text-dim); font-size: 11px;">
+                You may need to reload ${component} for changes to take effect.
+            </div>
+        `,
+        [
+            { label: '[CLOSE]', action: window.NixDeck.closePopup },
+            { label: '[RELOAD COMPONENT]', action: () => reloadComponent(component), primary: true }
+        ]
+    );
+}
+
+// ============================================================================
+// COMPONENT RELOADING
+// ============================================================================
+
+async function reloadComponent(component) {
+    window.NixDeck.closePopup();
+    
+    try {
+        logOperation(`Reloading ${component}...`, 'info');
+        const report = await invoke('reload_rice_component', { component });
+        
+        if (report.healthy) {
+            logOperation(`Reloaded ${component} (${report.strategy}): ${report.message}`, 'success');
+        } else {
+            logOperation(`${component} reload (${report.strategy}) needs attention: ${report.message}`, 'warning');
+        }
+        
         window.NixDeck.showPopup(
-            'Configuration Applied',
+            `Reload: ${component}`,
             `
                 <div class="info-item">
-                    <strong>Component:</strong> ${component}
+                    <strong>Strategy:</strong> ${report.strategy}
                 </div>
                 <div class="info-item" style="margin-top: 16px;">
-                    <strong>Status:</strong> <span style="color: var(--color-success);">Successfully applied</span>
+                    <strong>Status:</strong>
+                    <span style="color: var(${report.healthy ? '--color-success' : '--color-error'});">
+                        ${report.healthy ? 'Healthy' : 'Not healthy'}
+                    </span>
                 </div>
                 <div class="info-item" style="margin-top: 16px;">
-                    <strong>Backup:</strong> ${snapshotName}
+                    <strong>Running:</strong> ${report.pids.length ? report.pids.map(pid => `pid ${pid}`).join(', ') : 'no instances'}
                 </div>
                 <div class="info-item" style="margin-top: 16px; color: var(--color-text-dim); font-size: 11px;">
-                    You may need to reload ${component} for changes to take effect.
+                    ${report.message}
                 </div>
             `,
             [
-                { label: '[CLOSE]', action: window.NixDeck.closePopup },
-                { label: '[RELOAD COMPONENT]', action: () => reloadComponent(component), primary: true }
+                { label: '[CLOSE]', action: window.NixDeck.closePopup }
             ]
         );
     } catch (error) {
-        console.error('[Rice Studio] Apply error:', error);
-        logOperation(`Failed to apply ${component} config: ${error}`, 'error');
-        alert(`Failed to apply configuration: ${error}`);
+        console.error('[Rice Studio] Reload error:', error);
+        logOperation(`Failed to reload ${component}: ${error}`, 'error');
+        alert(`Failed to reload ${component}: ${error}`);
     }
 }
 
 // ============================================================================
-// COMPONENT RELOADING
+// AI ASSIST
 // ============================================================================
 
-async function reloadComponent(component) {
-    window.NixDeck.closePopup();
-    
-    const reloadCommands = {
-        'waybar': 'killall waybar && waybar &',
-        'polybar': 'killall polybar && polybar &',
-        'eww': 'eww reload',
-        'conky': 'killall conky && conky &',
-        'picom': 'killall picom && picom &',
-        'dunst': 'killall dunst && dunst &',
-        'kitty': 'echo "Restart Kitty terminal manually"',
-        'alacritty': 'echo "Restart Alacritty terminal manually"',
-        'rofi': 'echo "Rofi will use new config on next launch"'
-    };
-    
-    const command = reloadCommands[component];
+async function requestAIProposal() {
+    const component = RiceStudioState.currentComponent;
+    const request = prompt(`Describe the change you want to ${component}:`, '');
     
-    if (!command) {
-        alert(`No reload command defined for ${component}`);
-        return;
-    }
+    if (!request || !request.trim()) return;
     
     try {
-        const result = await invoke('execute_command', { command });
+        logOperation(`Requesting AI proposal for ${component}...`, 'info');
         
-        if (result.success) {
-            logOperation(`Reloaded ${component}`, 'success');
-            alert(`${component} reloaded successfully`);
-        } else {
-            logOperation(`Failed to reload ${component}: ${result.stderr}`, 'error');
-            alert(`Reload command completed but may have issues:\n${result.stderr}`);
-        }
+        const proposal = await invoke('propose_rice_config', {
+            component,
+            request: request.trim(),
+            loadout: AIConsoleState.activeLoadout
+        });
+        
+        showProposal(proposal);
+        logOperation(`Received AI proposal for ${component}`, 'success');
     } catch (error) {
-        console.error('[Rice Studio] Reload error:', error);
-        logOperation(`Failed to reload ${component}: ${error}`, 'error');
-        alert(`Failed to reload ${component}: ${error}`);
+        console.error('[Rice Studio] AI proposal error:', error);
+        logOperation(`AI proposal failed: ${error}`, 'error');
+        alert(`AI proposal failed: ${error}`);
     }
 }
 
-// ============================================================================
-// AI ASSIST
-// ============================================================================
+// Show a proposal for review; accepting only loads it into the editor, so
+// it still goes through the normal [APPLY] confirmation and backup
+function showProposal(proposal) {
+    window.NixDeck.showPopup(
+        `AI Proposal: ${proposal.component}`,
+        `
+            <div class="info-item">
+                <strong>Rationale:</strong>
+                <div style="margin-top: 8px; white-space: pre-wrap;">${escapeHtml(proposal.rationale || '(none given)')}</div>
+            </div>
+            <div class="info-item" style="margin-top: 16px;">
+                <strong>Changes:</strong>
+                ${renderDiff(proposal.diff)}
+            </div>
+        `,
+        [
+            { label: '[DISCARD]', action: window.NixDeck.closePopup },
+            { label: '[LOAD INTO EDITOR]', action: () => acceptProposal(proposal), primary: true }
+        ]
+    );
+}
 
-// TODO: Implement AI-assisted rice generation
-// This would integrate with the AI console to generate configurations
+function acceptProposal(proposal) {
+    window.NixDeck.closePopup();
+    
+    if (RiceStudioState.currentComponent !== proposal.component) {
+        selectComponent(proposal.component);
+    }
+    
+    const editor = document.getElementById('config-editor');
+    if (editor) {
+        editor.value = proposal.content;
+        editor.readOnly = false;
+    }
+    RiceStudioState.currentFile = null;
+    RiceStudioState.currentConfig = proposal.content;
+    RiceStudioState.hasUnsavedChanges = true;
+    
+    logOperation(`Loaded AI proposal for ${proposal.component} (${proposal.model}) into editor`, 'info');
+}
 
 // ============================================================================
 // UTILITIES
 // ============================================================================
 
+// Render a structured diff from the backend: a summary line, then each hunk
+// with changed words highlighted inside replaced lines
+function renderDiff(diff) {
+    if (!diff || diff.hunks.length === 0) {
+        return '<div style="margin-top: 8px; color: var(--color-text-dim);">(no changes)</div>';
+    }
+    
+    const colors = {
+        added: 'var(--color-success)',
+        removed: 'var(--color-error)',
+        context: 'var(--color-text-secondary)'
+    };
+    const markers = { added: '+', removed: '-', context: ' ' };
+    
+    const renderLine = (line) => {
+        const text = line.segments
+            ? line.segments
+                .map(seg => seg.changed
+                    ? `<span style="background: var(--color-bg-elevated); text-decoration: underline;">${escapeHtml(seg.text)}</span>`
+                    : escapeHtml(seg.text))
+                .join('')
+            : escapeHtml(line.text);
+        const eof = line.no_newline
+            ? '<div style="color: var(--color-text-dim);">\\ No newline at end of file</div>'
+            : '';
+        return `<div style="color: ${colors[line.kind]};">${markers[line.kind]}${text}</div>${eof}`;
+    };
+    
+    const hunks = diff.hunks.map(hunk => {
+        const note = hunk.whitespace_only ? ' (whitespace only)' : '';
+        const header = `@@ -${hunk.old_start},${hunk.old_count} +${hunk.new_start},${hunk.new_count} @@${note}`;
+        return `<div style="color: var(--color-text-accent);">${header}</div>${hunk.lines.map(renderLine).join('')}`;
+    }).join('');
+    
+    const summary = `${diff.new_name}: +${diff.added} -${diff.removed}` +
+        (diff.whitespace_only ? ' (whitespace only)' : '');
+    
+    return `
+        <div style="margin-top: 8px; font-size: 11px; color: var(--color-text-dim);">${escapeHtml(summary)}</div>
+        <pre style="background: var(--color-bg-tertiary); padding: 8px; margin-top: 8px; max-height: 400px; overflow-y: auto; font-size: 11px;">${hunks}</pre>
+    `;
+}
+
 function escapeHtml(text) {
     const div = document.createElement('div');
     div.textContent = text;
@@ -318,5 +789,6 @@ export {
     RiceStudioState,
     selectComponent,
     loadCurrentConfig,
-    applyConfig
+    applyConfig,
+    renderDiff
 };